@@ -0,0 +1,59 @@
+#ifndef QUADTREE_H
+#define QUADTREE_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef struct QuadtreeHandle QuadtreeHandle;
+
+/**
+ * Creates a tree, or returns null when the boundary has no area. The tree
+ * must be released with `quadtree_free`.
+ */
+struct QuadtreeHandle *quadtree_new(double x, double y, double width, double height);
+
+/**
+ * # Safety
+ *
+ * `tree` must be null or a handle returned by `quadtree_new` that has not
+ * been freed yet.
+ */
+void quadtree_free(struct QuadtreeHandle *tree);
+
+/**
+ * Stores `user` at (`x`, `y`) and returns whether the point was inside the
+ * tree boundary.
+ *
+ * # Safety
+ *
+ * `tree` must be a live handle returned by `quadtree_new`.
+ */
+bool quadtree_insert(struct QuadtreeHandle *tree, double x, double y, uintptr_t user);
+
+/**
+ * Writes the user values inside the range into `out`, up to `capacity` of
+ * them, and returns the total number of matches so callers can retry with
+ * a larger buffer.
+ *
+ * # Safety
+ *
+ * `tree` must be a live handle returned by `quadtree_new` and `out` must be
+ * valid for writing `capacity` values.
+ */
+uintptr_t quadtree_query(const struct QuadtreeHandle *tree,
+                         double x,
+                         double y,
+                         double width,
+                         double height,
+                         uintptr_t *out,
+                         uintptr_t capacity);
+
+/**
+ * # Safety
+ *
+ * `tree` must be a live handle returned by `quadtree_new`.
+ */
+uintptr_t quadtree_len(const struct QuadtreeHandle *tree);
+
+#endif  /* QUADTREE_H */
@@ -0,0 +1,475 @@
+use std::collections::BinaryHeap;
+
+use crate::nearest::Entry;
+use crate::{Layered, Options, Point, Position, Priority, Quadtree, Rectangle, Weighted};
+
+/// A summary of items that can be merged: `combine` must be associative and
+/// `empty` its identity, so that node summaries can be built from the
+/// summaries of their children.
+pub trait Aggregate<T> {
+    type Value: Clone;
+
+    fn empty(&self) -> Self::Value;
+
+    fn single(&self, item: &T) -> Self::Value;
+
+    fn combine(&self, a: &Self::Value, b: &Self::Value) -> Self::Value;
+}
+
+/// Counts items.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Count;
+
+/// Sums a value extracted from each item.
+#[derive(Debug, Clone, Copy)]
+pub struct Sum<F>(pub F);
+
+/// The smallest value extracted from an item, or `None` without items.
+#[derive(Debug, Clone, Copy)]
+pub struct Min<F>(pub F);
+
+/// The largest value extracted from an item, or `None` without items.
+#[derive(Debug, Clone, Copy)]
+pub struct Max<F>(pub F);
+
+/// The union of the layer masks of the items. Kept per node by an
+/// `AggregateQuadtree`, it lets `query_layers` skip whole subtrees.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Layers;
+
+/// The total `Weighted` weight of the items along with their weighted
+/// coordinate sums, from which the weighted centroid follows.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WeightedCentroid;
+
+/// The value kept by `WeightedCentroid`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WeightSums {
+    pub total: f64,
+    pub sum_x: f64,
+    pub sum_y: f64,
+}
+
+impl WeightSums {
+    /// The weighted centroid, or `None` when the total weight is zero.
+    pub fn centroid(&self) -> Option<Point> {
+        if self.total == 0.0 {
+            return None;
+        }
+        Some(Point::new(self.sum_x / self.total, self.sum_y / self.total))
+    }
+}
+
+impl<T> Aggregate<T> for Count {
+    type Value = usize;
+
+    fn empty(&self) -> usize {
+        0
+    }
+
+    fn single(&self, _item: &T) -> usize {
+        1
+    }
+
+    fn combine(&self, a: &usize, b: &usize) -> usize {
+        a + b
+    }
+}
+
+impl<T, F: Fn(&T) -> f64> Aggregate<T> for Sum<F> {
+    type Value = f64;
+
+    fn empty(&self) -> f64 {
+        0.0
+    }
+
+    fn single(&self, item: &T) -> f64 {
+        (self.0)(item)
+    }
+
+    fn combine(&self, a: &f64, b: &f64) -> f64 {
+        a + b
+    }
+}
+
+impl<T, F: Fn(&T) -> f64> Aggregate<T> for Min<F> {
+    type Value = Option<f64>;
+
+    fn empty(&self) -> Option<f64> {
+        None
+    }
+
+    fn single(&self, item: &T) -> Option<f64> {
+        Some((self.0)(item))
+    }
+
+    fn combine(&self, a: &Option<f64>, b: &Option<f64>) -> Option<f64> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(*b)),
+            _ => a.or(*b),
+        }
+    }
+}
+
+impl<T, F: Fn(&T) -> f64> Aggregate<T> for Max<F> {
+    type Value = Option<f64>;
+
+    fn empty(&self) -> Option<f64> {
+        None
+    }
+
+    fn single(&self, item: &T) -> Option<f64> {
+        Some((self.0)(item))
+    }
+
+    fn combine(&self, a: &Option<f64>, b: &Option<f64>) -> Option<f64> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(a.max(*b)),
+            _ => a.or(*b),
+        }
+    }
+}
+
+impl<T: Layered> Aggregate<T> for Layers {
+    type Value = u32;
+
+    fn empty(&self) -> u32 {
+        0
+    }
+
+    fn single(&self, item: &T) -> u32 {
+        item.layers()
+    }
+
+    fn combine(&self, a: &u32, b: &u32) -> u32 {
+        a | b
+    }
+}
+
+impl<T: Position + Weighted> Aggregate<T> for WeightedCentroid {
+    type Value = WeightSums;
+
+    fn empty(&self) -> WeightSums {
+        WeightSums::default()
+    }
+
+    fn single(&self, item: &T) -> WeightSums {
+        let (point, weight) = (item.position(), item.weight());
+        WeightSums {
+            total: weight,
+            sum_x: point.x * weight,
+            sum_y: point.y * weight,
+        }
+    }
+
+    fn combine(&self, a: &WeightSums, b: &WeightSums) -> WeightSums {
+        WeightSums {
+            total: a.total + b.total,
+            sum_x: a.sum_x + b.sum_x,
+            sum_y: a.sum_y + b.sum_y,
+        }
+    }
+}
+
+impl<T: Position + Layered, D: Default> Quadtree<T, D> {
+    /// Returns the items inside `range` sharing a layer with `mask`. Every
+    /// item in range is checked; see `AggregateQuadtree::query_layers` for
+    /// pruning by layer.
+    pub fn query_layers(&self, range: Rectangle, mask: u32) -> Vec<&T> {
+        let mut items = Vec::new();
+        self.visit(&range, &mut |item| {
+            if item.layers() & mask != 0 {
+                items.push(item);
+            }
+        });
+        items
+    }
+}
+
+impl<T: Position + Priority, D: Default> Quadtree<T, D> {
+    /// Returns the items inside `range` from the highest priority to the
+    /// lowest; equal priorities keep their traversal order. See
+    /// `AggregateQuadtree::query_ordered` to stop early, for instance to pick
+    /// only the topmost item.
+    pub fn query_ordered(&self, range: Rectangle) -> Vec<&T> {
+        let mut items = self.query(range);
+        items.sort_by(|a, b| b.priority().total_cmp(&a.priority()));
+        items
+    }
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Folds the items inside `range` with `agg`, without collecting them.
+    /// Every matching item is visited; see `AggregateQuadtree` for summaries
+    /// maintained per node.
+    pub fn aggregate_in_region<A: Aggregate<T>>(&self, range: Rectangle, agg: &A) -> A::Value {
+        let mut value = agg.empty();
+        self.visit(&range, &mut |item| {
+            value = agg.combine(&value, &agg.single(item));
+        });
+        value
+    }
+}
+
+/// The nodes of an `AggregateQuadtree`, each carrying the aggregate of the
+/// items below it, or `None` until it is computed.
+type Node<T, V> = Quadtree<T, Option<V>>;
+
+fn value<T, V>(node: &Node<T, V>) -> &V {
+    node.data
+        .as_ref()
+        .expect("aggregates are refreshed after every change")
+}
+
+/// Recomputes the aggregates of the nodes whose items may have changed at
+/// `point`, and of the nodes created or reset since the last refresh.
+fn refresh<T: Position, A: Aggregate<T>>(
+    node: &mut Node<T, A::Value>,
+    point: Option<&Point>,
+    agg: &A,
+) {
+    let mut value = agg.empty();
+    for item in &node.items {
+        value = agg.combine(&value, &agg.single(item));
+    }
+    for child in node.children.iter_mut().flatten() {
+        let on_path = point.filter(|point| {
+            child
+                .bounds()
+                .inflated(child.tolerance())
+                .contains_point(point)
+        });
+        if on_path.is_some() || child.data.is_none() {
+            refresh(child, on_path, agg);
+        }
+        value = agg.combine(&value, self::value(child));
+    }
+    node.data = Some(value);
+}
+
+fn fold<T: Position, A: Aggregate<T>>(
+    node: &Node<T, A::Value>,
+    range: &Rectangle,
+    agg: &A,
+    value: &mut A::Value,
+) {
+    let bounds = node.bounds();
+    if node.count == 0 || bounds.intersection(range).is_none() {
+        return;
+    }
+    if covers(range, &bounds) {
+        *value = agg.combine(value, self::value(node));
+        return;
+    }
+    for item in &node.items {
+        if range.contains_point(&item.position()) {
+            *value = agg.combine(value, &agg.single(item));
+        }
+    }
+    for child in node.children.iter().flatten() {
+        fold(child, range, agg, value);
+    }
+}
+
+/// Collects the items inside `range` accepted by `keep`, skipping the nodes
+/// whose value `enter` rejects.
+fn visit<'a, T: Position, V>(
+    node: &'a Node<T, V>,
+    range: &Rectangle,
+    enter: &dyn Fn(&V) -> bool,
+    keep: &dyn Fn(&T) -> bool,
+    items: &mut Vec<&'a T>,
+) {
+    if node.count == 0 || node.bounds().intersection(range).is_none() || !enter(value(node)) {
+        return;
+    }
+    for item in &node.items {
+        if range.contains_point(&item.position()) && keep(item) {
+            items.push(item);
+        }
+    }
+    for child in node.children.iter().flatten() {
+        visit(child, range, enter, keep, items);
+    }
+}
+
+/// A quadtree keeping the `Aggregate` of the items below every node as node
+/// data, so that `aggregate_in_region` reuses the summary of each node lying
+/// entirely inside the region and only looks at items in partially covered
+/// nodes. Items are stored, split and collapsed as in a `Quadtree` with the
+/// same options, and the summaries along the changed path are recomputed
+/// after every change.
+pub struct AggregateQuadtree<T, A: Aggregate<T>> {
+    tree: Node<T, A::Value>,
+    agg: A,
+}
+
+impl<T: Position, A: Aggregate<T>> AggregateQuadtree<T, A> {
+    pub fn new(boundary: Rectangle, agg: A) -> Self {
+        Self::with_options(boundary, Default::default(), agg)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `boundary` does not have a finite, positive width and height.
+    pub fn with_options(boundary: Rectangle, options: Options, agg: A) -> Self {
+        let mut tree = Quadtree::with_node_data(boundary, options);
+        refresh(&mut tree, None, &agg);
+        Self { tree, agg }
+    }
+
+    /// Stores `item` like `Quadtree::put`.
+    pub fn put(&mut self, item: T) {
+        let point = item.position();
+        let evicted = self.tree.put_evicting(item);
+        refresh(&mut self.tree, Some(&point), &self.agg);
+        for item in evicted {
+            refresh(&mut self.tree, Some(&item.position()), &self.agg);
+        }
+    }
+
+    /// Removes and returns an item stored at `point`, matched as by
+    /// `Quadtree::remove`.
+    pub fn remove(&mut self, point: Point) -> Option<T> {
+        let removed = self.tree.remove(point)?;
+        refresh(&mut self.tree, Some(&point), &self.agg);
+        Some(removed)
+    }
+
+    pub fn query(&self, range: Rectangle) -> Vec<&T> {
+        let mut items = Vec::new();
+        visit(&self.tree, &range, &|_| true, &|_| true, &mut items);
+        items
+    }
+
+    /// Returns the aggregate of every stored item.
+    pub fn aggregate(&self) -> A::Value {
+        value(&self.tree).clone()
+    }
+
+    pub fn aggregate_in_region(&self, range: Rectangle) -> A::Value {
+        let mut value = self.agg.empty();
+        fold(&self.tree, &range, &self.agg, &mut value);
+        value
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+}
+
+impl<T: Position + Layered> AggregateQuadtree<T, Layers> {
+    /// Returns the items inside `range` sharing a layer with `mask`, without
+    /// descending into nodes that hold no such item.
+    pub fn query_layers(&self, range: Rectangle, mask: u32) -> Vec<&T> {
+        let mut items = Vec::new();
+        visit(
+            &self.tree,
+            &range,
+            &|layers| layers & mask != 0,
+            &|item| item.layers() & mask != 0,
+            &mut items,
+        );
+        items
+    }
+}
+
+impl<T: Position + Weighted> AggregateQuadtree<T, WeightedCentroid> {
+    /// Same as `Quadtree::total_weight_in_region`, answering the nodes lying
+    /// entirely inside `range` from their summary.
+    pub fn total_weight_in_region(&self, range: Rectangle) -> f64 {
+        self.aggregate_in_region(range).total
+    }
+
+    /// Same as `Quadtree::centroid_in_region`, answering the nodes lying
+    /// entirely inside `range` from their summary.
+    pub fn centroid_in_region(&self, range: Rectangle) -> Option<Point> {
+        self.aggregate_in_region(range).centroid()
+    }
+}
+
+enum Ordered<'a, T, V> {
+    Node(&'a Node<T, V>),
+    Item(&'a T),
+}
+
+/// Lazily yields the items of a range from the highest `Max` key to the
+/// lowest, created by `AggregateQuadtree::query_ordered`.
+pub struct OrderedQuery<'a, T, F> {
+    range: Rectangle,
+    key: &'a F,
+    heap: BinaryHeap<Entry<Ordered<'a, T, Option<f64>>>>,
+}
+
+impl<'a, T: Position, F: Fn(&T) -> f64> Iterator for OrderedQuery<'a, T, F> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        while let Some(entry) = self.heap.pop() {
+            let node = match entry.value {
+                Ordered::Item(item) => return Some(item),
+                Ordered::Node(node) => node,
+            };
+            for item in &node.items {
+                if self.range.contains_point(&item.position()) {
+                    self.heap.push(Entry {
+                        distance: (self.key)(item),
+                        value: Ordered::Item(item),
+                    });
+                }
+            }
+            for child in node.children.iter().flatten() {
+                if let Some(max) = *value(child) {
+                    if child.count > 0 && child.bounds().intersection(&self.range).is_some() {
+                        self.heap.push(Entry {
+                            distance: max,
+                            value: Ordered::Node(child),
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<T: Position, F: Fn(&T) -> f64> AggregateQuadtree<T, Max<F>> {
+    /// Yields the items inside `range` from the highest key to the lowest,
+    /// visiting nodes best first by their maximum key. Taking only the first
+    /// items, such as the topmost sprite under the cursor, leaves the nodes
+    /// holding lower keys unvisited.
+    pub fn query_ordered(&self, range: Rectangle) -> OrderedQuery<'_, T, F> {
+        let mut heap = BinaryHeap::new();
+        if let Some(max) = *value(&self.tree) {
+            if self.tree.bounds().intersection(&range).is_some() {
+                heap.push(Entry {
+                    distance: max,
+                    value: Ordered::Node(&self.tree),
+                });
+            }
+        }
+        OrderedQuery {
+            range,
+            key: &self.agg.0,
+            heap,
+        }
+    }
+
+    /// Returns the `n` items inside `range` with the highest keys, highest
+    /// first. Nodes are visited best first by their maximum key, so the
+    /// search stops as soon as `n` items beat the maximum of every node left.
+    pub fn query_top(&self, range: Rectangle, n: usize) -> Vec<&T> {
+        self.query_ordered(range).take(n).collect()
+    }
+}
+
+fn covers(range: &Rectangle, cell: &Rectangle) -> bool {
+    cell.x >= range.x
+        && cell.x + cell.width <= range.x + range.width
+        && cell.y >= range.y
+        && cell.y + cell.height <= range.y + range.height
+}
@@ -0,0 +1,443 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::f64::consts::PI;
+
+use crate::nearest::Entry;
+use crate::{extend, Point, Position, Quadtree, Rectangle, Weighted, WeightedCentroid};
+
+const GAUSSIAN_CUTOFF: f64 = 4.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct NodeSummary {
+    pub bounds: Rectangle,
+    pub count: usize,
+    pub centroid: Point,
+}
+
+/// Approximate item count returned by `Quadtree::estimate_count_in_region`.
+/// The exact count lies within `error` of `count`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CountEstimate {
+    pub count: f64,
+    pub error: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kernel {
+    Uniform,
+    Epanechnikov,
+    Gaussian,
+}
+
+impl Kernel {
+    fn support(self) -> f64 {
+        match self {
+            Kernel::Uniform | Kernel::Epanechnikov => 1.0,
+            Kernel::Gaussian => GAUSSIAN_CUTOFF,
+        }
+    }
+
+    fn evaluate(self, u: f64) -> f64 {
+        match self {
+            Kernel::Uniform if u <= 1.0 => 1.0 / PI,
+            Kernel::Epanechnikov if u <= 1.0 => 2.0 / PI * (1.0 - u * u),
+            Kernel::Gaussian => (-0.5 * u * u).exp() / (2.0 * PI),
+            _ => 0.0,
+        }
+    }
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Summarizes every non-empty node at `depth` (or shallower leaf) that
+    /// intersects `range`. Each summary covers all items stored below the node.
+    pub fn query_at_depth(&self, range: Rectangle, depth: u8) -> Vec<NodeSummary> {
+        let mut summaries = Vec::new();
+        self.summarize(&range, depth, &mut summaries);
+        summaries
+    }
+
+    /// Counts the items inside `range` per grid cell, returned row by row.
+    pub fn heatmap(&self, range: Rectangle, cols: usize, rows: usize) -> Vec<f64> {
+        self.heatmap_by(range, cols, rows, |_| 1.0)
+    }
+
+    /// Sums `weight` over the items inside `range` per grid cell, returned
+    /// row by row, in a single traversal.
+    pub fn heatmap_by<W: Fn(&T) -> f64>(
+        &self,
+        range: Rectangle,
+        cols: usize,
+        rows: usize,
+        weight: W,
+    ) -> Vec<f64> {
+        let mut cells = vec![0.0; cols * rows];
+        if cells.is_empty() {
+            return cells;
+        }
+        self.visit(&range, &mut |item| {
+            let point = item.position();
+            let col = ((point.x - range.x) / range.width * cols as f64) as usize;
+            let row = ((point.y - range.y) / range.height * rows as f64) as usize;
+            cells[row.min(rows - 1) * cols + col.min(cols - 1)] += weight(item);
+        });
+        cells
+    }
+
+    /// Marks the cells of a `cols` by `rows` grid over `range` that contain at
+    /// least one item, returned row by row.
+    pub fn rasterize(&self, range: Rectangle, cols: usize, rows: usize) -> Vec<bool> {
+        let mut cells = vec![false; cols * rows];
+        if cells.is_empty() {
+            return cells;
+        }
+        self.visit(&range, &mut |item| {
+            let point = item.position();
+            let col = ((point.x - range.x) / range.width * cols as f64) as usize;
+            let row = ((point.y - range.y) / range.height * rows as f64) as usize;
+            cells[row.min(rows - 1) * cols + col.min(cols - 1)] = true;
+        });
+        cells
+    }
+
+    /// Counts the items inside `range` per bucket, as assigned by
+    /// `bucket_of`. Items mapped to a bucket past `buckets` are ignored.
+    pub fn histogram_in_region<B: Fn(&T) -> usize>(
+        &self,
+        range: Rectangle,
+        bucket_of: B,
+        buckets: usize,
+    ) -> Vec<usize> {
+        let mut counts = vec![0; buckets];
+        self.visit(&range, &mut |item| {
+            if let Some(count) = counts.get_mut(bucket_of(item)) {
+                *count += 1;
+            }
+        });
+        counts
+    }
+
+    /// Assigns the items inside `range` to square cells of side `cell_size`
+    /// laid out from the top-left corner of `range`, in one traversal. Only
+    /// non-empty cells are returned, row by row; items on the far edge of
+    /// `range` go to the last row or column.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cell_size` is not finite and positive.
+    pub fn group_by_grid(&self, range: Rectangle, cell_size: f64) -> Vec<(Rectangle, Vec<&T>)> {
+        assert!(
+            cell_size.is_finite() && cell_size > 0.0,
+            "grid cell size must be finite and positive: {}",
+            cell_size
+        );
+        let cols = ((range.width / cell_size).ceil() as usize).max(1);
+        let rows = ((range.height / cell_size).ceil() as usize).max(1);
+        let mut cells: BTreeMap<(usize, usize), Vec<&T>> = BTreeMap::new();
+        self.visit(&range, &mut |item| {
+            let point = item.position();
+            let col = ((point.x - range.x) / cell_size) as usize;
+            let row = ((point.y - range.y) / cell_size) as usize;
+            cells
+                .entry((row.min(rows - 1), col.min(cols - 1)))
+                .or_default()
+                .push(item);
+        });
+        cells
+            .into_iter()
+            .map(|((row, col), items)| {
+                let cell = Rectangle::new(
+                    range.x + col as f64 * cell_size,
+                    range.y + row as f64 * cell_size,
+                    cell_size,
+                    cell_size,
+                );
+                (cell, items)
+            })
+            .collect()
+    }
+
+    /// Estimates the item density at `at`. Only nodes within the kernel
+    /// support are visited; the Gaussian kernel is truncated at four
+    /// bandwidths, which makes its estimate approximate.
+    pub fn kde(&self, at: Point, bandwidth: f64, kernel: Kernel) -> f64 {
+        if self.count == 0 || bandwidth <= 0.0 {
+            return 0.0;
+        }
+        let reach = kernel.support() * bandwidth;
+        let range = Rectangle::new(at.x - reach, at.y - reach, 2.0 * reach, 2.0 * reach);
+        let mut sum = 0.0;
+        self.visit(&range, &mut |item| {
+            let point = item.position();
+            let distance = (point.x - at.x).hypot(point.y - at.y);
+            if distance <= reach {
+                sum += kernel.evaluate(distance / bandwidth);
+            }
+        });
+        sum / (self.count as f64 * bandwidth * bandwidth)
+    }
+
+    /// Returns the `n` items inside `range` with the highest `key`, highest
+    /// first. Matching items are ranked in a bounded heap during a single
+    /// traversal instead of being collected and sorted, but every one of them
+    /// is scored; see `AggregateQuadtree::query_top` to skip the nodes whose
+    /// maximum key cannot make the cut.
+    pub fn query_top_by<K: Fn(&T) -> f64>(&self, range: Rectangle, n: usize, key: K) -> Vec<&T> {
+        if n == 0 {
+            return Vec::new();
+        }
+        let mut best = BinaryHeap::with_capacity(n + 1);
+        self.visit(&range, &mut |item| {
+            let score = key(item);
+            if best.len() == n {
+                if best
+                    .peek()
+                    .is_some_and(|Reverse(e): &Reverse<Entry<&T>>| score <= e.distance)
+                {
+                    return;
+                }
+                best.pop();
+            }
+            best.push(Reverse(Entry {
+                distance: score,
+                value: item,
+            }));
+        });
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(e)| e.value)
+            .collect()
+    }
+
+    /// Returns the convex hull of the items inside `range` in counter-clockwise
+    /// order, starting from the lowest-leftmost point. Nodes whose items all
+    /// lie inside the hull built from the nodes visited before are skipped.
+    pub fn convex_hull_in_region(&self, range: Rectangle) -> Vec<Point> {
+        let mut points = Vec::new();
+        let mut hull = 0;
+        self.collect_hull(&range, &mut points, &mut hull);
+        convex_hull(points)
+    }
+
+    /// Gathers the candidate hull points inside `range`. The first `hull`
+    /// points form the hull of everything gathered up to its last rebuild,
+    /// which happens whenever the candidates double.
+    fn collect_hull(&self, range: &Rectangle, points: &mut Vec<Point>, hull: &mut usize) {
+        let extent = match self.extent.and_then(|extent| extent.intersection(range)) {
+            Some(extent) => extent,
+            None => return,
+        };
+        if *hull >= 3
+            && corners(&extent)
+                .iter()
+                .all(|c| in_hull(&points[..*hull], c))
+        {
+            return;
+        }
+        for item in &self.items {
+            let point = item.position();
+            if self._contains(&point, range) {
+                points.push(point);
+            }
+        }
+        if points.len() >= 2 * *hull + 8 {
+            *points = convex_hull(std::mem::take(points));
+            *hull = points.len();
+        }
+        for child in self.children.iter().flatten() {
+            child.collect_hull(range, points, hull);
+        }
+    }
+
+    /// Returns the tightest rectangle enclosing every stored item.
+    pub fn items_bounds(&self) -> Option<Rectangle> {
+        self.extent
+    }
+
+    /// Returns the tightest rectangle enclosing the items inside `range`.
+    pub fn bounds_in_region(&self, range: Rectangle) -> Option<Rectangle> {
+        let mut bounds = None;
+        self.collect_bounds(&range, &mut bounds);
+        bounds
+    }
+
+    fn collect_bounds(&self, range: &Rectangle, bounds: &mut Option<Rectangle>) {
+        let extent = match self.extent {
+            Some(extent) => extent,
+            None => return,
+        };
+        if extent.x > range.x + range.width
+            || extent.x + extent.width < range.x
+            || extent.y > range.y + range.height
+            || extent.y + extent.height < range.y
+        {
+            return;
+        }
+        if extent.x >= range.x
+            && extent.x + extent.width <= range.x + range.width
+            && extent.y >= range.y
+            && extent.y + extent.height <= range.y + range.height
+        {
+            *bounds = Some(extend(*bounds, &extent));
+            return;
+        }
+        for item in &self.items {
+            let point = item.position();
+            if self._contains(&point, range) {
+                *bounds = Some(extend(*bounds, &Rectangle::new(point.x, point.y, 0.0, 0.0)));
+            }
+        }
+        if let Some(ref children) = self.children {
+            for child in children {
+                child.collect_bounds(range, bounds);
+            }
+        }
+    }
+
+    /// Estimates how many items lie inside `range` from per-node counts,
+    /// scaling the count of a partly covered node by the share of its area
+    /// inside `range`. Nodes are only descended into while their uncertainty
+    /// would take the total error past `max_error`; a `max_error` of zero
+    /// gives the exact count.
+    pub fn estimate_count_in_region(&self, range: Rectangle, max_error: f64) -> CountEstimate {
+        let mut estimate = CountEstimate {
+            count: 0.0,
+            error: 0.0,
+        };
+        let mut budget = max_error.max(0.0);
+        self.estimate_node(&range, &mut budget, &mut estimate);
+        estimate
+    }
+
+    fn estimate_node(&self, range: &Rectangle, budget: &mut f64, estimate: &mut CountEstimate) {
+        let extent = match self.extent {
+            Some(extent) => extent,
+            None => return,
+        };
+        if extent.intersection(range).is_none() {
+            return;
+        }
+        if extent.x >= range.x
+            && extent.x + extent.width <= range.x + range.width
+            && extent.y >= range.y
+            && extent.y + extent.height <= range.y + range.height
+        {
+            estimate.count += self.count as f64;
+            return;
+        }
+
+        let bounds = self.bounds();
+        let overlap = bounds
+            .intersection(range)
+            .map_or(0.0, |shared| shared.width * shared.height);
+        let guess = self.count as f64 * overlap / (bounds.width * bounds.height);
+        let error = guess.max(self.count as f64 - guess);
+        if error <= *budget {
+            *budget -= error;
+            estimate.count += guess;
+            estimate.error += error;
+            return;
+        }
+
+        estimate.count += self
+            .items
+            .iter()
+            .filter(|item| self._contains(&item.position(), range))
+            .count() as f64;
+        if let Some(ref children) = self.children {
+            for child in children {
+                child.estimate_node(range, budget, estimate);
+            }
+        }
+    }
+
+    fn summarize(&self, range: &Rectangle, depth: u8, summaries: &mut Vec<NodeSummary>) {
+        if !self.intersects(range, &self.bounds()) {
+            return;
+        }
+        match self.children {
+            Some(ref children) if self.options.depth < depth => {
+                for child in children {
+                    child.summarize(range, depth, summaries);
+                }
+            }
+            _ => {
+                if self.count > 0 {
+                    let count = self.count as f64;
+                    summaries.push(NodeSummary {
+                        bounds: self.bounds(),
+                        count: self.count,
+                        centroid: Point::new(self.sum.0 / count, self.sum.1 / count),
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl<T: Position + Weighted, D: Default> Quadtree<T, D> {
+    /// Sums the weights of the items inside `range`, visiting each of them;
+    /// an `AggregateQuadtree` with `WeightedCentroid` keeps the sums per node.
+    pub fn total_weight_in_region(&self, range: Rectangle) -> f64 {
+        self.aggregate_in_region(range, &WeightedCentroid).total
+    }
+
+    /// Returns the weighted centroid of the items inside `range`, or `None`
+    /// when their total weight is zero. See `total_weight_in_region`.
+    pub fn centroid_in_region(&self, range: Rectangle) -> Option<Point> {
+        self.aggregate_in_region(range, &WeightedCentroid)
+            .centroid()
+    }
+
+    /// Sums the weights of the items inside `range` per grid cell, returned
+    /// row by row. See `heatmap_by`.
+    pub fn weighted_heatmap(&self, range: Rectangle, cols: usize, rows: usize) -> Vec<f64> {
+        self.heatmap_by(range, cols, rows, |item| item.weight())
+    }
+}
+
+fn convex_hull(mut points: Vec<Point>) -> Vec<Point> {
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    let mut hull: Vec<Point> = Vec::with_capacity(points.len() + 1);
+    for &point in &points {
+        while hull.len() >= 2 && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], &point) <= 0.0
+        {
+            hull.pop();
+        }
+        hull.push(point);
+    }
+    let lower = hull.len() + 1;
+    for &point in points.iter().rev().skip(1) {
+        while hull.len() >= lower
+            && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], &point) <= 0.0
+        {
+            hull.pop();
+        }
+        hull.push(point);
+    }
+    hull.pop();
+    hull
+}
+
+/// Whether `point` lies inside or on the counter-clockwise convex `hull`.
+fn in_hull(hull: &[Point], point: &Point) -> bool {
+    (0..hull.len()).all(|i| cross(&hull[i], &hull[(i + 1) % hull.len()], point) >= 0.0)
+}
+
+fn corners(rect: &Rectangle) -> [Point; 4] {
+    let (right, bottom) = (rect.x + rect.width, rect.y + rect.height);
+    [
+        Point::new(rect.x, rect.y),
+        Point::new(right, rect.y),
+        Point::new(right, bottom),
+        Point::new(rect.x, bottom),
+    ]
+}
+
+fn cross(o: &Point, a: &Point, b: &Point) -> f64 {
+    (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+}
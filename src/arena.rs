@@ -0,0 +1,122 @@
+use bumpalo::boxed::Box;
+use bumpalo::collections::Vec as BumpVec;
+use bumpalo::Bump;
+
+use crate::{Options, Position, Rectangle};
+
+struct Node<'bump, T> {
+    cell: Rectangle,
+    depth: u8,
+    items: BumpVec<'bump, T>,
+    children: Option<Box<'bump, [Node<'bump, T>; 4]>>,
+}
+
+impl<'bump, T: Position> Node<'bump, T> {
+    fn leaf(cell: Rectangle, depth: u8, bump: &'bump Bump) -> Self {
+        Node {
+            cell,
+            depth,
+            items: BumpVec::new_in(bump),
+            children: None,
+        }
+    }
+
+    fn insert(&mut self, item: T, options: &Options, bump: &'bump Bump) {
+        if let Some(ref mut children) = self.children {
+            let q = self.cell.quadrant(&item.position());
+            children[q].insert(item, options, bump);
+            return;
+        }
+        self.items.push(item);
+        if self.items.len() > options.max_items && self.depth < options.max_depth {
+            let depth = self.depth + 1;
+            let mut children = Box::new_in(
+                [0, 1, 2, 3].map(|q| Node::leaf(self.cell.quadrant_cell(q), depth, bump)),
+                bump,
+            );
+            let items = std::mem::replace(&mut self.items, BumpVec::new_in(bump));
+            for item in items {
+                let q = self.cell.quadrant(&item.position());
+                children[q].insert(item, options, bump);
+            }
+            self.children = Some(children);
+        }
+    }
+
+    fn visit<'a>(&'a self, range: &Rectangle, items: &mut Vec<&'a T>) {
+        if self.cell.intersection(range).is_none() {
+            return;
+        }
+        for item in &self.items {
+            if range.contains_point(&item.position()) {
+                items.push(item);
+            }
+        }
+        for child in self.children.iter().flat_map(|c| c.iter()) {
+            child.visit(range, items);
+        }
+    }
+}
+
+/// A quadtree whose nodes and item lists are allocated from a `bumpalo`
+/// arena instead of the global allocator. Dropping the tree runs the
+/// destructors of its items but returns no memory; reset the arena to
+/// reclaim everything at once, for instance between levels or loads.
+pub struct ArenaQuadtree<'bump, T> {
+    root: Node<'bump, T>,
+    options: Options,
+    bump: &'bump Bump,
+    count: usize,
+}
+
+impl<'bump, T: Position> ArenaQuadtree<'bump, T> {
+    pub fn new_in(boundary: Rectangle, bump: &'bump Bump) -> Self {
+        Self::with_options_in(boundary, Default::default(), bump)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `boundary` does not have a finite, positive width and height.
+    pub fn with_options_in(boundary: Rectangle, options: Options, bump: &'bump Bump) -> Self {
+        assert!(
+            boundary.has_area(),
+            "quadtree boundary must have a finite, positive area: {}",
+            boundary
+        );
+        Self {
+            root: Node::leaf(boundary, options.depth, bump),
+            options,
+            bump,
+            count: 0,
+        }
+    }
+
+    pub fn put(&mut self, item: T) {
+        if !self.root.cell.contains_point(&item.position()) {
+            return;
+        }
+        self.count += 1;
+        self.root.insert(item, &self.options, self.bump);
+    }
+
+    pub fn query(&self, range: Rectangle) -> Vec<&T> {
+        let mut items = Vec::new();
+        self.root.visit(&range, &mut items);
+        items
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Drops every item. The memory of the old nodes stays in the arena
+    /// until it is reset.
+    pub fn clear(&mut self) {
+        self.root = Node::leaf(self.root.cell, self.options.depth, self.bump);
+        self.count = 0;
+    }
+}
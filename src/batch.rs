@@ -0,0 +1,62 @@
+use std::sync::atomic::Ordering;
+
+use crate::{Position, Quadtree, Rectangle};
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Answers several range queries in one traversal; the result at index
+    /// `i` holds what `query(rects[i])` would return. Each node is visited
+    /// once for all the ranges overlapping it.
+    pub fn query_many(&self, rects: &[Rectangle]) -> Vec<Vec<&T>> {
+        if let Some(ref metrics) = self.metrics {
+            metrics
+                .queries
+                .fetch_add(rects.len() as u64, Ordering::Relaxed);
+        }
+        let mut results = vec![Vec::new(); rects.len()];
+        let active: Vec<usize> = (0..rects.len()).collect();
+        self.query_many_node(rects, &active, &mut results);
+        results
+    }
+
+    fn query_many_node<'a>(
+        &'a self,
+        rects: &[Rectangle],
+        active: &[usize],
+        results: &mut [Vec<&'a T>],
+    ) {
+        if let Some(ref metrics) = self.metrics {
+            metrics.nodes_visited.fetch_add(1, Ordering::Relaxed);
+        }
+        match self.children {
+            Some(ref children) => {
+                let bounds = self.bounds();
+                let active: Vec<usize> = active
+                    .iter()
+                    .copied()
+                    .filter(|&i| self.intersects(&rects[i], &bounds))
+                    .collect();
+                if active.is_empty() {
+                    return;
+                }
+                for child in children {
+                    child.query_many_node(rects, &active, results);
+                }
+            }
+            None => {
+                if let Some(ref metrics) = self.metrics {
+                    metrics
+                        .items_scanned
+                        .fetch_add(self.items.len() as u64, Ordering::Relaxed);
+                }
+                for item in &self.items {
+                    let point = item.position();
+                    for &i in active {
+                        if self._contains(&point, &rects[i]) {
+                            results[i].push(item);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+use bevy_transform::components::Transform;
+
+use crate::{ItemId, KeyedQuadtree, Options, Point, Rectangle};
+
+/// Marks entities whose `Transform` translation is kept in the
+/// `SpatialIndex`.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct Indexed;
+
+/// Indexes every `Indexed` entity by the x and y of its translation.
+/// Entities outside `boundary` are left out until they move back in.
+pub struct SpatialIndexPlugin {
+    pub boundary: Rectangle,
+    pub options: Options,
+}
+
+impl SpatialIndexPlugin {
+    pub fn new(boundary: Rectangle) -> Self {
+        Self {
+            boundary,
+            options: Default::default(),
+        }
+    }
+}
+
+impl Plugin for SpatialIndexPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SpatialIndex {
+            boundary: self.boundary,
+            tree: KeyedQuadtree::with_options(self.boundary, self.options),
+            ids: HashMap::new(),
+        })
+        .add_systems(PostUpdate, sync_spatial_index);
+    }
+}
+
+#[derive(Resource)]
+pub struct SpatialIndex {
+    boundary: Rectangle,
+    tree: KeyedQuadtree<Entity>,
+    ids: HashMap<Entity, ItemId>,
+}
+
+impl SpatialIndex {
+    pub fn query(&self, range: Rectangle) -> Vec<Entity> {
+        self.tree
+            .query(range)
+            .into_iter()
+            .map(|(_, &entity)| entity)
+            .collect()
+    }
+
+    pub fn position_of(&self, entity: Entity) -> Option<Point> {
+        self.tree.position_of(*self.ids.get(&entity)?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    fn covers(&self, point: &Point) -> bool {
+        self.boundary
+            .intersection(&Rectangle::new(point.x, point.y, 0.0, 0.0))
+            .is_some()
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if let Some(id) = self.ids.remove(&entity) {
+            self.tree.remove_by_id(id);
+        }
+    }
+}
+
+/// Read-only access to the `SpatialIndex` from systems.
+#[derive(SystemParam)]
+pub struct SpatialQuery<'w> {
+    index: Res<'w, SpatialIndex>,
+}
+
+impl<'w> SpatialQuery<'w> {
+    pub fn in_rect(&self, range: Rectangle) -> Vec<Entity> {
+        self.index.query(range)
+    }
+
+    pub fn position_of(&self, entity: Entity) -> Option<Point> {
+        self.index.position_of(entity)
+    }
+}
+
+/// Entities that moved, or that gained `Indexed` on an existing `Transform`.
+type Moved = (With<Indexed>, Or<(Changed<Transform>, Added<Indexed>)>);
+
+fn sync_spatial_index(
+    mut index: ResMut<SpatialIndex>,
+    changed: Query<(Entity, &Transform), Moved>,
+    mut removed: RemovedComponents<Indexed>,
+) {
+    let index = &mut *index;
+    for entity in removed.read() {
+        index.remove(entity);
+    }
+    let mut moves = Vec::new();
+    for (entity, transform) in &changed {
+        let point = Point::new(
+            f64::from(transform.translation.x),
+            f64::from(transform.translation.y),
+        );
+        match index.ids.get(&entity) {
+            Some(&id) if index.covers(&point) => moves.push((id, point)),
+            Some(_) => index.remove(entity),
+            None => {
+                if let Some(id) = index.tree.insert(point, entity) {
+                    index.ids.insert(entity, id);
+                }
+            }
+        }
+    }
+    index.tree.apply_moves(moves);
+}
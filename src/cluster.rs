@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use crate::{Position, Quadtree};
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Groups the items with DBSCAN, using `query_radius` as the neighbor
+    /// index. Noise items are left out of the result.
+    pub fn cluster_dbscan(&self, eps: f64, min_pts: usize) -> Vec<Vec<&T>> {
+        let mut items = Vec::with_capacity(self.count);
+        self.visit(&self.bounds(), &mut |item| items.push(item));
+        let index: HashMap<*const T, usize> = items
+            .iter()
+            .enumerate()
+            .map(|(i, &item)| (item as *const T, i))
+            .collect();
+
+        let mut labels: Vec<Option<usize>> = vec![None; items.len()];
+        let mut visited = vec![false; items.len()];
+        let mut clusters = 0;
+        for i in 0..items.len() {
+            if visited[i] {
+                continue;
+            }
+            visited[i] = true;
+            let neighbors = self.query_radius(items[i].position(), eps);
+            if neighbors.len() < min_pts {
+                continue;
+            }
+
+            labels[i] = Some(clusters);
+            let mut queue: Vec<usize> =
+                neighbors.iter().map(|&n| index[&(n as *const T)]).collect();
+            while let Some(j) = queue.pop() {
+                if labels[j].is_none() {
+                    labels[j] = Some(clusters);
+                }
+                if visited[j] {
+                    continue;
+                }
+                visited[j] = true;
+                let neighbors = self.query_radius(items[j].position(), eps);
+                if neighbors.len() >= min_pts {
+                    queue.extend(neighbors.iter().map(|&n| index[&(n as *const T)]));
+                }
+            }
+            clusters += 1;
+        }
+
+        let mut result = vec![Vec::new(); clusters];
+        for (item, label) in items.into_iter().zip(labels) {
+            if let Some(cluster) = label {
+                result[cluster].push(item);
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{Options, Point, Position, Rectangle};
+
+struct Node<T> {
+    cell: Rectangle,
+    depth: u8,
+    items: Vec<T>,
+    children: [Option<Box<Node<T>>>; 4],
+}
+
+impl<T: Position> Node<T> {
+    fn leaf(cell: Rectangle, depth: u8) -> Self {
+        Node {
+            cell,
+            depth,
+            items: Vec::new(),
+            children: [None, None, None, None],
+        }
+    }
+
+    fn is_leaf(&self) -> bool {
+        self.children.iter().all(Option::is_none)
+    }
+
+    fn insert(&mut self, item: T, options: &Options) {
+        if self.is_leaf() {
+            self.items.push(item);
+            if self.items.len() > options.max_items && self.depth < options.max_depth {
+                self.split(options);
+            }
+            return;
+        }
+
+        let point = item.position();
+        let q = self.cell.quadrant(&point);
+        let slot = &mut self.children[q];
+        match slot {
+            Some(child) if child.cell.contains_point(&point) => {
+                child.insert(item, options);
+                compact(slot);
+            }
+            Some(_) => {
+                let child = slot.take().unwrap();
+                *slot = Some(Box::new(Node::join(
+                    child,
+                    item,
+                    self.cell.quadrant_cell(q),
+                    self.depth + 1,
+                )));
+            }
+            None => {
+                let mut leaf = Node::leaf(self.cell.quadrant_cell(q), self.depth + 1);
+                leaf.items.push(item);
+                *slot = Some(Box::new(leaf));
+            }
+        }
+    }
+
+    /// Builds the smallest node below `cell` that separates `child` from an
+    /// item falling outside of it.
+    fn join(child: Box<Node<T>>, item: T, mut cell: Rectangle, mut depth: u8) -> Node<T> {
+        let point = item.position();
+        let center = Point::new(
+            child.cell.x + child.cell.width / 2.0,
+            child.cell.y + child.cell.height / 2.0,
+        );
+        while depth + 1 < child.depth && cell.quadrant(&point) == cell.quadrant(&center) {
+            cell = cell.quadrant_cell(cell.quadrant(&point));
+            depth += 1;
+        }
+
+        let mut node = Node::leaf(cell, depth);
+        let (qp, qc) = (cell.quadrant(&point), cell.quadrant(&center));
+        let mut leaf = Node::leaf(cell.quadrant_cell(qp), depth + 1);
+        leaf.items.push(item);
+        node.children[qp] = Some(Box::new(leaf));
+        node.children[qc] = Some(child);
+        node
+    }
+
+    fn split(&mut self, options: &Options) {
+        for item in std::mem::take(&mut self.items) {
+            let q = self.cell.quadrant(&item.position());
+            let (cell, depth) = (self.cell.quadrant_cell(q), self.depth + 1);
+            self.children[q]
+                .get_or_insert_with(|| Box::new(Node::leaf(cell, depth)))
+                .items
+                .push(item);
+        }
+        for slot in self.children.iter_mut() {
+            if let Some(child) = slot {
+                if child.items.len() > options.max_items && child.depth < options.max_depth {
+                    child.split(options);
+                    compact(slot);
+                }
+            }
+        }
+    }
+
+    fn visit<'a>(&'a self, range: &Rectangle, items: &mut Vec<&'a T>) {
+        if self.cell.intersection(range).is_none() {
+            return;
+        }
+        for item in &self.items {
+            if range.contains_point(&item.position()) {
+                items.push(item);
+            }
+        }
+        for child in self.children.iter().flatten() {
+            child.visit(range, items);
+        }
+    }
+
+    fn node_count(&self) -> usize {
+        1 + self
+            .children
+            .iter()
+            .flatten()
+            .map(|child| child.node_count())
+            .sum::<usize>()
+    }
+}
+
+/// A path-compressed quadtree. Chains of nodes with a single non-empty child
+/// are skipped: a child may cover a cell several levels below its parent, so
+/// clustered data does not pay for long runs of empty ancestors.
+pub struct CompressedQuadtree<T> {
+    root: Node<T>,
+    options: Options,
+    count: usize,
+}
+
+impl<T: Position> CompressedQuadtree<T> {
+    pub fn new(boundary: Rectangle) -> Self {
+        Self::with_options(boundary, Default::default())
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `boundary` does not have a finite, positive width and height.
+    pub fn with_options(boundary: Rectangle, options: Options) -> Self {
+        assert!(
+            boundary.has_area(),
+            "quadtree boundary must have a finite, positive area: {}",
+            boundary
+        );
+        Self {
+            root: Node::leaf(boundary, options.depth),
+            options,
+            count: 0,
+        }
+    }
+
+    pub fn put(&mut self, item: T) {
+        if !self.root.cell.contains_point(&item.position()) {
+            return;
+        }
+        self.count += 1;
+        self.root.insert(item, &self.options);
+    }
+
+    pub fn query(&self, range: Rectangle) -> Vec<&T> {
+        let mut items = Vec::new();
+        self.root.visit(&range, &mut items);
+        items
+    }
+
+    /// Returns the number of nodes, including the root.
+    pub fn node_count(&self) -> usize {
+        self.root.node_count()
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.root = Node::leaf(self.root.cell, self.options.depth);
+        self.count = 0;
+    }
+}
+
+/// Replaces an internal node that has a single child by that child.
+fn compact<T: Position>(slot: &mut Option<Box<Node<T>>>) {
+    while let Some(node) = slot {
+        if !node.items.is_empty() || node.children.iter().flatten().count() != 1 {
+            return;
+        }
+        *slot = node.children.iter_mut().find_map(Option::take);
+    }
+}
@@ -0,0 +1,60 @@
+use std::io::{self, Read};
+
+use csv::{Reader, StringRecord};
+
+use crate::{Point, Position, Quadtree, Rectangle};
+
+impl Quadtree<Point> {
+    /// Streams the points of a CSV file with a header row into a new tree,
+    /// reading coordinates from the columns named `x_col` and `y_col`.
+    pub fn from_csv<R: Read>(
+        reader: R,
+        boundary: Rectangle,
+        x_col: &str,
+        y_col: &str,
+    ) -> io::Result<Self> {
+        Quadtree::from_csv_with(reader, boundary, x_col, y_col, |point, _| point)
+    }
+}
+
+impl<T: Position> Quadtree<T> {
+    /// Like `from_csv`, but builds each item from its point and the full row.
+    /// Rows outside `boundary` are skipped, as with `put`.
+    pub fn from_csv_with<R: Read, F: FnMut(Point, &StringRecord) -> T>(
+        reader: R,
+        boundary: Rectangle,
+        x_col: &str,
+        y_col: &str,
+        mut item: F,
+    ) -> io::Result<Self> {
+        let mut reader = Reader::from_reader(reader);
+        let headers = reader.headers()?;
+        let column = |name: &str| {
+            headers
+                .iter()
+                .position(|header| header == name)
+                .ok_or_else(|| invalid(format!("missing column {:?}", name)))
+        };
+        let (x, y) = (column(x_col)?, column(y_col)?);
+
+        let mut tree = Quadtree::new(boundary);
+        let mut record = StringRecord::new();
+        while reader.read_record(&mut record)? {
+            let point = Point::new(coordinate(&record, x)?, coordinate(&record, y)?);
+            tree.put(item(point, &record));
+        }
+        Ok(tree)
+    }
+}
+
+fn coordinate(record: &StringRecord, column: usize) -> io::Result<f64> {
+    let field = record.get(column).unwrap_or_default();
+    field.trim().parse().map_err(|_| {
+        let line = record.position().map_or(0, |position| position.line());
+        invalid(format!("invalid coordinate {:?} on line {}", field, line))
+    })
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,236 @@
+use crate::eviction::removal_ranks;
+use crate::{Position, Quadtree, Rectangle};
+
+/// Navigates the nodes of a tree by hand, starting at the root. Quadrants
+/// are numbered in traversal order: 0 for (x, y), 1 for (x + w, y), 2 for
+/// (x + w, y + h) and 3 for (x, y + h).
+pub struct Cursor<'a, T, D = ()> {
+    path: Vec<&'a Quadtree<T, D>>,
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    pub fn cursor(&self) -> Cursor<'_, T, D> {
+        Cursor { path: vec![self] }
+    }
+
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T, D> {
+        CursorMut {
+            root: self,
+            path: Vec::new(),
+        }
+    }
+}
+
+impl<'a, T: Position, D: Default> Cursor<'a, T, D> {
+    fn node(&self) -> &'a Quadtree<T, D> {
+        self.path[self.path.len() - 1]
+    }
+
+    /// Moves back to the root.
+    pub fn root(&mut self) {
+        self.path.truncate(1);
+    }
+
+    /// Moves to a child of the current node. Returns `false`, without
+    /// moving, if the node is a leaf.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `quadrant` is not in `0..4`.
+    pub fn descend(&mut self, quadrant: usize) -> bool {
+        assert!(quadrant < 4, "quadrant must be in 0..4: {}", quadrant);
+        match self.node().children {
+            Some(ref children) => {
+                self.path.push(&children[quadrant]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves to the parent of the current node. Returns `false` at the root.
+    pub fn ascend(&mut self) -> bool {
+        if self.path.len() == 1 {
+            return false;
+        }
+        self.path.pop();
+        true
+    }
+
+    /// The number of levels below the root.
+    pub fn depth(&self) -> usize {
+        self.path.len() - 1
+    }
+
+    pub fn bounds(&self) -> Rectangle {
+        self.node().bounds()
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.node().children.is_none()
+    }
+
+    /// The items stored directly in the current node.
+    pub fn items(&self) -> &'a [T] {
+        &self.node().items
+    }
+
+    /// The number of items in the subtree of the current node.
+    pub fn len(&self) -> usize {
+        self.node().count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.node().count == 0
+    }
+
+    pub fn data(&self) -> &'a D {
+        &self.node().data
+    }
+}
+
+/// Same as `Cursor`, but able to change the data and items of the nodes it
+/// visits. Item counts and extents of the ancestors are kept up to date;
+/// nodes emptied this way are not collapsed until `Quadtree::optimize`.
+pub struct CursorMut<'a, T, D = ()> {
+    root: &'a mut Quadtree<T, D>,
+    path: Vec<usize>,
+}
+
+impl<'a, T: Position, D: Default> CursorMut<'a, T, D> {
+    fn node(&self) -> &Quadtree<T, D> {
+        let mut node = &*self.root;
+        for &quadrant in &self.path {
+            node = &node.children.as_ref().unwrap()[quadrant];
+        }
+        node
+    }
+
+    fn node_mut(&mut self) -> &mut Quadtree<T, D> {
+        let mut node = &mut *self.root;
+        for &quadrant in &self.path {
+            node = &mut node.children.as_mut().unwrap()[quadrant];
+        }
+        node
+    }
+
+    pub fn root(&mut self) {
+        self.path.clear();
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `quadrant` is not in `0..4`.
+    pub fn descend(&mut self, quadrant: usize) -> bool {
+        assert!(quadrant < 4, "quadrant must be in 0..4: {}", quadrant);
+        if self.node().children.is_none() {
+            return false;
+        }
+        self.path.push(quadrant);
+        true
+    }
+
+    pub fn ascend(&mut self) -> bool {
+        self.path.pop().is_some()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    pub fn bounds(&self) -> Rectangle {
+        self.node().bounds()
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.node().children.is_none()
+    }
+
+    pub fn items(&self) -> &[T] {
+        &self.node().items
+    }
+
+    pub fn len(&self) -> usize {
+        self.node().count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.node().count == 0
+    }
+
+    pub fn data(&self) -> &D {
+        &self.node().data
+    }
+
+    pub fn data_mut(&mut self) -> &mut D {
+        &mut self.node_mut().data
+    }
+
+    /// Removes the item at `index` among the items of the current node.
+    pub fn remove_item(&mut self, index: usize) -> Option<T> {
+        let mut removed = None;
+        let mut ranks = Vec::new();
+        self.edit(&mut |items| {
+            if index < items.len() {
+                let mut gone = vec![false; items.len()];
+                gone[index] = true;
+                ranks = removal_ranks(items, &gone);
+                removed = Some(items.remove(index));
+            }
+        });
+        self.root.forget(&ranks);
+        removed
+    }
+
+    /// Keeps only the items of the current node for which `f` returns
+    /// `true`, and returns how many were removed.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) -> usize {
+        let mut ranks = Vec::new();
+        self.edit(&mut |items| {
+            let gone: Vec<bool> = items.iter().map(|item| !f(item)).collect();
+            ranks = removal_ranks(items, &gone);
+            let mut gone = gone.into_iter();
+            items.retain(|_| !gone.next().unwrap());
+        });
+        self.root.forget(&ranks);
+        ranks.len()
+    }
+
+    fn edit(&mut self, edit: &mut dyn FnMut(&mut Vec<T>)) {
+        let bounds = self.bounds();
+        let path = std::mem::take(&mut self.path);
+        let removed = edit_at(self.root, &path, edit);
+        self.path = path;
+        if let Some(observer) = self.root.state.as_mut().and_then(|s| s.observer.as_mut()) {
+            for _ in 0..removed {
+                observer.on_remove(bounds);
+            }
+        }
+    }
+}
+
+/// Applies `edit` to the items of the node at `path`, then fixes the counts
+/// and extents on the way back up. Returns the number of items removed.
+fn edit_at<T: Position, D: Default>(
+    node: &mut Quadtree<T, D>,
+    path: &[usize],
+    edit: &mut dyn FnMut(&mut Vec<T>),
+) -> usize {
+    let removed = match path.split_first() {
+        Some((&quadrant, rest)) => {
+            edit_at(&mut node.children.as_mut().unwrap()[quadrant], rest, edit)
+        }
+        None => {
+            let len = node.items.len();
+            edit(&mut node.items);
+            let removed = len - node.items.len();
+            node.dirty |= removed > 0 && node.options.track_dirty;
+            removed
+        }
+    };
+    if removed > 0 {
+        node.count -= removed;
+        node.refresh_extent();
+    }
+    removed
+}
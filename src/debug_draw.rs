@@ -0,0 +1,23 @@
+use crate::{Point, Position, Quadtree, Rectangle};
+
+/// Drawing backend for `Quadtree::debug_draw`.
+pub trait DebugDraw {
+    /// Draws the outline of a node at `depth`, the root being at depth zero.
+    fn draw_rect(&mut self, bounds: Rectangle, depth: u8);
+
+    fn draw_point(&mut self, point: Point);
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Draws every node, parents before their children, followed by the
+    /// items stored in it.
+    pub fn debug_draw(&self, draw: &mut impl DebugDraw) {
+        draw.draw_rect(self.bounds(), self.options.depth);
+        for item in &self.items {
+            draw.draw_point(item.position());
+        }
+        for child in self.children.iter().flatten() {
+            child.debug_draw(draw);
+        }
+    }
+}
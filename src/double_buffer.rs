@@ -0,0 +1,42 @@
+use std::mem;
+
+use crate::{Options, Position, Quadtree, Rectangle};
+
+/// Keeps the tree built during the previous frame readable while the
+/// current frame fills a second one. The write tree starts every frame empty.
+pub struct DoubleBufferedQuadtree<T> {
+    front: Quadtree<T>,
+    back: Quadtree<T>,
+}
+
+impl<T: Position> DoubleBufferedQuadtree<T> {
+    pub fn new(boundary: Rectangle) -> Self {
+        Self::with_options(boundary, Default::default())
+    }
+
+    pub fn with_options(boundary: Rectangle, options: Options) -> Self {
+        Self {
+            front: Quadtree::with_options(boundary, options),
+            back: Quadtree::with_options(boundary, options),
+        }
+    }
+
+    pub fn read(&self) -> &Quadtree<T> {
+        &self.front
+    }
+
+    pub fn write(&mut self) -> &mut Quadtree<T> {
+        &mut self.back
+    }
+
+    /// Borrows the read and write trees at the same time.
+    pub fn split(&mut self) -> (&Quadtree<T>, &mut Quadtree<T>) {
+        (&self.front, &mut self.back)
+    }
+
+    /// Publishes the write tree and starts an empty one for the next frame.
+    pub fn swap(&mut self) {
+        mem::swap(&mut self.front, &mut self.back);
+        self.back.clear();
+    }
+}
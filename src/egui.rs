@@ -0,0 +1,199 @@
+use egui::{Color32, Painter, PointerButton, Pos2, Rect, Response, Sense, Stroke, StrokeKind, Ui};
+
+use crate::{DebugDraw, Point, Position, Quadtree, Rectangle};
+
+const ZOOM_RANGE: (f32, f32) = (0.1, 1000.0);
+
+/// Interactive view of a tree for debug overlays: drag to pan, scroll to
+/// zoom, double-click to reset the view and drag with the secondary button to
+/// run a live query. Hovering a node shows its bounds, depth and counts.
+#[derive(Debug, Clone, Default)]
+pub struct Inspector {
+    /// World point shown at the middle of the widget, the middle of the tree
+    /// until the view is panned.
+    center: Option<Point>,
+    zoom: Option<f32>,
+    drag_origin: Option<Point>,
+    /// The query rectangle, in world coordinates.
+    pub query: Option<Rectangle>,
+}
+
+/// Maps world coordinates to the widget's screen rectangle.
+#[derive(Debug, Clone, Copy)]
+struct View {
+    screen: Rect,
+    center: Point,
+    scale: f32,
+}
+
+impl View {
+    fn to_screen(self, x: f64, y: f64) -> Pos2 {
+        self.screen.center()
+            + egui::vec2(
+                (x - self.center.x) as f32 * self.scale,
+                (y - self.center.y) as f32 * self.scale,
+            )
+    }
+
+    fn to_world(self, pos: Pos2) -> Point {
+        let offset = (pos - self.screen.center()) / self.scale;
+        Point::new(
+            self.center.x + f64::from(offset.x),
+            self.center.y + f64::from(offset.y),
+        )
+    }
+
+    fn rect(self, bounds: &Rectangle) -> Rect {
+        Rect::from_two_pos(
+            self.to_screen(bounds.x, bounds.y),
+            self.to_screen(bounds.x + bounds.width, bounds.y + bounds.height),
+        )
+    }
+}
+
+struct Overlay<'a> {
+    painter: &'a Painter,
+    view: View,
+}
+
+impl DebugDraw for Overlay<'_> {
+    fn draw_rect(&mut self, bounds: Rectangle, depth: u8) {
+        let shade = 200u8.saturating_sub(depth.saturating_mul(24));
+        self.painter.rect_stroke(
+            self.view.rect(&bounds),
+            0.0,
+            Stroke::new(1.0, Color32::from_gray(shade)),
+            StrokeKind::Middle,
+        );
+    }
+
+    fn draw_point(&mut self, point: Point) {
+        self.painter.circle_filled(
+            self.view.to_screen(point.x, point.y),
+            2.0,
+            Color32::LIGHT_BLUE,
+        );
+    }
+}
+
+impl Inspector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draws `tree` over the remaining space of `ui` and handles the input
+    /// aimed at it.
+    pub fn show<T: Position, D: Default>(
+        &mut self,
+        ui: &mut Ui,
+        tree: &Quadtree<T, D>,
+    ) -> Response {
+        let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::click_and_drag());
+        let painter = painter.with_clip_rect(response.rect);
+        let bounds = tree.bounds();
+
+        if response.double_clicked() {
+            self.center = None;
+            self.zoom = None;
+        }
+        let fit = (response.rect.width() / bounds.width as f32)
+            .min(response.rect.height() / bounds.height as f32);
+        let mut zoom = self.zoom.unwrap_or(1.0);
+        if response.hovered() {
+            let scroll = ui.input(|input| input.smooth_scroll_delta.y);
+            zoom = (zoom * (scroll / 200.0).exp()).clamp(ZOOM_RANGE.0, ZOOM_RANGE.1);
+        }
+        let mut view = View {
+            screen: response.rect,
+            center: self.center.unwrap_or_else(|| {
+                Point::new(
+                    bounds.x + bounds.width / 2.0,
+                    bounds.y + bounds.height / 2.0,
+                )
+            }),
+            scale: fit * zoom,
+        };
+        if response.dragged_by(PointerButton::Primary) {
+            let delta = response.drag_delta() / view.scale;
+            view.center = Point::new(
+                view.center.x - f64::from(delta.x),
+                view.center.y - f64::from(delta.y),
+            );
+        }
+        self.center = Some(view.center);
+        self.zoom = Some(zoom);
+
+        if let Some(pos) = response.interact_pointer_pos() {
+            if response.drag_started_by(PointerButton::Secondary) {
+                let origin = ui.input(|input| input.pointer.press_origin());
+                self.drag_origin = Some(view.to_world(origin.unwrap_or(pos)));
+            }
+            if let (true, Some(origin)) = (
+                response.dragged_by(PointerButton::Secondary),
+                self.drag_origin,
+            ) {
+                self.query = Some(Rectangle::from_corners(origin, view.to_world(pos)));
+            }
+        }
+
+        tree.debug_draw(&mut Overlay {
+            painter: &painter,
+            view,
+        });
+
+        if let Some(query) = self.query {
+            let found = tree.query(query);
+            for item in &found {
+                let point = item.position();
+                painter.circle_filled(view.to_screen(point.x, point.y), 3.0, Color32::YELLOW);
+            }
+            let rect = view.rect(&query);
+            painter.rect_stroke(
+                rect,
+                0.0,
+                Stroke::new(1.5, Color32::YELLOW),
+                StrokeKind::Middle,
+            );
+            painter.text(
+                rect.left_top(),
+                egui::Align2::LEFT_BOTTOM,
+                format!("{} items", found.len()),
+                egui::FontId::monospace(12.0),
+                Color32::YELLOW,
+            );
+        }
+
+        let hovered = response
+            .hover_pos()
+            .map(|pos| view.to_world(pos))
+            .filter(|point| tree._contains(point, &bounds))
+            .map(|point| tree.node_at(&point));
+        match hovered {
+            Some(node) => {
+                painter.rect_stroke(
+                    view.rect(&node.bounds()),
+                    0.0,
+                    Stroke::new(2.0, Color32::LIGHT_RED),
+                    StrokeKind::Middle,
+                );
+                response.on_hover_ui_at_pointer(|ui| {
+                    ui.label(format!("bounds: {}", node.bounds()));
+                    ui.label(format!("depth: {}", node.options.depth));
+                    ui.label(format!("items below: {}", node.count));
+                    ui.label(format!("items here: {}", node.items.len()));
+                })
+            }
+            None => response,
+        }
+    }
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// The deepest node whose quadrant holds `point`.
+    fn node_at(&self, point: &Point) -> &Quadtree<T, D> {
+        match self.children {
+            Some(ref children) => children[self.quadrant(point)].node_at(point),
+            None => self,
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::{Point, Position, Quadtree};
+
+/// Chooses which items a tree bounded by `Options::max_total_items` drops
+/// once an insertion takes it over the limit.
+///
+/// Items are told apart by their position and their rank: the number of
+/// items stored at exactly the same position before them. Items sharing a
+/// position keep their insertion order, so a rank always designates the same
+/// item until one ahead of it is removed.
+pub trait EvictionPolicy {
+    /// Called with the position of every item accepted by the tree, before
+    /// it is stored.
+    fn on_insert(&mut self, _point: Point) {}
+
+    /// Called for every item leaving the tree other than by eviction.
+    fn on_remove(&mut self, _point: Point, _rank: usize) {}
+
+    /// Called when the tree is cleared.
+    fn clear(&mut self) {}
+
+    /// Returns the position of the next item to evict, given the positions
+    /// still stored, or `None` to stop evicting. The first item stored at
+    /// exactly that position is evicted.
+    fn victim(&mut self, positions: &mut dyn Iterator<Item = Point>) -> Option<Point>;
+}
+
+/// Evicts items in insertion order. Items removed by other means are
+/// forgotten right away, so it holds one entry per stored item.
+#[derive(Debug, Clone, Default)]
+pub struct OldestFirst {
+    next: u64,
+    order: BTreeMap<u64, Point>,
+    at: HashMap<Point, VecDeque<u64>>,
+}
+
+impl EvictionPolicy for OldestFirst {
+    fn on_insert(&mut self, point: Point) {
+        self.order.insert(self.next, point);
+        self.at.entry(point).or_default().push_back(self.next);
+        self.next += 1;
+    }
+
+    fn on_remove(&mut self, point: Point, rank: usize) {
+        if let Some(stamps) = self.at.get_mut(&point) {
+            if let Some(stamp) = stamps.remove(rank) {
+                self.order.remove(&stamp);
+            }
+            if stamps.is_empty() {
+                self.at.remove(&point);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.order.clear();
+        self.at.clear();
+    }
+
+    fn victim(&mut self, _positions: &mut dyn Iterator<Item = Point>) -> Option<Point> {
+        let (&stamp, &point) = self.order.iter().next()?;
+        self.on_remove(point, 0);
+        debug_assert!(!self.order.contains_key(&stamp));
+        Some(point)
+    }
+}
+
+/// Evicts the item farthest from `focus`, such as a camera or player
+/// position, keeping the neighbourhood of interest cached.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FarthestFrom {
+    pub focus: Point,
+}
+
+impl FarthestFrom {
+    pub fn new(focus: Point) -> Self {
+        Self { focus }
+    }
+}
+
+impl EvictionPolicy for FarthestFrom {
+    fn victim(&mut self, positions: &mut dyn Iterator<Item = Point>) -> Option<Point> {
+        let distance = |point: &Point| (point.x - self.focus.x).hypot(point.y - self.focus.y);
+        positions.max_by(|a, b| distance(a).total_cmp(&distance(b)))
+    }
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Stores `item` like `put`, and returns the items evicted to stay within
+    /// `Options::max_total_items`, possibly including `item` itself.
+    pub fn put_evicting(&mut self, item: T) -> Vec<T> {
+        if !self.contains(&item) {
+            return Vec::new();
+        }
+        if let Some(policy) = self.eviction_mut() {
+            policy.on_insert(item.position());
+        }
+        match self.take_observer() {
+            Some(mut observer) => {
+                self.insert(item, &mut *observer);
+                self.set_observer(observer);
+            }
+            None => self.insert(item, &mut ()),
+        }
+        self.evict()
+    }
+
+    /// Replaces the eviction policy used once the tree holds more than
+    /// `Options::max_total_items` items. Items already stored are reported to
+    /// the new policy in traversal order, and the items evicted to get back
+    /// within the limit are returned.
+    pub fn set_eviction_policy(
+        &mut self,
+        mut policy: Box<dyn EvictionPolicy + Send + Sync>,
+    ) -> Vec<T> {
+        self.positions().for_each(|point| policy.on_insert(point));
+        self.state_mut().eviction = Some(policy);
+        self.evict()
+    }
+
+    /// Takes the eviction policy out of the tree, leaving it unbounded until
+    /// another one is set.
+    pub fn take_eviction_policy(&mut self) -> Option<Box<dyn EvictionPolicy + Send + Sync>> {
+        self.state.as_mut()?.eviction.take()
+    }
+
+    pub(crate) fn evict(&mut self) -> Vec<T> {
+        let mut evicted = Vec::new();
+        let limit = self.options.max_total_items;
+        if limit == 0 || self.count <= limit {
+            return evicted;
+        }
+        let mut policy = match self.take_eviction_policy() {
+            Some(policy) => policy,
+            None => return evicted,
+        };
+        let defer = self.options.defer_collapse;
+        while self.count > limit {
+            let point = match policy.victim(&mut self.positions()) {
+                Some(point) => point,
+                None => break,
+            };
+            match self.remove_with(&point, &mut |item| item.position() == point, defer) {
+                Some(item) => evicted.push(item),
+                None => break,
+            }
+        }
+        self.state_mut().eviction = Some(policy);
+        evicted
+    }
+
+    /// Tells the eviction policy about items removed other than by eviction,
+    /// given as `(position, rank)` pairs in removal order.
+    pub(crate) fn forget(&mut self, removed: &[(Point, usize)]) {
+        if let Some(policy) = self.eviction_mut() {
+            for &(point, rank) in removed {
+                policy.on_remove(point, rank);
+            }
+        }
+    }
+}
+
+/// Ranks of the items of `items` flagged in `gone`, last one first, so that
+/// each rank is still valid once the items after it are gone.
+pub(crate) fn removal_ranks<T: Position>(items: &[T], gone: &[bool]) -> Vec<(Point, usize)> {
+    (0..items.len())
+        .rev()
+        .filter(|&index| gone[index])
+        .map(|index| {
+            let point = items[index].position();
+            let rank = items[..index]
+                .iter()
+                .filter(|item| item.position() == point)
+                .count();
+            (point, rank)
+        })
+        .collect()
+}
@@ -0,0 +1,75 @@
+use crate::eviction::removal_ranks;
+use crate::{Expiring, Observer, Point, Position, Quadtree, Rectangle};
+
+impl<T: Position + Expiring, D: Default> Quadtree<T, D> {
+    /// Removes and returns every item that has expired at `now`, in
+    /// traversal order. Nodes left under-full collapse unless
+    /// `Options::defer_collapse` is set.
+    pub fn prune_expired(&mut self, now: f64) -> Vec<T> {
+        let mut expired = Vec::new();
+        let mut ranks = Vec::new();
+        match self.take_observer() {
+            Some(mut observer) => {
+                self.sweep_expired(now, &mut expired, &mut ranks, &mut *observer);
+                self.set_observer(observer);
+            }
+            None => self.sweep_expired(now, &mut expired, &mut ranks, &mut ()),
+        }
+        self.forget(&ranks);
+        expired
+    }
+
+    /// Returns the items inside `range` that have not expired at `now`,
+    /// leaving expired ones in place until the next `prune_expired`.
+    pub fn query_live(&self, range: Rectangle, now: f64) -> Vec<&T> {
+        let mut items = Vec::new();
+        self.visit(&range, &mut |item| {
+            if item.expires_at() > now {
+                items.push(item);
+            }
+        });
+        items
+    }
+
+    fn sweep_expired(
+        &mut self,
+        now: f64,
+        expired: &mut Vec<T>,
+        ranks: &mut Vec<(Point, usize)>,
+        observer: &mut dyn Observer,
+    ) {
+        let before = expired.len();
+        if self.items.iter().any(|item| item.expires_at() <= now) {
+            let gone: Vec<bool> = self
+                .items
+                .iter()
+                .map(|item| item.expires_at() <= now)
+                .collect();
+            ranks.extend(removal_ranks(&self.items, &gone));
+            let (gone, kept) = std::mem::take(&mut self.items)
+                .into_iter()
+                .partition::<Vec<T>, _>(|item| item.expires_at() <= now);
+            self.items = kept;
+            self.dirty |= self.options.track_dirty;
+            for _ in 0..gone.len() {
+                observer.on_remove(self.bounds());
+            }
+            expired.extend(gone);
+        }
+        if let Some(ref mut children) = self.children {
+            for child in children.iter_mut() {
+                child.sweep_expired(now, expired, ranks, observer);
+            }
+        }
+
+        let removed = expired.len() - before;
+        if removed > 0 {
+            self.count -= removed;
+            if !self.options.defer_collapse && self.collapsible() {
+                observer.on_collapse(self.bounds());
+                self.collapse();
+            }
+            self.refresh_extent();
+        }
+    }
+}
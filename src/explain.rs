@@ -0,0 +1,68 @@
+use std::fmt;
+
+use crate::{Position, Quadtree, Rectangle};
+
+/// What a `query` over `range` did, as recorded by `Quadtree::explain_query`.
+#[derive(Debug, Clone, Copy)]
+pub struct QueryTrace {
+    pub range: Rectangle,
+    pub nodes_visited: usize,
+    /// Inner nodes entered but not descended into because their bounds miss
+    /// `range`.
+    pub nodes_pruned: usize,
+    pub items_scanned: usize,
+    pub items_matched: usize,
+}
+
+impl fmt::Display for QueryTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "query {}: {} nodes visited, {} pruned, {} items scanned, {} matched",
+            self.range,
+            self.nodes_visited,
+            self.nodes_pruned,
+            self.items_scanned,
+            self.items_matched
+        )
+    }
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Runs `query(range)` and reports the work it took instead of the items.
+    /// Metrics are left untouched.
+    pub fn explain_query(&self, range: Rectangle) -> QueryTrace {
+        let mut trace = QueryTrace {
+            range,
+            nodes_visited: 0,
+            nodes_pruned: 0,
+            items_scanned: 0,
+            items_matched: 0,
+        };
+        self.trace_node(&range, &mut trace);
+        trace
+    }
+
+    fn trace_node(&self, range: &Rectangle, trace: &mut QueryTrace) {
+        trace.nodes_visited += 1;
+        match self.children {
+            Some(ref children) => {
+                if self.intersects(range, &self.bounds()) {
+                    for child in children {
+                        child.trace_node(range, trace);
+                    }
+                } else {
+                    trace.nodes_pruned += 1;
+                }
+            }
+            None => {
+                trace.items_scanned += self.items.len();
+                trace.items_matched += self
+                    .items
+                    .iter()
+                    .filter(|item| self._contains(&item.position(), range))
+                    .count();
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! C bindings. Trees are opaque handles storing a caller-provided `uintptr_t`
+//! per point, which may be an id or a pointer the caller owns.
+
+use std::ptr;
+
+use crate::{Point, Position, Quadtree, Rectangle};
+
+struct Entry {
+    point: Point,
+    user: usize,
+}
+
+impl Position for Entry {
+    fn position(&self) -> Point {
+        self.point
+    }
+}
+
+pub struct QuadtreeHandle {
+    tree: Quadtree<Entry>,
+}
+
+/// Creates a tree, or returns null when the boundary has no area. The tree
+/// must be released with `quadtree_free`.
+#[no_mangle]
+pub extern "C" fn quadtree_new(x: f64, y: f64, width: f64, height: f64) -> *mut QuadtreeHandle {
+    let boundary = Rectangle::new(x, y, width, height);
+    if !boundary.has_area() {
+        return ptr::null_mut();
+    }
+    Box::into_raw(Box::new(QuadtreeHandle {
+        tree: Quadtree::new(boundary),
+    }))
+}
+
+/// # Safety
+///
+/// `tree` must be null or a handle returned by `quadtree_new` that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn quadtree_free(tree: *mut QuadtreeHandle) {
+    if !tree.is_null() {
+        drop(Box::from_raw(tree));
+    }
+}
+
+/// Stores `user` at (`x`, `y`) and returns whether the point was inside the
+/// tree boundary.
+///
+/// # Safety
+///
+/// `tree` must be a live handle returned by `quadtree_new`.
+#[no_mangle]
+pub unsafe extern "C" fn quadtree_insert(
+    tree: *mut QuadtreeHandle,
+    x: f64,
+    y: f64,
+    user: usize,
+) -> bool {
+    let tree = &mut (*tree).tree;
+    let len = tree.len();
+    tree.put(Entry {
+        point: Point::new(x, y),
+        user,
+    });
+    tree.len() > len
+}
+
+/// Writes the user values inside the range into `out`, up to `capacity` of
+/// them, and returns the total number of matches so callers can retry with
+/// a larger buffer.
+///
+/// # Safety
+///
+/// `tree` must be a live handle returned by `quadtree_new` and `out` must be
+/// valid for writing `capacity` values.
+#[no_mangle]
+pub unsafe extern "C" fn quadtree_query(
+    tree: *const QuadtreeHandle,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    out: *mut usize,
+    capacity: usize,
+) -> usize {
+    let found = (*tree).tree.query(Rectangle::new(x, y, width, height));
+    for (i, entry) in found.iter().take(capacity).enumerate() {
+        *out.add(i) = entry.user;
+    }
+    found.len()
+}
+
+/// # Safety
+///
+/// `tree` must be a live handle returned by `quadtree_new`.
+#[no_mangle]
+pub unsafe extern "C" fn quadtree_len(tree: *const QuadtreeHandle) -> usize {
+    (*tree).tree.len()
+}
@@ -0,0 +1,105 @@
+use crate::{Position, Quadtree};
+
+/// Marks a `FlatNode` without children.
+pub const NO_CHILDREN: u32 = u32::MAX;
+
+/// A node of a `FlatQuadtree`: its bounds as `[min_x, min_y, max_x, max_y]`,
+/// the index of the first of its four consecutive children (`NO_CHILDREN`
+/// for a leaf), and the range of its items in `FlatQuadtree::items`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlatNode {
+    pub bounds: [f32; 4],
+    pub first_child: u32,
+    pub item_start: u32,
+    pub item_count: u32,
+}
+
+/// The position of a stored item.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlatItem {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// A tree laid out in two contiguous arrays of plain `#[repr(C)]` records,
+/// ready to be copied into GPU buffers. The root is node 0, children are
+/// visited in the same order as the tree, and `items[i]` is the `i`-th item
+/// yielded by `Quadtree::iter`. Coordinates are narrowed to `f32`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FlatQuadtree {
+    pub nodes: Vec<FlatNode>,
+    pub items: Vec<FlatItem>,
+}
+
+impl FlatQuadtree {
+    /// The nodes as little-endian bytes, 28 per node.
+    pub fn node_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.nodes.len() * 28);
+        for node in &self.nodes {
+            for value in &node.bounds {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            for value in &[node.first_child, node.item_start, node.item_count] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// The items as little-endian bytes, 8 per item.
+    pub fn item_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.items.len() * 8);
+        for item in &self.items {
+            bytes.extend_from_slice(&item.x.to_le_bytes());
+            bytes.extend_from_slice(&item.y.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Exports the nodes and item positions as a `FlatQuadtree`.
+    pub fn flatten(&self) -> FlatQuadtree {
+        let mut flat = FlatQuadtree::default();
+        flat.nodes.push(self.flat_node());
+        self.flatten_into(0, &mut flat);
+        flat
+    }
+
+    fn flat_node(&self) -> FlatNode {
+        FlatNode {
+            bounds: [
+                self.x as f32,
+                self.y as f32,
+                (self.x + self.width) as f32,
+                (self.y + self.height) as f32,
+            ],
+            first_child: NO_CHILDREN,
+            item_start: 0,
+            item_count: 0,
+        }
+    }
+
+    fn flatten_into(&self, index: usize, flat: &mut FlatQuadtree) {
+        flat.nodes[index].item_start = flat.items.len() as u32;
+        flat.nodes[index].item_count = self.items.len() as u32;
+        flat.items.extend(self.items.iter().map(|item| {
+            let point = item.position();
+            FlatItem {
+                x: point.x as f32,
+                y: point.y as f32,
+            }
+        }));
+        if let Some(ref children) = self.children {
+            let first = flat.nodes.len();
+            flat.nodes[index].first_child = first as u32;
+            flat.nodes
+                .extend(children.iter().map(|child| child.flat_node()));
+            for (i, child) in children.iter().enumerate() {
+                child.flatten_into(first + i, flat);
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{Point, Position, Rectangle, SpatialIndex};
+
+/// A flat grid of equally sized square cells over a boundary. For dense,
+/// uniformly distributed items it avoids the descent of a quadtree, and it
+/// implements `SpatialIndex` so the two can be swapped freely.
+pub struct UniformGrid<T> {
+    boundary: Rectangle,
+    cell_size: f64,
+    cols: usize,
+    rows: usize,
+    cells: Vec<Vec<T>>,
+    count: usize,
+}
+
+impl<T: Position> UniformGrid<T> {
+    /// # Panics
+    ///
+    /// Panics if `boundary` does not have a finite, positive width and height,
+    /// or if `cell_size` is not finite and positive.
+    pub fn new(boundary: Rectangle, cell_size: f64) -> Self {
+        assert!(
+            boundary.has_area(),
+            "quadtree boundary must have a finite, positive area: {}",
+            boundary
+        );
+        assert!(
+            cell_size > 0.0 && cell_size.is_finite(),
+            "grid cell size must be finite and positive: {}",
+            cell_size
+        );
+        let cols = (boundary.width / cell_size).ceil().max(1.0) as usize;
+        let rows = (boundary.height / cell_size).ceil().max(1.0) as usize;
+        Self {
+            boundary,
+            cell_size,
+            cols,
+            rows,
+            cells: (0..cols * rows).map(|_| Vec::new()).collect(),
+            count: 0,
+        }
+    }
+
+    pub fn put(&mut self, item: T) {
+        let point = item.position();
+        if self.boundary.contains_point(&point) {
+            let (col, row) = self.cell_of(&point);
+            self.cells[row * self.cols + col].push(item);
+            self.count += 1;
+        }
+    }
+
+    /// Removes and returns the first item stored exactly at `point`. The
+    /// other items of its cell keep their order.
+    pub fn remove(&mut self, point: Point) -> Option<T> {
+        if !self.boundary.contains_point(&point) {
+            return None;
+        }
+        let (col, row) = self.cell_of(&point);
+        let cell = &mut self.cells[row * self.cols + col];
+        let index = cell.iter().position(|item| item.position() == point)?;
+        self.count -= 1;
+        Some(cell.remove(index))
+    }
+
+    pub fn query(&self, range: Rectangle) -> Vec<&T> {
+        let clipped = match self.boundary.intersection(&range) {
+            Some(clipped) => clipped,
+            None => return Vec::new(),
+        };
+        let (col0, row0) = self.cell_of(&Point::new(clipped.x, clipped.y));
+        let (col1, row1) = self.cell_of(&Point::new(
+            clipped.x + clipped.width,
+            clipped.y + clipped.height,
+        ));
+        let mut items = Vec::new();
+        for row in row0..=row1 {
+            for cell in &self.cells[row * self.cols + col0..=row * self.cols + col1] {
+                items.extend(
+                    cell.iter()
+                        .filter(|item| range.contains_point(&item.position())),
+                );
+            }
+        }
+        items
+    }
+
+    /// Returns the item closest to `point`, scanning rings of cells outward
+    /// from the cell holding it until no closer item can remain.
+    pub fn nearest(&self, point: Point) -> Option<&T> {
+        let (col, row) = self.cell_of(&point);
+        let mut best: Option<(f64, &T)> = None;
+        for ring in 0..self.cols.max(self.rows) {
+            for (c, r) in ring_cells(col, row, ring, self.cols, self.rows) {
+                for item in &self.cells[r * self.cols + c] {
+                    let p = item.position();
+                    let distance = (p.x - point.x).hypot(p.y - point.y);
+                    if best.is_none_or(|(d, _)| distance < d) {
+                        best = Some((distance, item));
+                    }
+                }
+            }
+            if let Some((distance, _)) = best {
+                if distance <= ring as f64 * self.cell_size {
+                    break;
+                }
+            }
+        }
+        best.map(|(_, item)| item)
+    }
+
+    pub fn cell_size(&self) -> f64 {
+        self.cell_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.iter_mut().for_each(Vec::clear);
+        self.count = 0;
+    }
+
+    fn cell_of(&self, point: &Point) -> (usize, usize) {
+        let col = ((point.x - self.boundary.x) / self.cell_size).floor();
+        let row = ((point.y - self.boundary.y) / self.cell_size).floor();
+        (
+            (col.max(0.0) as usize).min(self.cols - 1),
+            (row.max(0.0) as usize).min(self.rows - 1),
+        )
+    }
+}
+
+impl<T: Position> SpatialIndex<T> for UniformGrid<T> {
+    fn put(&mut self, item: T) {
+        UniformGrid::put(self, item)
+    }
+
+    fn remove(&mut self, point: Point) -> Option<T> {
+        UniformGrid::remove(self, point)
+    }
+
+    fn query(&self, range: Rectangle) -> Vec<&T> {
+        UniformGrid::query(self, range)
+    }
+
+    fn nearest(&self, point: Point) -> Option<&T> {
+        UniformGrid::nearest(self, point)
+    }
+
+    fn len(&self) -> usize {
+        UniformGrid::len(self)
+    }
+}
+
+/// The in-bounds cells at Chebyshev distance `ring` from `(col, row)`, row
+/// by row. Only the border of the ring is walked: the full first and last
+/// rows, and the two end cells of the rows between them.
+fn ring_cells(
+    col: usize,
+    row: usize,
+    ring: usize,
+    cols: usize,
+    rows: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    let (col, row, ring) = (col as isize, row as isize, ring as isize);
+    let (cols, rows) = (cols as isize, rows as isize);
+    let (left, right) = ((col - ring).max(0), (col + ring).min(cols - 1));
+    ((row - ring).max(0)..=(row + ring).min(rows - 1))
+        .flat_map(move |r| {
+            let (across, step) = if (r - row).abs() == ring {
+                (left..=right, 1)
+            } else {
+                (col - ring..=col + ring, (2 * ring) as usize)
+            };
+            across.step_by(step.max(1)).map(move |c| (c, r))
+        })
+        .filter(move |&(c, _)| c >= 0 && c < cols)
+        .map(|(c, r)| (c as usize, r as usize))
+}
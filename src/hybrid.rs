@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::iter;
+
+use crate::nearest::gap;
+use crate::{Options, Point, Position, Quadtree, Rectangle, SpatialIndex};
+
+/// A coarse grid of quadtrees over a large boundary. Items are dispatched to
+/// the tree of their grid cell in constant time, and each tree subdivides on
+/// its own, so a huge world never needs a single enormous root. Trees are
+/// only created for cells that receive items. The last column and row end
+/// at the boundary, and absorb any sliver left over from the division.
+pub struct HybridIndex<T> {
+    boundary: Rectangle,
+    cell_size: f64,
+    cols: usize,
+    rows: usize,
+    options: Options,
+    trees: HashMap<(usize, usize), Quadtree<T>>,
+}
+
+impl<T: Position> HybridIndex<T> {
+    pub fn new(boundary: Rectangle, cell_size: f64) -> Self {
+        Self::with_options(boundary, cell_size, Default::default())
+    }
+
+    /// Every tree of the grid is built with `options`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `boundary` does not have a finite, positive width and height,
+    /// or if `cell_size` is not finite and positive.
+    pub fn with_options(boundary: Rectangle, cell_size: f64, options: Options) -> Self {
+        assert!(
+            boundary.has_area(),
+            "quadtree boundary must have a finite, positive area: {}",
+            boundary
+        );
+        assert!(
+            cell_size > 0.0 && cell_size.is_finite(),
+            "grid cell size must be finite and positive: {}",
+            cell_size
+        );
+        Self {
+            boundary,
+            cell_size,
+            cols: cell_count(boundary.width, cell_size),
+            rows: cell_count(boundary.height, cell_size),
+            options,
+            trees: HashMap::new(),
+        }
+    }
+
+    pub fn put(&mut self, item: T) {
+        let point = item.position();
+        if !self.boundary.contains_point(&point) {
+            return;
+        }
+        let cell = self.cell_of(&point);
+        let boundary = self.cell_bounds(cell);
+        let options = self.options;
+        self.trees
+            .entry(cell)
+            .or_insert_with(|| Quadtree::with_options(boundary, options))
+            .put(item);
+    }
+
+    /// Removes and returns the first item stored at `point`, within
+    /// `Options::epsilon`, looking in the cell of `point` first and then in
+    /// the neighbouring cells within that tolerance. Trees left empty are
+    /// dropped.
+    pub fn remove(&mut self, point: Point) -> Option<T> {
+        let tolerance = self.options.epsilon.max(self.options.snap / 2.0);
+        let (col0, row0) = self.cell_of(&Point::new(point.x - tolerance, point.y - tolerance));
+        let (col1, row1) = self.cell_of(&Point::new(point.x + tolerance, point.y + tolerance));
+        let home = self.cell_of(&point);
+        let around = (row0..=row1)
+            .flat_map(|row| (col0..=col1).map(move |col| (col, row)))
+            .filter(|&cell| cell != home);
+        for cell in iter::once(home).chain(around) {
+            let tree = match self.trees.get_mut(&cell) {
+                Some(tree) => tree,
+                None => continue,
+            };
+            if let Some(removed) = tree.remove(point) {
+                if tree.is_empty() {
+                    self.trees.remove(&cell);
+                }
+                return Some(removed);
+            }
+        }
+        None
+    }
+
+    pub fn query(&self, range: Rectangle) -> Vec<&T> {
+        let clipped = match self.boundary.intersection(&range) {
+            Some(clipped) => clipped,
+            None => return Vec::new(),
+        };
+        let (col0, row0) = self.cell_of(&Point::new(clipped.x, clipped.y));
+        let (col1, row1) = self.cell_of(&Point::new(
+            clipped.x + clipped.width,
+            clipped.y + clipped.height,
+        ));
+        let mut items = Vec::new();
+        if (col1 - col0 + 1) * (row1 - row0 + 1) <= self.trees.len() {
+            for row in row0..=row1 {
+                for col in col0..=col1 {
+                    if let Some(tree) = self.trees.get(&(col, row)) {
+                        items.extend(tree.query(range));
+                    }
+                }
+            }
+        } else {
+            for (&(col, row), tree) in &self.trees {
+                if (col0..=col1).contains(&col) && (row0..=row1).contains(&row) {
+                    items.extend(tree.query(range));
+                }
+            }
+        }
+        items
+    }
+
+    /// Returns the item closest to `point`, visiting the occupied cells from
+    /// the closest one outward and stopping once no cell can hold a closer
+    /// item.
+    pub fn nearest(&self, point: Point) -> Option<&T> {
+        let mut cells: Vec<(f64, &Quadtree<T>)> = self
+            .trees
+            .iter()
+            .map(|(&cell, tree)| {
+                let (dx, dy) = gap(&point, &self.cell_bounds(cell));
+                (dx.hypot(dy), tree)
+            })
+            .collect();
+        cells.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut best: Option<(f64, &T)> = None;
+        for (bound, tree) in cells {
+            if best.is_some_and(|(distance, _)| distance <= bound) {
+                break;
+            }
+            if let Some(item) = tree.nearest(point) {
+                let p = item.position();
+                let distance = (p.x - point.x).hypot(p.y - point.y);
+                if best.is_none_or(|(d, _)| distance < d) {
+                    best = Some((distance, item));
+                }
+            }
+        }
+        best.map(|(_, item)| item)
+    }
+
+    /// Returns the tree of the grid cell holding `point`, if it has items.
+    pub fn tree_at(&self, point: Point) -> Option<&Quadtree<T>> {
+        if !self.boundary.contains_point(&point) {
+            return None;
+        }
+        self.trees.get(&self.cell_of(&point))
+    }
+
+    /// The number of grid cells that currently hold a tree.
+    pub fn tree_count(&self) -> usize {
+        self.trees.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.trees.values().map(|tree| tree.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trees.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.trees.clear();
+    }
+
+    fn cell_of(&self, point: &Point) -> (usize, usize) {
+        let col = ((point.x - self.boundary.x) / self.cell_size).floor();
+        let row = ((point.y - self.boundary.y) / self.cell_size).floor();
+        (
+            (col.max(0.0) as usize).min(self.cols - 1),
+            (row.max(0.0) as usize).min(self.rows - 1),
+        )
+    }
+
+    /// The bounds of a grid cell; the last column and row extend to the
+    /// boundary.
+    fn cell_bounds(&self, (col, row): (usize, usize)) -> Rectangle {
+        let x = self.boundary.x + col as f64 * self.cell_size;
+        let y = self.boundary.y + row as f64 * self.cell_size;
+        let width = if col + 1 == self.cols {
+            self.boundary.x + self.boundary.width - x
+        } else {
+            self.cell_size
+        };
+        let height = if row + 1 == self.rows {
+            self.boundary.y + self.boundary.height - y
+        } else {
+            self.cell_size
+        };
+        Rectangle::new(x, y, width, height)
+    }
+}
+
+impl<T: Position> SpatialIndex<T> for HybridIndex<T> {
+    fn put(&mut self, item: T) {
+        HybridIndex::put(self, item)
+    }
+
+    fn remove(&mut self, point: Point) -> Option<T> {
+        HybridIndex::remove(self, point)
+    }
+
+    fn query(&self, range: Rectangle) -> Vec<&T> {
+        HybridIndex::query(self, range)
+    }
+
+    fn nearest(&self, point: Point) -> Option<&T> {
+        HybridIndex::nearest(self, point)
+    }
+
+    fn len(&self) -> usize {
+        HybridIndex::len(self)
+    }
+}
+
+/// The number of cells of `cell_size` needed to span `length`. A last cell
+/// that would only cover rounding error, such as the fourth cell of 0.1 in
+/// 0.30000000000000004, is folded into the one before it.
+fn cell_count(length: f64, cell_size: f64) -> usize {
+    let mut count = (length / cell_size).ceil().max(1.0);
+    while count > 1.0 && length - (count - 1.0) * cell_size <= cell_size * 1e-9 {
+        count -= 1.0;
+    }
+    count as usize
+}
@@ -0,0 +1,83 @@
+use crate::{LinearQuadtree, Point, Position, Quadtree, Rectangle};
+
+/// The operations shared by the spatial indexes of this crate, so that an
+/// application can swap one structure for another, or benchmark them, behind
+/// a single interface.
+pub trait SpatialIndex<T> {
+    /// Stores `item`; items outside the index boundary are ignored.
+    fn put(&mut self, item: T);
+
+    /// Stores every item of `items`, as by `put`. Backends that can load
+    /// faster in bulk override it.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, items: I)
+    where
+        Self: Sized,
+    {
+        for item in items {
+            self.put(item);
+        }
+    }
+
+    /// Removes and returns an item stored at `point`.
+    fn remove(&mut self, point: Point) -> Option<T>;
+
+    /// Returns the items inside `range`, in no particular order.
+    fn query(&self, range: Rectangle) -> Vec<&T>;
+
+    /// Returns the item closest to `point` by Euclidean distance.
+    fn nearest(&self, point: Point) -> Option<&T>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Position, D: Default> SpatialIndex<T> for Quadtree<T, D> {
+    fn put(&mut self, item: T) {
+        Quadtree::put(self, item)
+    }
+
+    fn remove(&mut self, point: Point) -> Option<T> {
+        Quadtree::remove(self, point)
+    }
+
+    fn query(&self, range: Rectangle) -> Vec<&T> {
+        Quadtree::query(self, range)
+    }
+
+    fn nearest(&self, point: Point) -> Option<&T> {
+        Quadtree::nearest(self, point)
+    }
+
+    fn len(&self) -> usize {
+        Quadtree::len(self)
+    }
+}
+
+impl<T: Position> SpatialIndex<T> for LinearQuadtree<T> {
+    fn put(&mut self, item: T) {
+        LinearQuadtree::put(self, item)
+    }
+
+    fn extend<I: IntoIterator<Item = T>>(&mut self, items: I) {
+        LinearQuadtree::extend(self, items)
+    }
+
+    fn remove(&mut self, point: Point) -> Option<T> {
+        LinearQuadtree::remove(self, point)
+    }
+
+    fn query(&self, range: Rectangle) -> Vec<&T> {
+        LinearQuadtree::query(self, range)
+    }
+
+    fn nearest(&self, point: Point) -> Option<&T> {
+        LinearQuadtree::nearest(self, point)
+    }
+
+    fn len(&self) -> usize {
+        LinearQuadtree::len(self)
+    }
+}
@@ -0,0 +1,330 @@
+use std::fmt;
+use std::ops::Deref;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IntPoint {
+    x: i64,
+    y: i64,
+}
+
+impl IntPoint {
+    pub fn new(x: i64, y: i64) -> Self {
+        Self { x, y }
+    }
+
+    pub fn x(&self) -> i64 {
+        self.x
+    }
+
+    pub fn y(&self) -> i64 {
+        self.y
+    }
+}
+
+impl fmt::Display for IntPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+/// A half-open integer rectangle covering `x..x + width` and `y..y + height`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IntRectangle {
+    x: i64,
+    y: i64,
+    width: i64,
+    height: i64,
+}
+
+impl IntRectangle {
+    pub fn new(x: i64, y: i64, width: i64, height: i64) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn x(&self) -> i64 {
+        self.x
+    }
+
+    pub fn y(&self) -> i64 {
+        self.y
+    }
+
+    pub fn width(&self) -> i64 {
+        self.width
+    }
+
+    pub fn height(&self) -> i64 {
+        self.height
+    }
+
+    fn contains(&self, point: &IntPoint) -> bool {
+        point.x >= self.x
+            && point.x < self.x + self.width
+            && point.y >= self.y
+            && point.y < self.y + self.height
+    }
+
+    fn intersects(&self, other: &IntRectangle) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+}
+
+impl fmt::Display for IntRectangle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "({}, {}, {}, {})",
+            self.x,
+            self.y,
+            self.x + self.width,
+            self.y + self.height
+        )
+    }
+}
+
+pub trait IntPosition {
+    fn position(&self) -> IntPoint;
+}
+
+#[derive(Debug)]
+pub struct IntItem<'a, T> {
+    point: IntPoint,
+    data: &'a T,
+}
+
+impl<'a, T> IntItem<'a, T>
+where
+    T: 'a,
+{
+    pub fn new(point: IntPoint, data: &'a T) -> Self {
+        Self { point, data }
+    }
+}
+
+impl<'a, T> Deref for IntItem<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+impl<'a, T> IntPosition for IntItem<'a, T> {
+    fn position(&self) -> IntPoint {
+        self.point
+    }
+}
+
+/// The subset of `Options` that applies to an `IntQuadtree`; the
+/// tolerances of `Options` only make sense for float coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntOptions {
+    pub max_items: usize,
+    pub max_depth: u8,
+    pub depth: u8,
+}
+
+impl Default for IntOptions {
+    fn default() -> IntOptions {
+        IntOptions {
+            max_items: 20,
+            max_depth: 3,
+            depth: 0,
+        }
+    }
+}
+
+/// A quadtree over integer coordinates. Cells are split at exact integer
+/// midpoints, so the layout never depends on floating-point rounding.
+pub struct IntQuadtree<T> {
+    boundary: IntRectangle,
+    items: Vec<T>,
+    children: Option<[Box<IntQuadtree<T>>; 4]>,
+    options: IntOptions,
+    count: usize,
+}
+
+impl<T: IntPosition> IntQuadtree<T> {
+    pub fn new(boundary: IntRectangle) -> Self {
+        Self::with_options(boundary, Default::default())
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `boundary` does not have a positive width and height, or if
+    /// its far edges do not fit in an `i64`.
+    pub fn with_options(boundary: IntRectangle, options: IntOptions) -> Self {
+        let IntRectangle {
+            x,
+            y,
+            width,
+            height,
+        } = boundary;
+        assert!(
+            width > 0
+                && height > 0
+                && x.checked_add(width).is_some()
+                && y.checked_add(height).is_some(),
+            "quadtree boundary must have a positive area within i64: {:?}",
+            boundary
+        );
+        Self::node(boundary, options)
+    }
+
+    fn node(boundary: IntRectangle, options: IntOptions) -> Self {
+        Self {
+            boundary,
+            items: Vec::new(),
+            children: None,
+            options,
+            count: 0,
+        }
+    }
+
+    pub fn put(&mut self, item: T) {
+        if !self.boundary.contains(&item.position()) {
+            return;
+        }
+        self.insert(item);
+    }
+
+    pub fn query(&self, range: IntRectangle) -> Vec<&T> {
+        let mut items = Vec::new();
+        self.visit(&range, &mut items);
+        items
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.children = None;
+        self.count = 0;
+    }
+
+    fn insert(&mut self, item: T) {
+        self.count += 1;
+        if self.children.is_none()
+            && (self.items.len() < self.options.max_items
+                || self.options.depth >= self.options.max_depth
+                || self.boundary.width < 2
+                || self.boundary.height < 2)
+        {
+            self.items.push(item);
+            return;
+        }
+
+        if self.children.is_none() {
+            let mut children = self.subdivide();
+            for it in std::mem::take(&mut self.items) {
+                children[self.quadrant(&it.position())].insert(it);
+            }
+            self.children = Some(children);
+        }
+
+        let quadrant = self.quadrant(&item.position());
+        if let Some(ref mut children) = self.children {
+            children[quadrant].insert(item);
+        }
+    }
+
+    fn visit<'a>(&'a self, range: &IntRectangle, items: &mut Vec<&'a T>) {
+        if !self.boundary.intersects(range) {
+            return;
+        }
+        for item in &self.items {
+            if range.contains(&item.position()) {
+                items.push(item);
+            }
+        }
+        if let Some(ref children) = self.children {
+            for child in children {
+                child.visit(range, items);
+            }
+        }
+    }
+
+    fn quadrant(&self, point: &IntPoint) -> usize {
+        let (mid_x, mid_y) = self.midpoint();
+        match (point.x >= mid_x, point.y >= mid_y) {
+            (false, false) => 0,
+            (true, false) => 1,
+            (true, true) => 2,
+            (false, true) => 3,
+        }
+    }
+
+    fn midpoint(&self) -> (i64, i64) {
+        (
+            self.boundary.x + self.boundary.width / 2,
+            self.boundary.y + self.boundary.height / 2,
+        )
+    }
+
+    fn subdivide(&self) -> [Box<IntQuadtree<T>>; 4] {
+        let IntRectangle {
+            x,
+            y,
+            width,
+            height,
+        } = self.boundary;
+        let (mid_x, mid_y) = self.midpoint();
+        let options = IntOptions {
+            depth: self.options.depth + 1,
+            ..self.options
+        };
+        let child = |x0, y0, x1, y1| {
+            Box::new(IntQuadtree::node(
+                IntRectangle::new(x0, y0, x1 - x0, y1 - y0),
+                options,
+            ))
+        };
+        [
+            child(x, y, mid_x, mid_y),
+            child(mid_x, y, x + width, mid_y),
+            child(mid_x, mid_y, x + width, y + height),
+            child(x, mid_y, mid_x, y + height),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subdivide_odd_sizes() {
+        let qt = IntQuadtree::<IntItem<()>>::new(IntRectangle::new(0, 0, 5, 3));
+        let children = qt.subdivide();
+        let bounds: Vec<IntRectangle> = children.iter().map(|c| c.boundary).collect();
+        assert_eq!(
+            bounds,
+            vec![
+                IntRectangle::new(0, 0, 2, 1),
+                IntRectangle::new(2, 0, 3, 1),
+                IntRectangle::new(2, 1, 3, 2),
+                IntRectangle::new(0, 1, 2, 2),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "within i64")]
+    fn test_boundary_overflow() {
+        IntQuadtree::<IntItem<()>>::new(IntRectangle::new(i64::MAX - 1, 0, 2, 1));
+    }
+}
@@ -0,0 +1,167 @@
+use std::slice;
+use std::sync::atomic::Ordering;
+
+use crate::{morton, Point, Position, Quadtree, Rectangle};
+
+const CURVE_ORDER: u32 = 16;
+
+/// Lazily yields the items inside a range together with their positions,
+/// in the same order as `Quadtree::query`.
+pub struct QueryEntries<'a, T, D = ()> {
+    range: Rectangle,
+    stack: Vec<&'a Quadtree<T, D>>,
+    items: slice::Iter<'a, T>,
+}
+
+impl<'a, T: Position, D: Default> Iterator for QueryEntries<'a, T, D> {
+    type Item = (Point, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            for item in &mut self.items {
+                let point = item.position();
+                if self.range.contains_point(&point) {
+                    return Some((point, item));
+                }
+            }
+            let node = self.stack.pop()?;
+            if let Some(ref metrics) = node.metrics {
+                metrics.nodes_visited.fetch_add(1, Ordering::Relaxed);
+            }
+            match node.children {
+                Some(ref children) => {
+                    if node.intersects(&self.range, &node.bounds()) {
+                        self.stack
+                            .extend(children.iter().rev().map(|child| &**child));
+                    }
+                }
+                None => {
+                    if let Some(ref metrics) = node.metrics {
+                        metrics
+                            .items_scanned
+                            .fetch_add(node.items.len() as u64, Ordering::Relaxed);
+                    }
+                    self.items = node.items.iter();
+                }
+            }
+        }
+    }
+}
+
+/// Yields every stored item in traversal order.
+pub struct Iter<'a, T, D = ()> {
+    stack: Vec<&'a Quadtree<T, D>>,
+    items: slice::Iter<'a, T>,
+}
+
+impl<'a, T, D> Iterator for Iter<'a, T, D> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(item);
+            }
+            let node = self.stack.pop()?;
+            self.items = node.items.iter();
+            if let Some(ref children) = node.children {
+                self.stack
+                    .extend(children.iter().rev().map(|child| &**child));
+            }
+        }
+    }
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    pub fn iter(&self) -> Iter<'_, T, D> {
+        Iter {
+            stack: vec![self],
+            items: [].iter(),
+        }
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Point> + '_ {
+        self.iter().map(Position::position)
+    }
+
+    pub fn values(&self) -> Iter<'_, T, D> {
+        self.iter()
+    }
+
+    /// Yields all items sorted along a Z-order (Morton) curve over the tree
+    /// boundary. Items sharing a curve cell keep their traversal order.
+    pub fn iter_zorder(&self) -> impl Iterator<Item = &T> {
+        let bounds = self.bounds();
+        self.sorted_by_key(move |point| morton(&bounds, point))
+    }
+
+    /// Yields all items sorted along a Hilbert curve over the tree boundary,
+    /// which unlike Z-order never jumps between distant cells.
+    pub fn iter_hilbert(&self) -> impl Iterator<Item = &T> {
+        let bounds = self.bounds();
+        self.sorted_by_key(move |point| hilbert(&bounds, point))
+    }
+
+    fn sorted_by_key<F: Fn(&Point) -> u32>(&self, key: F) -> impl Iterator<Item = &T> {
+        let mut keyed: Vec<(u32, &T)> = self
+            .iter()
+            .map(|item| (key(&item.position()), item))
+            .collect();
+        keyed.sort_by_key(|&(key, _)| key);
+        keyed.into_iter().map(|(_, item)| item)
+    }
+
+    pub fn query_entries(&self, range: Rectangle) -> Vec<(Point, &T)> {
+        self.query_entries_iter(range).collect()
+    }
+
+    pub fn query_entries_iter(&self, range: Rectangle) -> QueryEntries<'_, T, D> {
+        if let Some(ref metrics) = self.metrics {
+            metrics.queries.fetch_add(1, Ordering::Relaxed);
+        }
+        QueryEntries {
+            range,
+            stack: vec![self],
+            items: [].iter(),
+        }
+    }
+}
+
+impl<'a, T: Position, D: Default> IntoIterator for &'a Quadtree<T, D> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, D>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Returns the distance of `point` along a Hilbert curve of order 16
+/// spanning `bounds`.
+fn hilbert(bounds: &Rectangle, point: &Point) -> u32 {
+    let side = 1u32 << CURVE_ORDER;
+    let scale = |v: f64, origin: f64, size: f64| {
+        let v = if size > 0.0 { (v - origin) / size } else { 0.0 };
+        (v.clamp(0.0, 1.0) * f64::from(side - 1)) as u32
+    };
+    let (mut x, mut y) = (
+        scale(point.x, bounds.x, bounds.width),
+        scale(point.y, bounds.y, bounds.height),
+    );
+    let mut d = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = u32::from(x & s > 0);
+        let ry = u32::from(y & s > 0);
+        d += s * s * ((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    d
+}
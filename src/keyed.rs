@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+
+use crate::{morton, NodeRef, Options, Point, Position, Quadtree, Rectangle};
+
+/// Identifies an item by its slot and the generation the slot had when the
+/// item was stored, so ids of removed items never address a reused slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ItemId {
+    index: u32,
+    generation: u32,
+}
+
+/// Identifies a region registered with `KeyedQuadtree::subscribe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u32);
+
+/// A change seen by a subscribed region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionEvent {
+    /// The item was inserted in, or moved into, the region.
+    Entered(ItemId),
+    /// The item was removed from, or moved out of, the region.
+    Left(ItemId),
+    /// The item moved and stayed inside the region.
+    Moved(ItemId),
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<(Point, T)>,
+}
+
+struct Entry {
+    id: ItemId,
+    point: Point,
+}
+
+impl Position for Entry {
+    fn position(&self) -> Point {
+        self.point
+    }
+}
+
+/// A quadtree that owns its payloads in a slot map and hands out stable
+/// `ItemId`s for them.
+pub struct KeyedQuadtree<T> {
+    tree: Quadtree<Entry>,
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    subscriptions: Vec<(SubscriptionId, Rectangle)>,
+    next_subscription: u32,
+    events: Vec<(SubscriptionId, RegionEvent)>,
+}
+
+impl<T> KeyedQuadtree<T> {
+    pub fn new(boundary: Rectangle) -> Self {
+        Self::with_options(boundary, Default::default())
+    }
+
+    pub fn with_options(boundary: Rectangle, options: Options) -> Self {
+        Self {
+            tree: Quadtree::with_options(boundary, options),
+            slots: Vec::new(),
+            free: Vec::new(),
+            subscriptions: Vec::new(),
+            next_subscription: 0,
+            events: Vec::new(),
+        }
+    }
+
+    /// Stores `data` at `point`, or returns `None` when the point lies
+    /// outside the tree boundary.
+    pub fn insert(&mut self, point: Point, data: T) -> Option<ItemId> {
+        if !self.tree._contains(&point, &self.tree.bounds()) {
+            return None;
+        }
+        let id = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.value = Some((point, data));
+                ItemId {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: Some((point, data)),
+                });
+                ItemId {
+                    index: self.slots.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        };
+        let evicted = self.tree.put_evicting(Entry { id, point });
+        self.notify(id, None, Some(point));
+        self.release_evicted(evicted);
+        Some(id)
+    }
+
+    pub fn get_by_id(&self, id: ItemId) -> Option<&T> {
+        self.slot(id).map(|(_, data)| data)
+    }
+
+    pub fn get_by_id_mut(&mut self, id: ItemId) -> Option<&mut T> {
+        match self.slots.get_mut(id.index as usize) {
+            Some(Slot {
+                generation,
+                value: Some((_, data)),
+            }) if *generation == id.generation => Some(data),
+            _ => None,
+        }
+    }
+
+    pub fn position_of(&self, id: ItemId) -> Option<Point> {
+        self.slot(id).map(|(point, _)| *point)
+    }
+
+    /// Returns the leaf cell the item lives in. Comparing it with
+    /// `cell_key_at` for a new position tells whether moving the item would
+    /// change cells.
+    pub fn cell_key_of(&self, id: ItemId) -> Option<NodeRef> {
+        self.tree.leaf_at(self.position_of(id)?)
+    }
+
+    /// Returns the leaf cell covering `point`.
+    pub fn cell_key_at(&self, point: Point) -> Option<NodeRef> {
+        self.tree.leaf_at(point)
+    }
+
+    /// Returns the quadrants leading from the root to the cell of the item.
+    pub fn path_to(&self, id: ItemId) -> Option<Vec<usize>> {
+        self.cell_key_of(id).map(|key| key.quadrants())
+    }
+
+    pub fn remove_by_id(&mut self, id: ItemId) -> Option<T> {
+        self.take(id).map(|(_, data)| data)
+    }
+
+    /// Removes the item and hands back its position along with the owned
+    /// payload, ready to be inserted into another container without `Clone`.
+    pub fn take(&mut self, id: ItemId) -> Option<(Point, T)> {
+        let point = self.position_of(id)?;
+        self.tree.remove_where(&point, &mut |entry| entry.id == id);
+        self.release(id)
+    }
+
+    /// Frees the slot of an item already gone from the tree. A slot whose
+    /// generation is exhausted is retired instead of reused, so that no id
+    /// ever matches a later item.
+    fn release(&mut self, id: ItemId) -> Option<(Point, T)> {
+        let slot = &mut self.slots[id.index as usize];
+        let (point, data) = slot.value.take()?;
+        if let Some(generation) = slot.generation.checked_add(1) {
+            slot.generation = generation;
+            self.free.push(id.index);
+        }
+        self.notify(id, Some(point), None);
+        Some((point, data))
+    }
+
+    /// Frees the slots of the entries evicted by the tree to stay within
+    /// `Options::max_total_items`.
+    fn release_evicted(&mut self, evicted: Vec<Entry>) {
+        for entry in evicted {
+            self.release(entry.id);
+        }
+    }
+
+    /// Relocates many items at once. Removals and reinsertions are each
+    /// processed in spatial order so consecutive updates hit the same nodes,
+    /// and the nodes left under-full along the vacated paths are only
+    /// collapsed once at the end. When an id appears several times its last
+    /// target wins; moves with a stale id or a target outside the boundary
+    /// are ignored.
+    pub fn apply_moves<I: IntoIterator<Item = (ItemId, Point)>>(&mut self, moves: I) {
+        let mut targets: Vec<(ItemId, Point)> = Vec::new();
+        let mut seen: HashMap<ItemId, usize> = HashMap::new();
+        for (id, to) in moves {
+            match seen.get(&id) {
+                Some(&index) => targets[index].1 = to,
+                None => {
+                    seen.insert(id, targets.len());
+                    targets.push((id, to));
+                }
+            }
+        }
+        let bounds = self.tree.bounds();
+        let mut moves: Vec<(ItemId, Point, Point)> = targets
+            .into_iter()
+            .filter_map(|(id, to)| {
+                let from = self.position_of(id)?;
+                if from == to || !self.tree._contains(&to, &bounds) {
+                    return None;
+                }
+                Some((id, from, to))
+            })
+            .collect();
+
+        moves.sort_by_key(|&(_, from, _)| morton(&bounds, &from));
+        for &(id, from, to) in &moves {
+            self.tree
+                .remove_with(&from, &mut |entry| entry.id == id, true);
+            if let Some((point, _)) = self.slots[id.index as usize].value.as_mut() {
+                *point = to;
+            }
+        }
+
+        moves.sort_by_key(|&(_, _, to)| morton(&bounds, &to));
+        let mut evicted = Vec::new();
+        for &(id, from, to) in &moves {
+            evicted.extend(self.tree.put_evicting(Entry { id, point: to }));
+            self.notify(id, Some(from), Some(to));
+        }
+        self.release_evicted(evicted);
+        if !self.tree.options.defer_collapse {
+            for &(_, from, _) in &moves {
+                self.tree.collapse_toward(&from);
+            }
+        }
+    }
+
+    pub fn query(&self, range: Rectangle) -> Vec<(ItemId, &T)> {
+        self.tree
+            .query(range)
+            .into_iter()
+            .filter_map(|entry| self.get_by_id(entry.id).map(|data| (entry.id, data)))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Starts recording the items entering, leaving and moving within
+    /// `region`. Events accumulate until `take_events`.
+    pub fn subscribe(&mut self, region: Rectangle) -> SubscriptionId {
+        let id = SubscriptionId(self.next_subscription);
+        self.next_subscription += 1;
+        self.subscriptions.push((id, region));
+        id
+    }
+
+    /// Stops recording events for `id`. Returns `false` if it was not
+    /// subscribed.
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let len = self.subscriptions.len();
+        self.subscriptions
+            .retain(|&(subscription, _)| subscription != id);
+        self.subscriptions.len() < len
+    }
+
+    /// Returns the events recorded since the previous call, in the order
+    /// the changes were made.
+    pub fn take_events(&mut self) -> Vec<(SubscriptionId, RegionEvent)> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn notify(&mut self, id: ItemId, from: Option<Point>, to: Option<Point>) {
+        let tree = &self.tree;
+        for &(subscription, ref region) in &self.subscriptions {
+            let inside =
+                |point: Option<Point>| point.is_some_and(|point| tree._contains(&point, region));
+            let event = match (inside(from), inside(to)) {
+                (true, true) => RegionEvent::Moved(id),
+                (false, true) => RegionEvent::Entered(id),
+                (true, false) => RegionEvent::Left(id),
+                (false, false) => continue,
+            };
+            self.events.push((subscription, event));
+        }
+    }
+
+    fn slot(&self, id: ItemId) -> Option<&(Point, T)> {
+        match self.slots.get(id.index as usize) {
+            Some(slot) if slot.generation == id.generation => slot.value.as_ref(),
+            _ => None,
+        }
+    }
+}
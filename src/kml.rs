@@ -0,0 +1,62 @@
+use std::fmt::Write;
+
+use crate::{DebugDraw, Point, Position, Quadtree, Rectangle};
+
+/// Collects the KML polygon of every node, tagged with its depth.
+struct NodePolygons(String);
+
+impl DebugDraw for NodePolygons {
+    fn draw_rect(&mut self, bounds: Rectangle, depth: u8) {
+        let (left, top) = (bounds.x, bounds.y);
+        let (right, bottom) = (bounds.x + bounds.width, bounds.y + bounds.height);
+        let _ = writeln!(
+            self.0,
+            "<Placemark><name>node depth {}</name><Polygon><outerBoundaryIs><LinearRing>\
+             <coordinates>{},{} {},{} {},{} {},{} {},{}</coordinates>\
+             </LinearRing></outerBoundaryIs></Polygon></Placemark>",
+            depth, left, top, right, top, right, bottom, left, bottom, left, top
+        );
+    }
+
+    fn draw_point(&mut self, _point: Point) {}
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Writes a KML document with a placemark per item, reading `x` as the
+    /// longitude and `y` as the latitude, and, when `node_bounds` is set, a
+    /// polygon per node.
+    pub fn to_kml(&self, node_bounds: bool) -> String {
+        self.to_kml_with(node_bounds, |_| String::new())
+    }
+
+    /// Same as `to_kml`, naming each item's placemark with `name`.
+    pub fn to_kml_with<F: Fn(&T) -> String>(&self, node_bounds: bool, name: F) -> String {
+        let mut kml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n",
+        );
+        for item in self.iter() {
+            let point = item.position();
+            let _ = writeln!(
+                kml,
+                "<Placemark><name>{}</name><Point><coordinates>{},{}</coordinates></Point></Placemark>",
+                escape(&name(item)),
+                point.x,
+                point.y
+            );
+        }
+        if node_bounds {
+            let mut polygons = NodePolygons(String::new());
+            self.debug_draw(&mut polygons);
+            kml.push_str(&polygons.0);
+        }
+        kml.push_str("</Document>\n</kml>\n");
+        kml
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
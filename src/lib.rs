@@ -1,8 +1,131 @@
 #![crate_type = "lib"]
 #![crate_name = "quadtree"]
 
+use std::cmp;
+use std::collections::BTreeMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+mod aggregate;
+mod analysis;
+#[cfg(feature = "bumpalo")]
+mod arena;
+mod batch;
+#[cfg(feature = "bevy")]
+pub mod bevy;
+mod cluster;
+mod compressed;
+#[cfg(feature = "csv")]
+mod csv;
+mod cursor;
+mod debug_draw;
+mod double_buffer;
+#[cfg(feature = "egui")]
+mod egui;
+mod eviction;
+mod expiry;
+mod explain;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod flat;
+mod grid;
+mod hybrid;
+mod index;
+mod integer;
+mod iter;
+mod keyed;
+mod kml;
+mod linear;
+mod log;
+mod maintenance;
+mod metrics;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod nearest;
+mod node_data;
+mod normalized;
+mod paged;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod persist;
+#[cfg(feature = "plotters")]
+mod plot;
+mod precision;
+mod predictive;
+#[cfg(feature = "proptest")]
+mod proptest;
+#[cfg(feature = "python")]
+mod python;
+mod radius;
+mod ray;
+mod rebuild;
+mod region;
+#[cfg(feature = "rand")]
+mod sampling;
+mod scaled;
+mod scratch;
+#[cfg(feature = "shapefile")]
+mod shapefile;
+mod sync;
+mod transform;
+mod visibility;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+#[cfg(feature = "egui")]
+pub use self::egui::Inspector;
+pub use aggregate::{
+    Aggregate, AggregateQuadtree, Count, Layers, Max, Min, OrderedQuery, Sum, WeightSums,
+    WeightedCentroid,
+};
+pub use analysis::{CountEstimate, Kernel, NodeSummary};
+#[cfg(feature = "bumpalo")]
+pub use arena::ArenaQuadtree;
+pub use compressed::CompressedQuadtree;
+pub use cursor::{Cursor, CursorMut};
+pub use debug_draw::DebugDraw;
+pub use double_buffer::DoubleBufferedQuadtree;
+pub use eviction::{EvictionPolicy, FarthestFrom, OldestFirst};
+pub use explain::QueryTrace;
+pub use flat::{FlatItem, FlatNode, FlatQuadtree, NO_CHILDREN};
+pub use grid::UniformGrid;
+pub use hybrid::HybridIndex;
+pub use index::SpatialIndex;
+pub use integer::{IntItem, IntOptions, IntPoint, IntPosition, IntQuadtree, IntRectangle};
+pub use iter::{Iter, QueryEntries};
+pub use keyed::{ItemId, KeyedQuadtree, RegionEvent, SubscriptionId};
+pub use linear::{LinearQuadtree, MortonKey};
+pub use log::LoggedQuadtree;
+use metrics::Counters;
+pub use metrics::Metrics;
+#[cfg(feature = "mmap")]
+pub use mmap::MmapQuadtree;
+pub use nearest::{Chebyshev, Euclidean, FnMetric, Manhattan, Metric};
+pub use node_data::NodeRef;
+pub use normalized::NormalizedQuadtree;
+pub use paged::PagedQuadtree;
+pub use persist::Persist;
+#[cfg(feature = "plotters")]
+pub use plot::PlotStyle;
+pub use precision::PrecisionReport;
+pub use predictive::{Moving, PredictiveQuadtree};
+#[cfg(feature = "proptest")]
+pub use proptest::NaiveIndex;
+pub use rebuild::{RebuildingQuadtree, SnapshotReader};
+pub use region::RegionQuadtree;
+#[cfg(feature = "rand")]
+pub use sampling::poisson_disk;
+pub use scaled::ScaledQuadtree;
+pub use scratch::QueryScratch;
+pub use sync::{Delta, Version, VersionedQuadtree};
+pub use transform::{Affine, TransformedQuadtree};
+pub use visibility::{Occluder, Occlusion};
+#[cfg(feature = "wasm")]
+pub use wasm::WasmQuadtree;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Point {
@@ -14,6 +137,14 @@ impl Point {
     pub fn new(x: f64, y: f64) -> Self {
         Self { x, y }
     }
+
+    pub fn x(&self) -> f64 {
+        self.x
+    }
+
+    pub fn y(&self) -> f64 {
+        self.y
+    }
 }
 
 impl fmt::Display for Point {
@@ -24,14 +155,176 @@ impl fmt::Display for Point {
 
 impl PartialEq<Point> for Point {
     fn eq(&self, other: &Point) -> bool {
-        self.x == other.x && self.y == other.y
+        self.cmp(other) == cmp::Ordering::Equal
     }
 }
 
 impl Eq for Point {}
 
+impl PartialOrd for Point {
+    fn partial_cmp(&self, other: &Point) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Orders points by `x`, then `y`, using `f64::total_cmp` with `-0.0` and
+/// `0.0` treated as the same coordinate.
+impl Ord for Point {
+    fn cmp(&self, other: &Point) -> cmp::Ordering {
+        canonical(self.x)
+            .total_cmp(&canonical(other.x))
+            .then_with(|| canonical(self.y).total_cmp(&canonical(other.y)))
+    }
+}
+
+impl Hash for Point {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        canonical(self.x).to_bits().hash(state);
+        canonical(self.y).to_bits().hash(state);
+    }
+}
+
+fn canonical(value: f64) -> f64 {
+    if value == 0.0 {
+        0.0
+    } else {
+        value
+    }
+}
+
 pub trait Position {
     fn position(&self) -> Point;
+
+    /// Share of a leaf's `Options::max_items` budget taken by this item.
+    /// Heavy items, such as large polygons, can report more than the default
+    /// of 1 so the leaves holding them subdivide sooner.
+    fn cost(&self) -> usize {
+        1
+    }
+
+    /// How far the item's footprint extends around its position, such as its
+    /// `Radius`. Every node tracks the largest reach stored below it, so the
+    /// radius-aware queries also find footprints wider than
+    /// `Options::max_radius`.
+    fn reach(&self) -> f64 {
+        0.0
+    }
+}
+
+impl Position for Point {
+    fn position(&self) -> Point {
+        *self
+    }
+}
+
+impl Position for (f64, f64) {
+    fn position(&self) -> Point {
+        Point::new(self.0, self.1)
+    }
+}
+
+impl Position for [f64; 2] {
+    fn position(&self) -> Point {
+        Point::new(self[0], self[1])
+    }
+}
+
+impl<T: Position + ?Sized> Position for &T {
+    fn position(&self) -> Point {
+        (**self).position()
+    }
+
+    fn cost(&self) -> usize {
+        (**self).cost()
+    }
+
+    fn reach(&self) -> f64 {
+        (**self).reach()
+    }
+}
+
+impl<T: Position + ?Sized> Position for Box<T> {
+    fn position(&self) -> Point {
+        (**self).position()
+    }
+
+    fn cost(&self) -> usize {
+        (**self).cost()
+    }
+
+    fn reach(&self) -> f64 {
+        (**self).reach()
+    }
+}
+
+impl<T: Position + ?Sized> Position for Rc<T> {
+    fn position(&self) -> Point {
+        (**self).position()
+    }
+
+    fn cost(&self) -> usize {
+        (**self).cost()
+    }
+
+    fn reach(&self) -> f64 {
+        (**self).reach()
+    }
+}
+
+impl<T: Position + ?Sized> Position for Arc<T> {
+    fn position(&self) -> Point {
+        (**self).position()
+    }
+
+    fn cost(&self) -> usize {
+        (**self).cost()
+    }
+
+    fn reach(&self) -> f64 {
+        (**self).reach()
+    }
+}
+
+/// Receives the bounds of the node affected by every structural change.
+pub trait Observer {
+    fn on_insert(&mut self, _bounds: Rectangle) {}
+
+    fn on_remove(&mut self, _bounds: Rectangle) {}
+
+    fn on_subdivide(&mut self, _bounds: Rectangle) {}
+
+    fn on_collapse(&mut self, _bounds: Rectangle) {}
+}
+
+impl Observer for () {}
+
+pub trait Weighted {
+    fn weight(&self) -> f64;
+}
+
+/// Items with a circular footprint around their position, for the
+/// radius-aware queries. Radii larger than `Options::max_radius` must also be
+/// reported as `Position::reach`, or the queries may miss those items.
+pub trait Radius {
+    fn radius(&self) -> f64;
+}
+
+/// Items drawn or picked in order, such as sprites by z-index. Higher
+/// priorities come first in ordered queries.
+pub trait Priority {
+    fn priority(&self) -> f64;
+}
+
+/// Items that stop being valid at some time, in the caller's units. An
+/// item has expired once `now` reaches its expiry.
+pub trait Expiring {
+    fn expires_at(&self) -> f64;
+}
+
+/// Items belonging to collision-style layers, one per bit of the mask.
+/// Layer queries only return items sharing a bit with the queried mask.
+pub trait Layered {
+    fn layers(&self) -> u32;
 }
 
 #[derive(Debug)]
@@ -63,6 +356,93 @@ impl<'a, T> Position for Item<'a, T> {
     }
 }
 
+impl<'a, T: Weighted> Weighted for Item<'a, T> {
+    fn weight(&self) -> f64 {
+        self.data.weight()
+    }
+}
+
+impl<'a, T: Radius> Radius for Item<'a, T> {
+    fn radius(&self) -> f64 {
+        self.data.radius()
+    }
+}
+
+impl<'a, T: Priority> Priority for Item<'a, T> {
+    fn priority(&self) -> f64 {
+        self.data.priority()
+    }
+}
+
+impl<'a, T: Expiring> Expiring for Item<'a, T> {
+    fn expires_at(&self) -> f64 {
+        self.data.expires_at()
+    }
+}
+
+impl<'a, T: Layered> Layered for Item<'a, T> {
+    fn layers(&self) -> u32 {
+        self.data.layers()
+    }
+}
+
+impl<'a, T: Moving> Moving for Item<'a, T> {
+    fn velocity(&self) -> (f64, f64) {
+        self.data.velocity()
+    }
+}
+
+/// An item stored at its position rounded to a lattice, so that range
+/// queries, nearest searches and point lookups all see the lattice position.
+/// Built by `Quadtree::put_snapped` from `Options::snap`.
+#[derive(Debug, Clone)]
+pub struct Snapped<T> {
+    point: Point,
+    item: T,
+}
+
+impl<T: Position> Snapped<T> {
+    /// Wraps `item` at its position rounded to the nearest multiple of `cell`
+    /// on both axes; a `cell` of zero keeps the position as is.
+    pub fn new(item: T, cell: f64) -> Self {
+        Self {
+            point: snap(&item.position(), cell),
+            item,
+        }
+    }
+}
+
+impl<T> Snapped<T> {
+    pub fn into_inner(self) -> T {
+        self.item
+    }
+}
+
+impl<T> Deref for Snapped<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.item
+    }
+}
+
+impl<T> Position for Snapped<T> {
+    fn position(&self) -> Point {
+        self.point
+    }
+}
+
+fn snap(point: &Point, cell: f64) -> Point {
+    if cell > 0.0 {
+        Point::new(
+            (point.x / cell).round() * cell,
+            (point.y / cell).round() * cell,
+        )
+    } else {
+        *point
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Rectangle {
     x: f64,
@@ -72,7 +452,19 @@ pub struct Rectangle {
 }
 
 impl Rectangle {
+    /// Creates a rectangle, flipping negative extents so the rectangle always
+    /// spans from its smallest corner.
     pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        let (x, width) = if width < 0.0 {
+            (x + width, -width)
+        } else {
+            (x, width)
+        };
+        let (y, height) = if height < 0.0 {
+            (y + height, -height)
+        } else {
+            (y, height)
+        };
         Self {
             x,
             y,
@@ -80,6 +472,165 @@ impl Rectangle {
             height,
         }
     }
+
+    pub fn from_center(center: Point, half_width: f64, half_height: f64) -> Self {
+        Self::new(
+            center.x - half_width,
+            center.y - half_height,
+            2.0 * half_width,
+            2.0 * half_height,
+        )
+    }
+
+    /// Builds the rectangle spanned by two opposite corners given in any order.
+    pub fn from_corners(a: Point, b: Point) -> Self {
+        Self::new(
+            a.x.min(b.x),
+            a.y.min(b.y),
+            (a.x - b.x).abs(),
+            (a.y - b.y).abs(),
+        )
+    }
+
+    pub fn x(&self) -> f64 {
+        self.x
+    }
+
+    pub fn y(&self) -> f64 {
+        self.y
+    }
+
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    pub fn height(&self) -> f64 {
+        self.height
+    }
+
+    /// Returns the overlap of both rectangles. Rectangles that only share an
+    /// edge intersect in a zero-area rectangle.
+    pub fn intersection(&self, other: &Rectangle) -> Option<Rectangle> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        if right < x || bottom < y {
+            return None;
+        }
+        Some(Rectangle::new(x, y, right - x, bottom - y))
+    }
+
+    /// Whether `point` lies inside the rectangle or on its edges.
+    pub fn contains_point(&self, point: &Point) -> bool {
+        point.x >= self.x
+            && point.x <= self.x + self.width
+            && point.y >= self.y
+            && point.y <= self.y + self.height
+    }
+
+    /// The quadrant holding `point`, numbered as the children of a node:
+    /// 0 at (x, y), 1 at (x + w, y), 2 at (x + w, y + h) and 3 at (x, y + h).
+    /// Points on a midline belong to the quadrant nearer to (x, y).
+    pub fn quadrant(&self, point: &Point) -> usize {
+        let east = point.x > self.x + self.width / 2.0;
+        let south = point.y > self.y + self.height / 2.0;
+        match (east, south) {
+            (false, false) => 0,
+            (true, false) => 1,
+            (true, true) => 2,
+            (false, true) => 3,
+        }
+    }
+
+    /// The bounds of `quadrant`, numbered as in `quadrant`.
+    pub fn quadrant_cell(&self, quadrant: usize) -> Rectangle {
+        let (w, h) = (self.width / 2.0, self.height / 2.0);
+        let (x, y) = match quadrant {
+            0 => (self.x, self.y),
+            1 => (self.x + w, self.y),
+            2 => (self.x + w, self.y + h),
+            _ => (self.x, self.y + h),
+        };
+        Rectangle::new(x, y, w, h)
+    }
+
+    /// Grows the rectangle by `margin` on every side; a negative margin shrinks it.
+    pub fn inflated(&self, margin: f64) -> Rectangle {
+        Rectangle::new(
+            self.x - margin,
+            self.y - margin,
+            self.width + 2.0 * margin,
+            self.height + 2.0 * margin,
+        )
+    }
+
+    pub fn translated(&self, dx: f64, dy: f64) -> Rectangle {
+        Rectangle::new(self.x + dx, self.y + dy, self.width, self.height)
+    }
+
+    /// Scales the rectangle by `factor` around its center.
+    pub fn scaled(&self, factor: f64) -> Rectangle {
+        let (width, height) = (self.width * factor, self.height * factor);
+        Rectangle::new(
+            self.x + (self.width - width) / 2.0,
+            self.y + (self.height - height) / 2.0,
+            width,
+            height,
+        )
+    }
+
+    fn has_area(&self) -> bool {
+        self.width > 0.0 && self.height > 0.0 && self.width.is_finite() && self.height.is_finite()
+    }
+
+    /// Returns the smallest rectangle enclosing both rectangles.
+    pub fn union(&self, other: &Rectangle) -> Rectangle {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rectangle::new(
+            x,
+            y,
+            (self.x + self.width).max(other.x + other.width) - x,
+            (self.y + self.height).max(other.y + other.height) - y,
+        )
+    }
+
+    /// Clips the segment from `a` to `b` to this rectangle (edges included)
+    /// with the Liang-Barsky algorithm. Returns `None` if no part of the
+    /// segment lies inside.
+    pub fn clip_segment(&self, a: Point, b: Point) -> Option<(Point, Point)> {
+        let (t0, t1) = self.segment_span(&a, &b)?;
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        Some((
+            Point::new(a.x + t0 * dx, a.y + t0 * dy),
+            Point::new(a.x + t1 * dx, a.y + t1 * dy),
+        ))
+    }
+
+    /// The parameters, between 0 and 1, at which the segment from `a` to `b`
+    /// enters and leaves this rectangle.
+    fn segment_span(&self, a: &Point, b: &Point) -> Option<(f64, f64)> {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let (mut t0, mut t1) = (0.0_f64, 1.0_f64);
+        for &(p, q) in &[
+            (-dx, a.x - self.x),
+            (dx, self.x + self.width - a.x),
+            (-dy, a.y - self.y),
+            (dy, self.y + self.height - a.y),
+        ] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+            } else if p < 0.0 {
+                t0 = t0.max(q / p);
+            } else {
+                t1 = t1.min(q / p);
+            }
+        }
+        (t0 <= t1).then_some((t0, t1))
+    }
 }
 
 impl fmt::Display for Rectangle {
@@ -95,10 +646,36 @@ impl fmt::Display for Rectangle {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
 pub struct Options {
+    /// Budget of a leaf before it subdivides, summed over the items'
+    /// `Position::cost`, which is one per item unless overridden.
     pub max_items: usize,
     pub max_depth: u8,
     pub depth: u8,
+    pub track_dirty: bool,
+    pub collect_metrics: bool,
+    pub defer_collapse: bool,
+    /// Coordinates closer than this on both axes address the same location
+    /// when looking items up by point.
+    pub epsilon: f64,
+    /// When positive, items put with `Quadtree::put_snapped` are stored at
+    /// their position rounded to the nearest multiple of `snap` on both axes,
+    /// and point lookups round the queried point the same way. See
+    /// `Options::snap_to_grid`.
+    pub snap: f64,
+    /// Upper bound on the `Radius` of stored items that do not report it as
+    /// their `Position::reach`. Radius-aware queries widen their search by
+    /// this much, or by the largest reach stored if that is more.
+    pub max_radius: f64,
+    /// Keep the nodes freed by `clear` and collapses, and reuse them for the
+    /// next subdivisions of the same nodes instead of reallocating. Suits
+    /// trees that are cleared and refilled every frame.
+    pub reuse_nodes: bool,
+    /// When positive, the tree holds at most this many items: every insertion
+    /// that goes over the limit evicts the items chosen by the tree's
+    /// `EvictionPolicy`, oldest first unless another one is set.
+    pub max_total_items: usize,
 }
 
 impl Default for Options {
@@ -107,18 +684,125 @@ impl Default for Options {
             max_items: 20,
             max_depth: 3,
             depth: 0,
+            track_dirty: false,
+            collect_metrics: false,
+            defer_collapse: false,
+            epsilon: 0.0,
+            snap: 0.0,
+            max_radius: 0.0,
+            reuse_nodes: false,
+            max_total_items: 0,
         }
     }
 }
 
-pub struct Quadtree<T> {
+impl Options {
+    /// Rounds positions to a lattice of `cell` spacing: `Quadtree::put_snapped`
+    /// stores items at their rounded position, and `get`, `remove` and the
+    /// other point lookups round the queried point, so noisy copies of a
+    /// lattice position address the same location.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cell` is negative or not finite.
+    pub fn snap_to_grid(self, cell: f64) -> Options {
+        assert!(
+            cell.is_finite() && cell >= 0.0,
+            "grid cell must be finite and non-negative: {}",
+            cell
+        );
+        Options { snap: cell, ..self }
+    }
+
+    /// Returns default options with `max_items` and `max_depth` sized for
+    /// about `item_count` items in `boundary`: leaves are expected to hold
+    /// 8 to 16 items, with four extra levels of depth for clustered data.
+    /// The depth is capped so that the smallest cells stay well above the
+    /// floating-point resolution of the boundary coordinates.
+    pub fn auto(item_count: usize, boundary: Rectangle) -> Options {
+        const LEAF_ITEMS: usize = 16;
+        const CLUSTER_LEVELS: u8 = 4;
+        const MAX_DEPTH: u8 = 32;
+
+        let leaves = (item_count / (LEAF_ITEMS / 2)).max(1);
+        let mut depth = 0;
+        while depth < MAX_DEPTH && 4usize.saturating_pow(u32::from(depth)) < leaves {
+            depth += 1;
+        }
+        depth = (depth + CLUSTER_LEVELS).min(MAX_DEPTH);
+
+        let magnitude = [
+            boundary.x,
+            boundary.y,
+            boundary.x + boundary.width,
+            boundary.y + boundary.height,
+        ]
+        .iter()
+        .fold(1.0f64, |max, v| max.max(v.abs()));
+        let resolution = magnitude * f64::EPSILON * 1024.0;
+        let side = boundary.width.min(boundary.height);
+        while depth > 0 && side / 2f64.powi(i32::from(depth)) < resolution {
+            depth -= 1;
+        }
+
+        Options {
+            max_items: LEAF_ITEMS,
+            max_depth: depth,
+            ..Default::default()
+        }
+    }
+}
+
+/// A point quadtree. Traversals are deterministic: children are visited in
+/// the order (x, y), (x + w, y), (x + w, y + h), (x, y + h) and items of a
+/// node in insertion order, so the same sequence of operations always yields
+/// results in the same order.
+///
+/// The tree owns its items and only uses atomics for metrics, so it is `Send`
+/// and `Sync` whenever `T` and `D` are, and can be shared behind an `Arc`.
+pub struct Quadtree<T, D = ()> {
     x: f64,
     y: f64,
     width: f64,
     height: f64,
     items: Vec<T>,
-    children: Option<[Box<Quadtree<T>>; 4]>,
+    children: Option<[Box<Quadtree<T, D>>; 4]>,
     options: Options,
+    count: usize,
+    /// Sums of the x and y coordinates of the items below this node.
+    sum: (f64, f64),
+    extent: Option<Rectangle>,
+    /// Largest `Position::reach` of the items below this node.
+    reach: f64,
+    dirty: bool,
+    metrics: Option<Arc<Counters>>,
+    data: D,
+    spare: Option<[Box<Quadtree<T, D>>; 4]>,
+    /// Set on the root only, once it needs any of the state.
+    state: Option<Box<RootState>>,
+}
+
+/// State that belongs to a whole tree rather than to each node, kept out of
+/// the nodes so they stay small.
+#[derive(Default)]
+struct RootState {
+    observer: Option<Box<dyn Observer + Send + Sync>>,
+    /// Path to the next node of the current `optimize_step` pass.
+    maintenance: Vec<usize>,
+    eviction: Option<Box<dyn EvictionPolicy + Send + Sync>>,
+}
+
+impl<T, D> fmt::Debug for Quadtree<T, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Quadtree")
+            .field(
+                "boundary",
+                &Rectangle::new(self.x, self.y, self.width, self.height),
+            )
+            .field("len", &self.count)
+            .field("options", &self.options)
+            .finish()
+    }
 }
 
 impl<T: Position> Quadtree<T> {
@@ -131,7 +815,41 @@ impl<T: Position> Quadtree<T> {
         )
     }
 
+    /// # Panics
+    ///
+    /// Panics if `boundary` does not have a finite, positive width and height.
     pub fn with_options(boundary: Rectangle, options: Options) -> Self {
+        Self::with_node_data(boundary, options)
+    }
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Creates a tree carrying a `D` payload on every node. Nodes created by a
+    /// subdivision start with `D::default()` and their payloads are dropped
+    /// when the parent collapses.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `boundary` does not have a finite, positive width and height.
+    pub fn with_node_data(boundary: Rectangle, options: Options) -> Self {
+        assert!(
+            boundary.has_area(),
+            "quadtree boundary must have a finite, positive area: {}",
+            boundary
+        );
+        let metrics = if options.collect_metrics {
+            Some(Arc::new(Counters::default()))
+        } else {
+            None
+        };
+        let mut tree = Self::node(boundary, options, metrics);
+        if options.max_total_items > 0 {
+            tree.state_mut().eviction = Some(Box::new(OldestFirst::default()));
+        }
+        tree
+    }
+
+    fn node(boundary: Rectangle, options: Options, metrics: Option<Arc<Counters>>) -> Self {
         Self {
             x: boundary.x,
             y: boundary.y,
@@ -140,79 +858,604 @@ impl<T: Position> Quadtree<T> {
             items: Vec::new(),
             children: None,
             options,
+            count: 0,
+            sum: (0.0, 0.0),
+            extent: None,
+            reach: 0.0,
+            dirty: false,
+            metrics,
+            data: D::default(),
+            spare: None,
+            state: None,
         }
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(items = self.count))
+    )]
     pub fn put(&mut self, item: T) {
-        if !self.contains(&item) {
-            return;
-        }
+        self.put_evicting(item);
+    }
 
-        if self.children.is_none()
-            && self.items.len() < self.options.max_items
-            && self.options.depth < self.options.max_depth
+    /// Returns the first item stored at `point`, within `Options::epsilon`.
+    pub fn get(&self, point: Point) -> Option<&T> {
+        if !self._contains(&point, &self.bounds().inflated(self.tolerance())) {
+            return None;
+        }
+        if let Some(item) = self
+            .items
+            .iter()
+            .find(|it| self.matches(&it.position(), &point))
         {
-            self.items.push(item);
-            return;
+            return Some(item);
         }
+        self.children
+            .iter()
+            .flatten()
+            .filter(|child| child.reaches(&point))
+            .find_map(|child| child.get(point))
+    }
 
-        match self.children {
-            Some(ref mut children) => {
-                for child in children {
-                    if child.contains(&item) {
-                        child.items.push(item);
-                        break;
-                    }
+    /// Removes and returns the first item stored at `point`, within
+    /// `Options::epsilon`.
+    pub fn remove(&mut self, point: Point) -> Option<T> {
+        self.remove_where(&point, &mut |_| true)
+    }
+
+    /// Returns every item stored at `point`, within `Options::epsilon`, in
+    /// traversal order.
+    pub fn entries_at(&self, point: Point) -> Vec<&T> {
+        let mut entries = Vec::new();
+        if self.reaches(&point) {
+            self.collect_at(&point, &mut entries);
+        }
+        entries
+    }
+
+    /// Groups all items by their exact position, in ascending point order.
+    pub fn group_by_position(&self) -> impl Iterator<Item = (Point, Vec<&T>)> {
+        let mut groups: BTreeMap<Point, Vec<&T>> = BTreeMap::new();
+        self.visit(&self.bounds(), &mut |item| {
+            groups.entry(item.position()).or_default().push(item)
+        });
+        groups.into_iter()
+    }
+
+    /// Removes every item stored at `point`, within `Options::epsilon`.
+    pub fn remove_all_at(&mut self, point: Point) -> Vec<T> {
+        let mut removed = Vec::new();
+        while let Some(item) = self.remove(point) {
+            removed.push(item);
+        }
+        removed
+    }
+
+    /// Removes and returns the item inside `range` closest to its center, or
+    /// `None` if `range` holds no item.
+    pub fn pop_in_region(&mut self, range: Rectangle) -> Option<T> {
+        let center = Point::new(range.x + range.width / 2.0, range.y + range.height / 2.0);
+        let distance = |item: &T| {
+            let point = item.position();
+            (point.x - center.x).hypot(point.y - center.y)
+        };
+        let (point, target) = self
+            .query(range)
+            .into_iter()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+            .map(|item| (item.position(), item as *const T))?;
+        self.remove_where(&point, &mut |item| std::ptr::eq(item, target))
+    }
+
+    /// Stores `item`, replacing and returning the first item already stored
+    /// at its position (within `Options::epsilon`). An item stored at exactly
+    /// the same coordinates is swapped in place in a single descent.
+    pub fn insert_or_replace(&mut self, item: T) -> Option<T> {
+        if !self.contains(&item) {
+            return None;
+        }
+        let replaced = match self.take_observer() {
+            // Swapping in place would keep the evicted slot of the old item.
+            observer if self.eviction_mut().is_some() => {
+                if let Some(observer) = observer {
+                    self.set_observer(observer);
                 }
+                Err(item)
             }
-            None => {
-                self.items.push(item);
-                let mut children = self.subdivide();
-                while let Some(it) = self.items.pop() {
-                    for child in children.iter_mut() {
-                        if child.contains(&it) {
-                            child.items.push(it);
-                            break;
-                        }
-                    }
+            Some(mut observer) => {
+                let replaced = self.replace_exact(item, &mut *observer);
+                self.set_observer(observer);
+                replaced
+            }
+            None => self.replace_exact(item, &mut ()),
+        };
+        match replaced {
+            Ok(previous) => Some(previous),
+            Err(item) => {
+                let previous = self.remove(item.position());
+                self.put(item);
+                previous
+            }
+        }
+    }
+
+    /// Inserts the items of `iter` in chunks of `chunk` items. Each chunk is
+    /// routed down the tree in one pass, so a node subdivides at most once per
+    /// chunk and only one chunk is buffered at a time. Items outside the
+    /// boundary are skipped, as with `put`.
+    pub fn extend_streaming<I: IntoIterator<Item = T>>(&mut self, iter: I, chunk: usize) {
+        let bounds = self.bounds();
+        let mut iter = iter.into_iter();
+        loop {
+            let batch: Vec<T> = iter
+                .by_ref()
+                .filter(|item| self._contains(&item.position(), &bounds))
+                .take(chunk.max(1))
+                .collect();
+            if batch.is_empty() {
+                return;
+            }
+            if let Some(policy) = self.eviction_mut() {
+                batch
+                    .iter()
+                    .for_each(|item| policy.on_insert(item.position()));
+            }
+            match self.take_observer() {
+                Some(mut observer) => {
+                    self.insert_batch(batch, &mut *observer);
+                    self.set_observer(observer);
                 }
-                self.children = Some(children)
+                None => self.insert_batch(batch, &mut ()),
             }
+            self.evict();
+        }
+    }
+
+    pub fn set_observer(&mut self, observer: Box<dyn Observer + Send + Sync>) {
+        self.state_mut().observer = Some(observer);
+    }
+
+    pub fn take_observer(&mut self) -> Option<Box<dyn Observer + Send + Sync>> {
+        self.state.as_mut()?.observer.take()
+    }
+
+    pub fn clear(&mut self) {
+        if self.count > 0 {
+            self.dirty |= self.options.track_dirty;
+        }
+        self.items.clear();
+        self.release_children();
+        self.count = 0;
+        self.sum = (0.0, 0.0);
+        self.extent = None;
+        self.reach = 0.0;
+        if let Some(policy) = self.eviction_mut() {
+            policy.clear();
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(items = self.count))
+    )]
     pub fn query(&self, range: Rectangle) -> Vec<&T> {
+        let mut items = Vec::<&T>::new();
+        self.visit(&range, &mut |item| items.push(item));
+        items
+    }
+
+    pub fn query_radius(&self, center: Point, radius: f64) -> Vec<&T> {
+        let range = Rectangle::new(
+            center.x - radius,
+            center.y - radius,
+            2.0 * radius,
+            2.0 * radius,
+        );
+        let mut items = Vec::new();
+        self.visit(&range, &mut |item| {
+            let point = item.position();
+            if (point.x - center.x).hypot(point.y - center.y) <= radius {
+                items.push(item);
+            }
+        });
+        items
+    }
+
+    /// Returns the items inside `viewport` grown by `margin` on every side,
+    /// sorted by `y` then `x` so the draw order is stable between frames.
+    pub fn visible_items(&self, viewport: Rectangle, margin: f64) -> Vec<&T> {
+        let mut items = Vec::new();
+        self.visible_items_into(viewport, margin, &mut items);
+        items
+    }
+
+    /// Same as `visible_items`, but reuses the caller's buffer.
+    pub fn visible_items_into<'a>(
+        &'a self,
+        viewport: Rectangle,
+        margin: f64,
+        items: &mut Vec<&'a T>,
+    ) {
+        items.clear();
+        let range = Rectangle::new(
+            viewport.x - margin,
+            viewport.y - margin,
+            viewport.width + 2.0 * margin,
+            viewport.height + 2.0 * margin,
+        );
+        self.visit(&range, &mut |item| items.push(item));
+        items.sort_by(|a, b| {
+            let (a, b) = (a.position(), b.position());
+            a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x))
+        });
+    }
+
+    /// Returns the bounds of the nodes modified since the previous call, when
+    /// `Options::track_dirty` is set. Regions never overlap each other.
+    pub fn take_dirty_regions(&mut self) -> Vec<Rectangle> {
+        let mut regions = Vec::new();
+        self.collect_dirty(&mut regions);
+        regions
+    }
+
+    fn collect_dirty(&mut self, regions: &mut Vec<Rectangle>) {
+        if self.dirty {
+            regions.push(self.bounds());
+            self.clear_dirty();
+            return;
+        }
+        if let Some(ref mut children) = self.children {
+            for child in children.iter_mut() {
+                child.collect_dirty(regions);
+            }
+        }
+    }
+
+    fn clear_dirty(&mut self) {
+        self.dirty = false;
+        if let Some(ref mut children) = self.children {
+            for child in children.iter_mut() {
+                child.clear_dirty();
+            }
+        }
+    }
+
+    fn visit<'a, F: FnMut(&'a T)>(&'a self, range: &Rectangle, f: &mut F) {
+        if let Some(ref metrics) = self.metrics {
+            metrics.queries.fetch_add(1, Ordering::Relaxed);
+        }
+        self.visit_node(range, f);
+    }
+
+    fn visit_node<'a, F: FnMut(&'a T)>(&'a self, range: &Rectangle, f: &mut F) {
+        if let Some(ref metrics) = self.metrics {
+            metrics.nodes_visited.fetch_add(1, Ordering::Relaxed);
+        }
         match self.children {
             Some(ref children) => {
-                let mut items = Vec::<&T>::new();
-                if self.intersects(&range, &self.bounds()) {
+                if self.intersects(range, &self.bounds()) {
                     for child in children {
-                        items.extend(child.query(range));
+                        child.visit_node(range, f);
                     }
                 }
-                items
             }
             None => {
-                let mut items = Vec::<&T>::new();
+                if let Some(ref metrics) = self.metrics {
+                    metrics
+                        .items_scanned
+                        .fetch_add(self.items.len() as u64, Ordering::Relaxed);
+                }
                 for item in &self.items {
-                    if self._contains(&item.position(), &range) {
-                        items.push(item);
+                    if self._contains(&item.position(), range) {
+                        f(item);
                     }
                 }
-                items
             }
         }
     }
 
+    fn insert(&mut self, item: T, observer: &mut dyn Observer) {
+        self.count += 1;
+        let point = item.position();
+        self.sum.0 += point.x;
+        self.sum.1 += point.y;
+        self.extent = Some(extend(
+            self.extent,
+            &Rectangle::new(point.x, point.y, 0.0, 0.0),
+        ));
+        self.reach = self.reach.max(item.reach());
+
+        if self.children.is_none()
+            && (self.leaf_cost() + item.cost() <= self.options.max_items
+                || self.options.depth >= self.options.max_depth)
+        {
+            self.items.push(item);
+            self.dirty |= self.options.track_dirty;
+            observer.on_insert(self.bounds());
+            return;
+        }
+
+        if self.children.is_none() {
+            self.dirty |= self.options.track_dirty;
+            observer.on_subdivide(self.bounds());
+            let mut children = self.subdivide();
+            for it in std::mem::take(&mut self.items) {
+                children[self.quadrant(&it.position())].insert(it, &mut ());
+            }
+            self.children = Some(children);
+        }
+
+        let quadrant = self.quadrant(&item.position());
+        if let Some(ref mut children) = self.children {
+            children[quadrant].insert(item, observer);
+        }
+    }
+
+    fn collect_at<'a>(&'a self, point: &Point, entries: &mut Vec<&'a T>) {
+        for item in &self.items {
+            if self.matches(&item.position(), point) {
+                entries.push(item);
+            }
+        }
+        for child in self.children.iter().flatten() {
+            if child.reaches(point) {
+                child.collect_at(point, entries);
+            }
+        }
+    }
+
+    fn replace_exact(&mut self, item: T, observer: &mut dyn Observer) -> Result<T, T> {
+        let point = item.position();
+        let quadrant = self.quadrant(&point);
+        match self.children {
+            Some(ref mut children) => children[quadrant].replace_exact(item, observer),
+            None => match self.items.iter().position(|it| it.position() == point) {
+                Some(index) => {
+                    self.dirty |= self.options.track_dirty;
+                    observer.on_remove(self.bounds());
+                    observer.on_insert(self.bounds());
+                    Ok(std::mem::replace(&mut self.items[index], item))
+                }
+                None => Err(item),
+            },
+        }
+    }
+
+    fn insert_batch(&mut self, batch: Vec<T>, observer: &mut dyn Observer) {
+        self.count += batch.len();
+        for item in &batch {
+            let point = item.position();
+            self.sum.0 += point.x;
+            self.sum.1 += point.y;
+            self.extent = Some(extend(
+                self.extent,
+                &Rectangle::new(point.x, point.y, 0.0, 0.0),
+            ));
+            self.reach = self.reach.max(item.reach());
+        }
+        self.dirty |= self.options.track_dirty;
+
+        if self.children.is_none()
+            && (self.leaf_cost() + batch.iter().map(Position::cost).sum::<usize>()
+                <= self.options.max_items
+                || self.options.depth >= self.options.max_depth)
+        {
+            for item in batch {
+                self.items.push(item);
+                observer.on_insert(self.bounds());
+            }
+            return;
+        }
+
+        if self.children.is_none() {
+            observer.on_subdivide(self.bounds());
+            let mut children = self.subdivide();
+            for it in std::mem::take(&mut self.items) {
+                children[self.quadrant(&it.position())].insert(it, &mut ());
+            }
+            self.children = Some(children);
+        }
+
+        let mut parts: [Vec<T>; 4] = Default::default();
+        for item in batch {
+            parts[self.quadrant(&item.position())].push(item);
+        }
+        if let Some(ref mut children) = self.children {
+            for (child, part) in children.iter_mut().zip(parts) {
+                if !part.is_empty() {
+                    child.insert_batch(part, observer);
+                }
+            }
+        }
+    }
+
+    fn quadrant(&self, point: &Point) -> usize {
+        self.bounds().quadrant(point)
+    }
+
+    fn remove_where<F: FnMut(&T) -> bool>(&mut self, point: &Point, pred: &mut F) -> Option<T> {
+        let defer = self.options.defer_collapse;
+        self.remove_with(point, pred, defer)
+    }
+
+    fn remove_with<F: FnMut(&T) -> bool>(
+        &mut self,
+        point: &Point,
+        pred: &mut F,
+        defer: bool,
+    ) -> Option<T> {
+        if !self.reaches(point) {
+            return None;
+        }
+        let (removed, rank) = match self.take_observer() {
+            Some(mut observer) => {
+                let removed = self.take_where(point, pred, defer, &mut *observer);
+                self.set_observer(observer);
+                removed
+            }
+            None => self.take_where(point, pred, defer, &mut ()),
+        }?;
+        self.forget(&[(removed.position(), rank)]);
+        Some(removed)
+    }
+
+    fn take_where<F: FnMut(&T) -> bool>(
+        &mut self,
+        point: &Point,
+        pred: &mut F,
+        defer: bool,
+        observer: &mut dyn Observer,
+    ) -> Option<(T, usize)> {
+        let removed = match self.children {
+            Some(ref mut children) => children
+                .iter_mut()
+                .filter(|child| child.reaches(point))
+                .find_map(|child| child.take_where(point, pred, defer, observer))?,
+            None => {
+                let index = self
+                    .items
+                    .iter()
+                    .position(|it| self.matches(&it.position(), point) && pred(it))?;
+                self.dirty |= self.options.track_dirty;
+                observer.on_remove(self.bounds());
+                let removed = self.items.remove(index);
+                let exact = removed.position();
+                let rank = self.items[..index]
+                    .iter()
+                    .filter(|it| it.position() == exact)
+                    .count();
+                (removed, rank)
+            }
+        };
+
+        self.count -= 1;
+        if !defer && self.collapsible() {
+            observer.on_collapse(self.bounds());
+            self.collapse();
+        }
+        self.refresh_extent();
+        Some(removed)
+    }
+
+    fn matches(&self, a: &Point, b: &Point) -> bool {
+        let (a, b) = (self.snapped(a), self.snapped(b));
+        (a.x - b.x).abs() <= self.options.epsilon && (a.y - b.y).abs() <= self.options.epsilon
+    }
+
+    fn snapped(&self, point: &Point) -> Point {
+        snap(point, self.options.snap)
+    }
+
+    /// How far from `point` an item matching it may lie.
+    fn tolerance(&self) -> f64 {
+        self.options.epsilon + self.options.snap
+    }
+
+    /// Whether an item matching `point` could be stored below this node.
+    fn reaches(&self, point: &Point) -> bool {
+        self.count > 0 && self._contains(point, &self.bounds().inflated(self.tolerance()))
+    }
+
+    fn collapsible(&self) -> bool {
+        self.children.is_some()
+            && self.count <= self.options.max_items
+            && self.iter().map(Position::cost).sum::<usize>() <= self.options.max_items
+    }
+
+    /// Total cost of the items stored directly in this node.
+    fn leaf_cost(&self) -> usize {
+        self.items.iter().map(Position::cost).sum()
+    }
+
+    fn collapse(&mut self) {
+        if let Some(ref mut children) = self.children {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(depth = self.options.depth, items = self.count, "collapse");
+            self.dirty |= self.options.track_dirty;
+            for child in children.iter_mut() {
+                child.collapse();
+                self.items.append(&mut child.items);
+            }
+            self.release_children();
+        }
+    }
+
+    /// Drops the children, or keeps them emptied as spares for the next
+    /// subdivision when `Options::reuse_nodes` is set.
+    fn release_children(&mut self) {
+        let mut children = match self.children.take() {
+            Some(children) if self.options.reuse_nodes => children,
+            _ => return,
+        };
+        for child in children.iter_mut() {
+            child.items.clear();
+            child.release_children();
+            child.count = 0;
+            child.sum = (0.0, 0.0);
+            child.extent = None;
+            child.reach = 0.0;
+            child.dirty = false;
+            child.data = D::default();
+        }
+        self.spare = Some(children);
+    }
+
+    /// Recomputes the extent, reach and coordinate sums of this node from its
+    /// items and children, after items were removed below it.
+    fn refresh_extent(&mut self) {
+        let mut extent = None;
+        let mut reach: f64 = 0.0;
+        let mut sum = (0.0, 0.0);
+        for item in &self.items {
+            let point = item.position();
+            extent = Some(extend(extent, &Rectangle::new(point.x, point.y, 0.0, 0.0)));
+            reach = reach.max(item.reach());
+            sum.0 += point.x;
+            sum.1 += point.y;
+        }
+        for child in self.children.iter().flatten() {
+            if let Some(ref child_extent) = child.extent {
+                extent = Some(extend(extent, child_extent));
+            }
+            reach = reach.max(child.reach);
+            sum.0 += child.sum.0;
+            sum.1 += child.sum.1;
+        }
+        self.extent = extent;
+        self.reach = reach;
+        self.sum = sum;
+    }
+
+    fn state_mut(&mut self) -> &mut RootState {
+        self.state.get_or_insert_with(Default::default)
+    }
+
+    pub(crate) fn eviction_mut(
+        &mut self,
+    ) -> Option<&mut (dyn EvictionPolicy + Send + Sync + 'static)> {
+        self.state.as_mut()?.eviction.as_deref_mut()
+    }
+
+    /// How far from its position the footprint of an item below this node
+    /// may extend.
+    pub(crate) fn max_reach(&self) -> f64 {
+        self.options.max_radius.max(self.reach)
+    }
+
     fn contains(&self, item: &T) -> bool {
         self._contains(&item.position(), &self.bounds())
     }
 
     fn _contains(&self, point: &Point, boundary: &Rectangle) -> bool {
-        point.x >= boundary.x
-            && point.x <= boundary.x + boundary.width
-            && point.y >= boundary.y
-            && point.y <= boundary.y + boundary.height
+        boundary.contains_point(point)
     }
 
     fn intersects(&self, rectangle: &Rectangle, boundary: &Rectangle) -> bool {
@@ -222,42 +1465,43 @@ impl<T: Position> Quadtree<T> {
             && rectangle.y + rectangle.height > boundary.y
     }
 
-    fn subdivide(&self) -> [Box<Quadtree<T>>; 4] {
+    fn subdivide(&mut self) -> [Box<Quadtree<T, D>>; 4] {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            depth = self.options.depth,
+            items = self.items.len(),
+            "subdivide"
+        );
         let w = self.width / 2.0;
         let h = self.height / 2.0;
+        let options = Options {
+            depth: self.options.depth + 1,
+            ..self.options
+        };
+        if let Some(ref metrics) = self.metrics {
+            metrics.subdivisions.fetch_add(1, Ordering::Relaxed);
+            metrics
+                .max_depth
+                .fetch_max(options.depth, Ordering::Relaxed);
+        }
+        if let Some(spare) = self.spare.take() {
+            if let Some(ref metrics) = self.metrics {
+                metrics.nodes_reused.fetch_add(4, Ordering::Relaxed);
+            }
+            return spare;
+        }
+        let child = |x, y| {
+            Box::new(Quadtree::node(
+                Rectangle::new(x, y, w, h),
+                options,
+                self.metrics.clone(),
+            ))
+        };
         [
-            Box::new(Quadtree::with_options(
-                Rectangle::new(self.x, self.y, w, h),
-                Options {
-                    max_items: self.options.max_items,
-                    max_depth: self.options.max_depth,
-                    depth: self.options.depth + 1,
-                },
-            )),
-            Box::new(Quadtree::with_options(
-                Rectangle::new(self.x + w, self.y, w, h),
-                Options {
-                    max_items: self.options.max_items,
-                    max_depth: self.options.max_depth,
-                    depth: self.options.depth + 1,
-                },
-            )),
-            Box::new(Quadtree::with_options(
-                Rectangle::new(self.x + w, self.y + h, w, h),
-                Options {
-                    max_items: self.options.max_items,
-                    max_depth: self.options.max_depth,
-                    depth: self.options.depth + 1,
-                },
-            )),
-            Box::new(Quadtree::with_options(
-                Rectangle::new(self.x, self.y + h, w, h),
-                Options {
-                    max_items: self.options.max_items,
-                    max_depth: self.options.max_depth,
-                    depth: self.options.depth + 1,
-                },
-            )),
+            child(self.x, self.y),
+            child(self.x + w, self.y),
+            child(self.x + w, self.y + h),
+            child(self.x, self.y + h),
         ]
     }
 
@@ -266,9 +1510,45 @@ impl<T: Position> Quadtree<T> {
     }
 }
 
+impl<T: Position, D: Default> Quadtree<Snapped<T>, D> {
+    /// Stores `item` at its position rounded to `Options::snap`, so every
+    /// query sees the lattice position and noisy copies of it share one
+    /// location.
+    pub fn put_snapped(&mut self, item: T) {
+        let cell = self.options.snap;
+        self.put(Snapped::new(item, cell));
+    }
+}
+
+fn extend(extent: Option<Rectangle>, rect: &Rectangle) -> Rectangle {
+    match extent {
+        Some(extent) => extent.union(rect),
+        None => *rect,
+    }
+}
+
+/// Interleaves the 16-bit scaled coordinates of `point` within `bounds` into
+/// a Z-order key.
+fn morton(bounds: &Rectangle, point: &Point) -> u32 {
+    let scale = |v: f64, origin: f64, size: f64| {
+        let v = if size > 0.0 { (v - origin) / size } else { 0.0 };
+        (v.clamp(0.0, 1.0) * f64::from(u16::MAX)) as u32
+    };
+    let spread = |mut v: u32| {
+        v = (v | (v << 8)) & 0x00ff_00ff;
+        v = (v | (v << 4)) & 0x0f0f_0f0f;
+        v = (v | (v << 2)) & 0x3333_3333;
+        (v | (v << 1)) & 0x5555_5555
+    };
+    let x = scale(point.x, bounds.x, bounds.width);
+    let y = scale(point.y, bounds.y, bounds.height);
+    spread(x) | (spread(y) << 1)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
 
     #[test]
     fn test_points() {
@@ -279,6 +1559,38 @@ mod tests {
         assert_eq!(format!("Point: {}", p1), "Point: (10, 5)");
     }
 
+    #[test]
+    fn test_point_ordering() {
+        use std::collections::{BTreeSet, HashSet};
+
+        assert!(Point::new(1.0, 5.0) < Point::new(2.0, 0.0));
+        assert!(Point::new(1.0, 5.0) > Point::new(1.0, 4.0));
+        assert_eq!(Point::new(-0.0, 1.0), Point::new(0.0, 1.0));
+        assert_eq!(Point::new(f64::NAN, 1.0), Point::new(f64::NAN, 1.0));
+
+        let mut hashed = HashSet::new();
+        hashed.insert(Point::new(0.0, 1.0));
+        assert!(hashed.contains(&Point::new(-0.0, 1.0)));
+
+        let sorted: Vec<Point> = vec![
+            Point::new(3.0, 1.0),
+            Point::new(-1.0, 7.0),
+            Point::new(3.0, -2.0),
+        ]
+        .into_iter()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+        assert_eq!(
+            sorted,
+            vec![
+                Point::new(-1.0, 7.0),
+                Point::new(3.0, -2.0),
+                Point::new(3.0, 1.0),
+            ]
+        );
+    }
+
     #[test]
     fn test_items() {
         let data1 = String::from("data1");
@@ -386,7 +1698,7 @@ mod tests {
 
     #[test]
     fn test_subdivide() {
-        let q1 = Quadtree::<Item<String>>::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+        let mut q1 = Quadtree::<Item<String>>::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
         let children = q1.subdivide();
         assert_eq!(children.len(), 4);
 
@@ -504,6 +1816,102 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_put_respects_max_depth() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                max_depth: 2,
+                ..Default::default()
+            },
+        );
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(20.0, 20.0), &entity));
+        qt.put(Item::new(Point::new(30.0, 30.0), &entity));
+        let c1 = &qt.children.as_ref().unwrap()[0];
+        assert!(c1.items.is_empty());
+        let g1 = &c1.children.as_ref().unwrap()[0];
+        assert_eq!(g1.options.depth, 2);
+        assert_eq!(g1.items.len(), 3);
+        assert!(g1.children.is_none());
+    }
+
+    #[test]
+    fn test_remove_and_collapse() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 2,
+                ..Default::default()
+            },
+        );
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(110.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(110.0, 110.0), &entity));
+        assert!(qt.children.is_some());
+
+        let removed = qt.remove_where(&Point::new(110.0, 10.0), &mut |_| true);
+        assert_eq!(removed.unwrap().point, Point::new(110.0, 10.0));
+        assert_eq!(qt.count, 2);
+        assert!(qt.children.is_none());
+        assert_eq!(qt.items.len(), 2);
+        let extent = qt.extent.unwrap();
+        assert_eq!(
+            (extent.x, extent.y, extent.width, extent.height),
+            (10.0, 10.0, 100.0, 100.0)
+        );
+
+        assert!(qt
+            .remove_where(&Point::new(110.0, 10.0), &mut |_| true)
+            .is_none());
+        assert!(qt
+            .remove_where(&Point::new(10.0, 10.0), &mut |_| false)
+            .is_none());
+        assert_eq!(qt.count, 2);
+    }
+
+    #[test]
+    fn test_optimize_step() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                defer_collapse: true,
+                ..Default::default()
+            },
+        );
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(20.0, 20.0), &entity));
+        qt.put(Item::new(Point::new(110.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(110.0, 110.0), &entity));
+        qt.remove_where(&Point::new(20.0, 20.0), &mut |_| true);
+        assert!(qt.children.as_ref().unwrap()[0].children.is_some());
+
+        let mut steps = 0;
+        while !qt.optimize_step(Duration::from_secs(0)) {
+            steps += 1;
+        }
+        assert_eq!(steps, 4);
+        let c1 = &qt.children.as_ref().unwrap()[0];
+        assert!(c1.children.is_none());
+        assert_eq!(c1.items.len(), 1);
+        assert_eq!(qt.query(qt.bounds()).len(), 3);
+
+        qt.remove_where(&Point::new(110.0, 10.0), &mut |_| true);
+        qt.remove_where(&Point::new(110.0, 110.0), &mut |_| true);
+        assert!(qt.children.is_some());
+        qt.optimize();
+        assert!(qt.children.is_none());
+        assert_eq!(qt.items.len(), 1);
+    }
+
     #[test]
     fn test_bounds() {
         let q1 = Quadtree::<Item<String>>::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
@@ -0,0 +1,240 @@
+use crate::{morton, Options, Point, Position, Rectangle};
+
+const KEY_BITS: u8 = 16;
+
+/// A Z-order key interleaving the 16-bit grid coordinates of a point within
+/// a boundary, x in the even bits and y in the odd bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MortonKey(pub u32);
+
+impl MortonKey {
+    pub fn of(boundary: &Rectangle, point: &Point) -> Self {
+        MortonKey(morton(boundary, point))
+    }
+
+    /// Covers `range` with inclusive key ranges of the grid cells at `depth`
+    /// (at most 16) that it touches. Every point of `range` has a key inside
+    /// one of the ranges; keys in the ranges may lie slightly outside it.
+    /// Adjacent ranges are merged, and the result is sorted.
+    pub fn ranges(
+        boundary: &Rectangle,
+        range: &Rectangle,
+        depth: u8,
+    ) -> Vec<(MortonKey, MortonKey)> {
+        let clipped = match boundary.intersection(range) {
+            Some(clipped) => clipped,
+            None => return Vec::new(),
+        };
+        let lo = morton_coords(boundary, &Point::new(clipped.x, clipped.y));
+        let hi = morton_coords(
+            boundary,
+            &Point::new(clipped.x + clipped.width, clipped.y + clipped.height),
+        );
+        let mut ranges: Vec<(MortonKey, MortonKey)> = Vec::new();
+        let cell = 1u32 << (KEY_BITS - depth.min(KEY_BITS));
+        cover(0, 0, 1 << KEY_BITS, cell, (lo, hi), &mut ranges);
+        ranges
+    }
+}
+
+fn morton_coords(boundary: &Rectangle, point: &Point) -> (u32, u32) {
+    let key = morton(boundary, point);
+    (compact(key), compact(key >> 1))
+}
+
+fn compact(mut v: u32) -> u32 {
+    v &= 0x5555_5555;
+    v = (v | (v >> 1)) & 0x3333_3333;
+    v = (v | (v >> 2)) & 0x0f0f_0f0f;
+    v = (v | (v >> 4)) & 0x00ff_00ff;
+    (v | (v >> 8)) & 0x0000_ffff
+}
+
+fn spread(mut v: u32) -> u32 {
+    v = (v | (v << 8)) & 0x00ff_00ff;
+    v = (v | (v << 4)) & 0x0f0f_0f0f;
+    v = (v | (v << 2)) & 0x3333_3333;
+    (v | (v << 1)) & 0x5555_5555
+}
+
+fn cover(
+    x: u32,
+    y: u32,
+    size: u32,
+    cell: u32,
+    ((x0, y0), (x1, y1)): ((u32, u32), (u32, u32)),
+    ranges: &mut Vec<(MortonKey, MortonKey)>,
+) {
+    let (right, bottom) = (x + size - 1, y + size - 1);
+    if right < x0 || x > x1 || bottom < y0 || y > y1 {
+        return;
+    }
+    if size <= cell || (x >= x0 && right <= x1 && y >= y0 && bottom <= y1) {
+        let lo = spread(x) | (spread(y) << 1);
+        let hi = lo + (u64::from(size) * u64::from(size) - 1) as u32;
+        match ranges.last_mut() {
+            Some(last) if last.1 .0.checked_add(1) == Some(lo) => last.1 = MortonKey(hi),
+            _ => ranges.push((MortonKey(lo), MortonKey(hi))),
+        }
+        return;
+    }
+    let half = size / 2;
+    let bounds = ((x0, y0), (x1, y1));
+    cover(x, y, half, cell, bounds, ranges);
+    cover(x + half, y, half, cell, bounds, ranges);
+    cover(x, y + half, half, cell, bounds, ranges);
+    cover(x + half, y + half, half, cell, bounds, ranges);
+}
+
+/// A linear quadtree: items are kept in one vector sorted by their Morton
+/// key, so each quadtree cell is a contiguous key range.
+pub struct LinearQuadtree<T> {
+    boundary: Rectangle,
+    options: Options,
+    items: Vec<(MortonKey, T)>,
+}
+
+impl<T: Position> LinearQuadtree<T> {
+    pub fn new(boundary: Rectangle) -> Self {
+        Self::with_options(boundary, Default::default())
+    }
+
+    /// Range queries are decomposed into cells at `options.max_depth`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `boundary` does not have a finite, positive width and height.
+    pub fn with_options(boundary: Rectangle, options: Options) -> Self {
+        assert!(
+            boundary.has_area(),
+            "quadtree boundary must have a finite, positive area: {}",
+            boundary
+        );
+        Self {
+            boundary,
+            options,
+            items: Vec::new(),
+        }
+    }
+
+    pub fn put(&mut self, item: T) {
+        let point = item.position();
+        if self.boundary.contains_point(&point) {
+            let key = MortonKey::of(&self.boundary, &point);
+            let index = self.items.partition_point(|(k, _)| *k <= key);
+            self.items.insert(index, (key, item));
+        }
+    }
+
+    /// Stores every item of `items`, pushing them all and then sorting by key
+    /// once instead of inserting them one by one. As with `put`, items
+    /// outside the boundary are skipped and items sharing a key keep their
+    /// insertion order.
+    pub fn extend<I: IntoIterator<Item = T>>(&mut self, items: I) {
+        let boundary = self.boundary;
+        self.items.extend(items.into_iter().filter_map(|item| {
+            let point = item.position();
+            if boundary.contains_point(&point) {
+                Some((MortonKey::of(&boundary, &point), item))
+            } else {
+                None
+            }
+        }));
+        self.items.sort_by_key(|&(key, _)| key);
+    }
+
+    /// Removes and returns an item stored exactly at `point`.
+    pub fn remove(&mut self, point: Point) -> Option<T> {
+        let key = MortonKey::of(&self.boundary, &point);
+        let start = self.items.partition_point(|(k, _)| *k < key);
+        let offset = self.items[start..]
+            .iter()
+            .take_while(|(k, _)| *k == key)
+            .position(|(_, item)| item.position() == point)?;
+        Some(self.items.remove(start + offset).1)
+    }
+
+    /// Returns the item closest to `point`, searching squares of doubling
+    /// size around it until one holds an item no farther than its half-size.
+    /// Returns `None` if `point` has a NaN or infinite coordinate.
+    pub fn nearest(&self, point: Point) -> Option<&T> {
+        if !point.x.is_finite() || !point.y.is_finite() {
+            return None;
+        }
+        let b = &self.boundary;
+        let far_x = (point.x - b.x).abs().max((point.x - b.x - b.width).abs());
+        let far_y = (point.y - b.y).abs().max((point.y - b.y - b.height).abs());
+        let mut reach = b.width.max(b.height) / 64.0;
+        loop {
+            // Clipping to the boundary keeps the range finite for far points.
+            let (x0, y0) = ((point.x - reach).max(b.x), (point.y - reach).max(b.y));
+            let x1 = (point.x + reach).min(b.x + b.width);
+            let y1 = (point.y + reach).min(b.y + b.height);
+            let best = if x0 <= x1 && y0 <= y1 {
+                self.query(Rectangle::new(x0, y0, x1 - x0, y1 - y0))
+                    .into_iter()
+                    .map(|item| {
+                        let p = item.position();
+                        ((p.x - point.x).hypot(p.y - point.y), item)
+                    })
+                    .min_by(|a, b| a.0.total_cmp(&b.0))
+            } else {
+                None
+            };
+            let covered = (reach >= far_x && reach >= far_y) || reach == f64::MAX;
+            match best {
+                Some((distance, item)) if distance <= reach || covered => return Some(item),
+                None if covered => return None,
+                _ => reach = (reach * 2.0).min(f64::MAX),
+            }
+        }
+    }
+
+    pub fn query(&self, range: Rectangle) -> Vec<&T> {
+        let mut items = Vec::new();
+        for (lo, hi) in self.key_ranges(range) {
+            items.extend(
+                self.key_slice(lo, hi)
+                    .iter()
+                    .map(|(_, item)| item)
+                    .filter(|item| range.contains_point(&item.position())),
+            );
+        }
+        items
+    }
+
+    /// Returns the items whose key lies in `lo..=hi`, in key order.
+    pub fn query_key_range(&self, lo: MortonKey, hi: MortonKey) -> Vec<&T> {
+        self.key_slice(lo, hi)
+            .iter()
+            .map(|(_, item)| item)
+            .collect()
+    }
+
+    /// Returns the key ranges `query` scans for `range`.
+    pub fn key_ranges(&self, range: Rectangle) -> Vec<(MortonKey, MortonKey)> {
+        MortonKey::ranges(&self.boundary, &range, self.options.max_depth)
+    }
+
+    pub fn key_of(&self, point: &Point) -> MortonKey {
+        MortonKey::of(&self.boundary, point)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    fn key_slice(&self, lo: MortonKey, hi: MortonKey) -> &[(MortonKey, T)] {
+        let start = self.items.partition_point(|(key, _)| *key < lo);
+        let end = self.items.partition_point(|(key, _)| *key <= hi);
+        &self.items[start..end.max(start)]
+    }
+}
@@ -0,0 +1,119 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+
+use crate::persist::{invalid, read_settings, write_settings, Persist};
+use crate::{Point, Position, Quadtree};
+
+const MAGIC: &[u8; 4] = b"QDTL";
+const VERSION: u16 = 1;
+const INSERT: u8 = 1;
+const REMOVE: u8 = 2;
+
+/// A quadtree whose inserts and removals are appended to a log file before
+/// they are applied, so that `Quadtree::replay` can rebuild it after a crash.
+///
+/// Each record is handed to the operating system as a single write; call
+/// `sync` to also force it to stable storage.
+pub struct LoggedQuadtree<T> {
+    tree: Quadtree<T>,
+    log: File,
+}
+
+impl<T: Position + Persist> Quadtree<T> {
+    /// Starts logging changes to `path`, replacing any existing file. The
+    /// log begins with the boundary, options and current items of the tree.
+    pub fn with_log<P: AsRef<Path>>(self, path: P) -> io::Result<LoggedQuadtree<T>> {
+        let mut header = Vec::new();
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        write_settings(&mut header, &self.bounds(), &self.options)?;
+        for item in self.iter() {
+            header.push(INSERT);
+            item.write_to(&mut header)?;
+        }
+
+        let mut log = File::create(path)?;
+        log.write_all(&header)?;
+        log.sync_all()?;
+        Ok(LoggedQuadtree { tree: self, log })
+    }
+
+    /// Rebuilds a tree from a log written by a `LoggedQuadtree`. A record cut
+    /// short by a crash at the end of the log is ignored.
+    pub fn replay<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a quadtree log"));
+        }
+        let mut version = [0; 2];
+        reader.read_exact(&mut version)?;
+        let version = u16::from_le_bytes(version);
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported log version {}", version)));
+        }
+        let (boundary, options) = read_settings(&mut reader)?;
+
+        let mut tree = Quadtree::with_options(boundary, options);
+        loop {
+            let mut tag = [0];
+            if reader.read(&mut tag)? == 0 {
+                break;
+            }
+            let applied = match tag[0] {
+                INSERT => T::read_from(&mut reader).map(|item| {
+                    tree.put(item);
+                }),
+                REMOVE => Point::read_from(&mut reader).map(|point| {
+                    tree.remove(point);
+                }),
+                tag => return Err(invalid(&format!("unknown log record {}", tag))),
+            };
+            match applied {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+        }
+        tree.clear_dirty();
+        Ok(tree)
+    }
+}
+
+impl<T: Position + Persist> LoggedQuadtree<T> {
+    pub fn put(&mut self, item: T) -> io::Result<()> {
+        let mut record = vec![INSERT];
+        item.write_to(&mut record)?;
+        self.log.write_all(&record)?;
+        self.tree.put(item);
+        Ok(())
+    }
+
+    /// Removes the first item stored at `point`, logging the removal only if
+    /// an item was found.
+    pub fn remove(&mut self, point: Point) -> io::Result<Option<T>> {
+        if self.tree.get(point).is_none() {
+            return Ok(None);
+        }
+        let mut record = vec![REMOVE];
+        point.write_to(&mut record)?;
+        self.log.write_all(&record)?;
+        Ok(self.tree.remove(point))
+    }
+
+    /// Forces the log to stable storage.
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.log.sync_data()
+    }
+
+    pub fn tree(&self) -> &Quadtree<T> {
+        &self.tree
+    }
+
+    /// Stops logging and returns the tree.
+    pub fn into_inner(self) -> Quadtree<T> {
+        self.tree
+    }
+}
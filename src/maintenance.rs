@@ -0,0 +1,132 @@
+use std::mem;
+use std::time::{Duration, Instant};
+
+use crate::{Observer, Options, Point, Position, Quadtree};
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Collapses every node left under-full by removals made with
+    /// `Options::defer_collapse`.
+    pub fn optimize(&mut self) {
+        if let Some(ref mut state) = self.state {
+            state.maintenance.clear();
+        }
+        while !self.optimize_step(Duration::MAX) {}
+    }
+
+    /// Collapses the under-full nodes on the path to `point`, for removals
+    /// made there with collapsing deferred, leaving the rest of the tree
+    /// untouched.
+    pub(crate) fn collapse_toward(&mut self, point: &Point) {
+        let mut observer = self.take_observer();
+        self.collapse_path(point, &mut observer);
+        if let Some(observer) = observer {
+            self.set_observer(observer);
+        }
+    }
+
+    fn collapse_path(
+        &mut self,
+        point: &Point,
+        observer: &mut Option<Box<dyn Observer + Send + Sync>>,
+    ) {
+        if self.collapsible() {
+            if let Some(ref mut observer) = observer {
+                observer.on_collapse(self.bounds());
+            }
+            self.collapse();
+            return;
+        }
+        let quadrant = self.quadrant(point);
+        if let Some(ref mut children) = self.children {
+            children[quadrant].collapse_path(point, observer);
+        }
+    }
+
+    /// Continues the current maintenance pass for roughly `budget`, visiting at
+    /// least one node. Returns `true` once the pass has covered the whole tree.
+    pub fn optimize_step(&mut self, budget: Duration) -> bool {
+        let started = Instant::now();
+        let mut path = match self.state {
+            Some(ref mut state) => mem::take(&mut state.maintenance),
+            None => Vec::new(),
+        };
+        let mut observer = self.take_observer();
+        let done = loop {
+            let (node, depth) = self.node_at_mut(&path);
+            path.truncate(depth);
+            if node.collapsible() {
+                if let Some(ref mut observer) = observer {
+                    observer.on_collapse(node.bounds());
+                }
+                node.collapse();
+            }
+
+            if node.children.is_some() {
+                path.push(0);
+            } else {
+                while path.last() == Some(&3) {
+                    path.pop();
+                }
+                match path.last_mut() {
+                    Some(quadrant) => *quadrant += 1,
+                    None => break true,
+                }
+            }
+            if started.elapsed() >= budget {
+                break false;
+            }
+        };
+        if let Some(observer) = observer {
+            self.set_observer(observer);
+        }
+        if !done {
+            self.state_mut().maintenance = path;
+        }
+        done
+    }
+
+    /// Replaces `max_items` and `max_depth` with `Options::auto` for the
+    /// current number of items, and rebuilds the tree with them. Observers
+    /// are not notified, and node data is reset to `D::default()`.
+    pub fn auto_tune(&mut self) {
+        let tuned = Options::auto(self.count, self.bounds());
+        let mut items = Vec::with_capacity(self.count);
+        self.drain_into(&mut items);
+        if !items.is_empty() {
+            self.dirty |= self.options.track_dirty;
+        }
+        self.release_children();
+        self.count = 0;
+        self.sum = (0.0, 0.0);
+        self.extent = None;
+        self.reach = 0.0;
+        if let Some(ref mut state) = self.state {
+            state.maintenance.clear();
+        }
+        self.data = D::default();
+        self.options = Options {
+            max_items: tuned.max_items,
+            max_depth: self.options.depth.saturating_add(tuned.max_depth),
+            ..self.options
+        };
+        if !items.is_empty() {
+            self.insert_batch(items, &mut ());
+        }
+    }
+
+    fn drain_into(&mut self, items: &mut Vec<T>) {
+        items.append(&mut self.items);
+        for child in self.children.iter_mut().flatten() {
+            child.drain_into(items);
+        }
+    }
+
+    fn node_at_mut(&mut self, path: &[usize]) -> (&mut Quadtree<T, D>, usize) {
+        if path.is_empty() || self.children.is_none() {
+            return (self, 0);
+        }
+        let children = self.children.as_mut().unwrap();
+        let (node, depth) = children[path[0]].node_at_mut(&path[1..]);
+        (node, depth + 1)
+    }
+}
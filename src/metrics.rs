@@ -0,0 +1,51 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::{Position, Quadtree};
+
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    pub(crate) queries: AtomicU64,
+    pub(crate) nodes_visited: AtomicU64,
+    pub(crate) items_scanned: AtomicU64,
+    pub(crate) subdivisions: AtomicU64,
+    pub(crate) nodes_reused: AtomicU64,
+    pub(crate) max_depth: AtomicU8,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    pub queries: u64,
+    pub nodes_visited: u64,
+    pub items_scanned: u64,
+    pub subdivisions: u64,
+    /// Nodes taken from the spares kept under `Options::reuse_nodes`
+    /// instead of being allocated.
+    pub nodes_reused: u64,
+    pub max_depth: u8,
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Returns the counters collected since creation or the last reset, or
+    /// `None` unless `Options::collect_metrics` was set.
+    pub fn metrics(&self) -> Option<Metrics> {
+        self.metrics.as_ref().map(|counters| Metrics {
+            queries: counters.queries.load(Ordering::Relaxed),
+            nodes_visited: counters.nodes_visited.load(Ordering::Relaxed),
+            items_scanned: counters.items_scanned.load(Ordering::Relaxed),
+            subdivisions: counters.subdivisions.load(Ordering::Relaxed),
+            nodes_reused: counters.nodes_reused.load(Ordering::Relaxed),
+            max_depth: counters.max_depth.load(Ordering::Relaxed),
+        })
+    }
+
+    pub fn reset_metrics(&self) {
+        if let Some(ref counters) = self.metrics {
+            counters.queries.store(0, Ordering::Relaxed);
+            counters.nodes_visited.store(0, Ordering::Relaxed);
+            counters.items_scanned.store(0, Ordering::Relaxed);
+            counters.subdivisions.store(0, Ordering::Relaxed);
+            counters.nodes_reused.store(0, Ordering::Relaxed);
+            counters.max_depth.store(0, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use memmap2::Mmap;
+
+use crate::persist::{invalid, write_f64, write_u64, Persist};
+use crate::{Options, Position, Quadtree, Rectangle};
+
+const MAGIC: &[u8; 4] = b"QDTM";
+const VERSION: u16 = 1;
+const HEADER_LEN: usize = 24;
+const NODE_LEN: usize = 64;
+const NO_CHILDREN: u64 = u64::MAX;
+
+/// A read-only quadtree answering queries straight from a memory-mapped
+/// file, so only the pages a query touches are loaded.
+///
+/// The file starts with a header holding the node count and the offset of
+/// the node table. Items follow, encoded with `Persist` and grouped by node;
+/// the node table comes last. Each node is a 64-byte record with its
+/// boundary, the index of its first child (the four children are stored
+/// consecutively), the number of items below it and the byte range of its
+/// own items. Children always come after their parent in the table.
+pub struct MmapQuadtree<T> {
+    map: Mmap,
+    nodes: usize,
+    node_count: usize,
+    marker: PhantomData<T>,
+}
+
+struct Node {
+    bounds: Rectangle,
+    first_child: u64,
+    count: u64,
+    items: (u64, u64),
+}
+
+impl<T: Position + Persist> MmapQuadtree<T> {
+    /// Writes `tree` in the mapped layout to `path`.
+    pub fn create<P: AsRef<Path>, D: Default>(path: P, tree: &Quadtree<T, D>) -> io::Result<()> {
+        let mut order = vec![tree];
+        let mut i = 0;
+        while i < order.len() {
+            if let Some(ref children) = order[i].children {
+                order.extend(children.iter().map(|child| &**child));
+            }
+            i += 1;
+        }
+
+        let mut writer = Counter {
+            inner: BufWriter::new(File::create(path)?),
+            written: 0,
+        };
+        writer.write_all(&[0; HEADER_LEN])?;
+        let mut ranges = Vec::with_capacity(order.len());
+        for node in &order {
+            let start = writer.written;
+            for item in &node.items {
+                item.write_to(&mut writer)?;
+            }
+            ranges.push((start, writer.written));
+        }
+
+        let mut next_child = 1;
+        let table: Vec<Node> = order
+            .iter()
+            .zip(ranges)
+            .map(|(node, items)| Node {
+                bounds: node.bounds(),
+                first_child: if node.children.is_some() {
+                    next_child += 4;
+                    next_child - 4
+                } else {
+                    NO_CHILDREN
+                },
+                count: node.count as u64,
+                items,
+            })
+            .collect();
+        finish(writer, &table)
+    }
+
+    /// Writes the mapped layout to `path` straight from `items`, without
+    /// building a `Quadtree` or holding the items in memory. Items are
+    /// spilled to scratch files next to `path` and partitioned one node at a
+    /// time: a node splits while it holds more than `options.max_items`
+    /// items and lies above `options.max_depth`. Items outside `boundary`
+    /// are skipped, as with `Quadtree::put`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `boundary` does not have a finite, positive width and height.
+    pub fn build<P, I>(path: P, boundary: Rectangle, options: Options, items: I) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = T>,
+    {
+        assert!(
+            boundary.has_area(),
+            "quadtree boundary must have a finite, positive area: {}",
+            boundary
+        );
+        let path = path.as_ref();
+        let mut builder = Builder {
+            writer: Counter {
+                inner: BufWriter::new(File::create(path)?),
+                written: 0,
+            },
+            table: vec![placeholder()],
+            options,
+            path: path.to_path_buf(),
+            scratch: 0,
+        };
+        builder.writer.write_all(&[0; HEADER_LEN])?;
+        let mut root = builder.spill()?;
+        for item in items {
+            if boundary.contains_point(&item.position()) {
+                root.push(&item)?;
+            }
+        }
+        let root = root.finish()?;
+        builder.node::<T>(0, boundary, options.depth, root)?;
+        finish(builder.writer, &builder.table)
+    }
+
+    /// Maps a file written by `create` or `build`.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this process or any
+    /// other, for as long as the returned tree is alive: the tree reads the
+    /// mapped bytes directly, and a change to them is undefined behavior.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // Safety: the caller guarantees the file is left unchanged while the
+        // mapping is alive.
+        let map = unsafe { Mmap::map(&file)? };
+        if map.len() < HEADER_LEN || &map[..4] != MAGIC {
+            return Err(invalid("not a mapped quadtree file"));
+        }
+        let version = u16::from_le_bytes([map[4], map[5]]);
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported format version {}", version)));
+        }
+        let node_count = u64_at(&map, 8) as usize;
+        let nodes = u64_at(&map, 16) as usize;
+        let fits = node_count
+            .checked_mul(NODE_LEN)
+            .and_then(|len| len.checked_add(nodes))
+            .is_some_and(|end| end <= map.len());
+        if node_count == 0 || !fits {
+            return Err(invalid("truncated node table"));
+        }
+        Ok(Self {
+            map,
+            nodes,
+            node_count,
+            marker: PhantomData,
+        })
+    }
+
+    pub fn bounds(&self) -> Rectangle {
+        self.node(0).bounds
+    }
+
+    pub fn len(&self) -> usize {
+        self.node(0).count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Decodes the items inside `range`. Fails if the file is corrupt,
+    /// including when a node's children do not come after it, so a corrupt
+    /// table cannot send the query around a cycle.
+    pub fn query(&self, range: Rectangle) -> io::Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut stack = vec![0];
+        let mut visits = 0;
+        while let Some(index) = stack.pop() {
+            visits += 1;
+            if visits > self.node_count {
+                return Err(invalid("node table visits a node twice"));
+            }
+            let node = self.node(index);
+            if node.count == 0 || node.bounds.intersection(&range).is_none() {
+                continue;
+            }
+            let (start, end) = (node.items.0 as usize, node.items.1 as usize);
+            if start > end || end > self.nodes {
+                return Err(invalid("item range out of bounds"));
+            }
+            let mut bytes = &self.map[start..end];
+            while !bytes.is_empty() {
+                let item = T::read_from(&mut bytes)?;
+                if range.contains_point(&item.position()) {
+                    items.push(item);
+                }
+            }
+            if node.first_child != NO_CHILDREN {
+                let first = node.first_child as usize;
+                if first.checked_add(4).is_none_or(|end| end > self.node_count) {
+                    return Err(invalid("child index out of bounds"));
+                }
+                if first <= index {
+                    return Err(invalid("child stored before its parent"));
+                }
+                stack.extend((first..first + 4).rev());
+            }
+        }
+        Ok(items)
+    }
+
+    fn node(&self, index: usize) -> Node {
+        let at = self.nodes + index * NODE_LEN;
+        let f = |i: usize| f64::from_bits(u64_at(&self.map, at + i * 8));
+        Node {
+            bounds: Rectangle::new(f(0), f(1), f(2), f(3)),
+            first_child: u64_at(&self.map, at + 32),
+            count: u64_at(&self.map, at + 40),
+            items: (u64_at(&self.map, at + 48), u64_at(&self.map, at + 56)),
+        }
+    }
+}
+
+/// Lays out the nodes of `MmapQuadtree::build` depth first, reserving the
+/// four children of a node together so they stay consecutive.
+struct Builder {
+    writer: Counter<BufWriter<File>>,
+    table: Vec<Node>,
+    options: Options,
+    path: PathBuf,
+    scratch: usize,
+}
+
+impl Builder {
+    fn node<T: Position + Persist>(
+        &mut self,
+        index: usize,
+        bounds: Rectangle,
+        depth: u8,
+        spill: Spill,
+    ) -> io::Result<()> {
+        let start = self.writer.written;
+        if spill.count as usize <= self.options.max_items || depth >= self.options.max_depth {
+            io::copy(&mut File::open(&spill.path)?, &mut self.writer)?;
+            self.table[index] = Node {
+                bounds,
+                first_child: NO_CHILDREN,
+                count: spill.count,
+                items: (start, self.writer.written),
+            };
+            return Ok(());
+        }
+
+        let mut parts = (0..4)
+            .map(|_| self.spill())
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut reader = BufReader::new(File::open(&spill.path)?);
+        for _ in 0..spill.count {
+            let item = T::read_from(&mut reader)?;
+            parts[bounds.quadrant(&item.position())].push(&item)?;
+        }
+        drop(spill);
+
+        let first = self.table.len();
+        self.table.extend((0..4).map(|_| placeholder()));
+        let mut count = 0;
+        for (q, part) in parts.into_iter().enumerate() {
+            let part = part.finish()?;
+            count += part.count;
+            self.node::<T>(first + q, bounds.quadrant_cell(q), depth + 1, part)?;
+        }
+        self.table[index] = Node {
+            bounds,
+            first_child: first as u64,
+            count,
+            items: (start, start),
+        };
+        Ok(())
+    }
+
+    fn spill(&mut self) -> io::Result<SpillWriter> {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}.tmp", self.scratch));
+        self.scratch += 1;
+        let path = PathBuf::from(name);
+        let writer = BufWriter::new(File::create(&path)?);
+        Ok(SpillWriter {
+            writer,
+            spill: Spill { path, count: 0 },
+        })
+    }
+}
+
+/// A scratch file of encoded items, removed when dropped.
+struct Spill {
+    path: PathBuf,
+    count: u64,
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+struct SpillWriter {
+    writer: BufWriter<File>,
+    spill: Spill,
+}
+
+impl SpillWriter {
+    fn push<T: Persist>(&mut self, item: &T) -> io::Result<()> {
+        item.write_to(&mut self.writer)?;
+        self.spill.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<Spill> {
+        self.writer.flush()?;
+        Ok(self.spill)
+    }
+}
+
+fn placeholder() -> Node {
+    Node {
+        bounds: Rectangle::new(0.0, 0.0, 0.0, 0.0),
+        first_child: NO_CHILDREN,
+        count: 0,
+        items: (0, 0),
+    }
+}
+
+/// Appends the node table after the items and fills in the header.
+fn finish(mut writer: Counter<BufWriter<File>>, table: &[Node]) -> io::Result<()> {
+    let nodes = writer.written;
+    for node in table {
+        let bounds = node.bounds;
+        for &value in &[bounds.x, bounds.y, bounds.width, bounds.height] {
+            write_f64(&mut writer, value)?;
+        }
+        write_u64(&mut writer, node.first_child)?;
+        write_u64(&mut writer, node.count)?;
+        write_u64(&mut writer, node.items.0)?;
+        write_u64(&mut writer, node.items.1)?;
+    }
+
+    let mut file = writer.inner.into_inner().map_err(|e| e.into_error())?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(MAGIC)?;
+    file.write_all(&VERSION.to_le_bytes())?;
+    file.write_all(&[0; 2])?;
+    write_u64(&mut file, table.len() as u64)?;
+    write_u64(&mut file, nodes)?;
+    file.sync_all()
+}
+
+struct Counter<W> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for Counter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    let mut word = [0; 8];
+    word.copy_from_slice(&bytes[at..at + 8]);
+    u64::from_le_bytes(word)
+}
@@ -0,0 +1,300 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+use crate::{morton, Point, Position, Quadtree, Rectangle};
+
+pub trait Metric {
+    fn distance(&self, a: &Point, b: &Point) -> f64;
+
+    /// Lower bound of `distance` between `point` and any point inside `rect`.
+    fn min_distance(&self, point: &Point, rect: &Rectangle) -> f64;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Euclidean;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Manhattan;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Chebyshev;
+
+impl Metric for Euclidean {
+    fn distance(&self, a: &Point, b: &Point) -> f64 {
+        (a.x - b.x).hypot(a.y - b.y)
+    }
+
+    fn min_distance(&self, point: &Point, rect: &Rectangle) -> f64 {
+        let (dx, dy) = gap(point, rect);
+        dx.hypot(dy)
+    }
+}
+
+impl Metric for Manhattan {
+    fn distance(&self, a: &Point, b: &Point) -> f64 {
+        (a.x - b.x).abs() + (a.y - b.y).abs()
+    }
+
+    fn min_distance(&self, point: &Point, rect: &Rectangle) -> f64 {
+        let (dx, dy) = gap(point, rect);
+        dx + dy
+    }
+}
+
+impl Metric for Chebyshev {
+    fn distance(&self, a: &Point, b: &Point) -> f64 {
+        (a.x - b.x).abs().max((a.y - b.y).abs())
+    }
+
+    fn min_distance(&self, point: &Point, rect: &Rectangle) -> f64 {
+        let (dx, dy) = gap(point, rect);
+        dx.max(dy)
+    }
+}
+
+/// A metric built from a distance closure and a matching rectangle lower bound.
+pub struct FnMetric<D, B> {
+    distance: D,
+    min_distance: B,
+}
+
+impl<D, B> FnMetric<D, B>
+where
+    D: Fn(&Point, &Point) -> f64,
+    B: Fn(&Point, &Rectangle) -> f64,
+{
+    pub fn new(distance: D, min_distance: B) -> Self {
+        Self {
+            distance,
+            min_distance,
+        }
+    }
+}
+
+impl<D, B> Metric for FnMetric<D, B>
+where
+    D: Fn(&Point, &Point) -> f64,
+    B: Fn(&Point, &Rectangle) -> f64,
+{
+    fn distance(&self, a: &Point, b: &Point) -> f64 {
+        (self.distance)(a, b)
+    }
+
+    fn min_distance(&self, point: &Point, rect: &Rectangle) -> f64 {
+        (self.min_distance)(point, rect)
+    }
+}
+
+pub(crate) struct Entry<V> {
+    pub(crate) distance: f64,
+    pub(crate) value: V,
+}
+
+impl<V> PartialEq for Entry<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<V> Eq for Entry<V> {}
+
+impl<V> PartialOrd for Entry<V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<V> Ord for Entry<V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance)
+    }
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    pub fn nearest(&self, point: Point) -> Option<&T> {
+        self.nearest_by(point, &Euclidean)
+    }
+
+    pub fn nearest_by<M: Metric>(&self, point: Point, metric: &M) -> Option<&T> {
+        self.knn_by(point, 1, metric).pop()
+    }
+
+    pub fn knn(&self, point: Point, k: usize) -> Vec<&T> {
+        self.knn_by(point, k, &Euclidean)
+    }
+
+    /// Returns up to `k` items ordered from closest to farthest under `metric`.
+    pub fn knn_by<M: Metric>(&self, point: Point, k: usize, metric: &M) -> Vec<&T> {
+        self.knn_within_by(point, k, f64::INFINITY, metric)
+    }
+
+    pub fn knn_within(&self, point: Point, k: usize, max_distance: f64) -> Vec<&T> {
+        self.knn_within_by(point, k, max_distance, &Euclidean)
+    }
+
+    /// Same as `knn_by`, but ignores items and nodes farther than `max_distance`.
+    pub fn knn_within_by<M: Metric>(
+        &self,
+        point: Point,
+        k: usize,
+        max_distance: f64,
+        metric: &M,
+    ) -> Vec<&T> {
+        let mut best = BinaryHeap::with_capacity(k);
+        let mut nodes = BinaryHeap::new();
+        self.knn_search(point, k, max_distance, metric, &mut best, &mut nodes);
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|e| e.value)
+            .collect()
+    }
+
+    /// Runs `knn` for every point of `points`, reusing the search queues
+    /// between probes and visiting the probes in spatial order so that
+    /// consecutive searches touch the same nodes. Results follow the order
+    /// of `points`.
+    pub fn knn_batch(&self, points: &[Point], k: usize) -> Vec<Vec<&T>> {
+        let mut order: Vec<usize> = (0..points.len()).collect();
+        let bounds = self.bounds();
+        order.sort_by_key(|&i| morton(&bounds, &points[i]));
+
+        let mut results = vec![Vec::new(); points.len()];
+        let mut best = BinaryHeap::with_capacity(k);
+        let mut nodes = BinaryHeap::new();
+        for i in order {
+            self.knn_search(
+                points[i],
+                k,
+                f64::INFINITY,
+                &Euclidean,
+                &mut best,
+                &mut nodes,
+            );
+            let mut sorted = best.into_sorted_vec();
+            results[i] = sorted.iter().map(|e| e.value).collect();
+            sorted.clear();
+            best = BinaryHeap::from(sorted);
+        }
+        results
+    }
+
+    fn knn_search<'a, M: Metric>(
+        &'a self,
+        point: Point,
+        k: usize,
+        max_distance: f64,
+        metric: &M,
+        best: &mut BinaryHeap<Entry<&'a T>>,
+        nodes: &mut BinaryHeap<Reverse<Entry<&'a Quadtree<T, D>>>>,
+    ) {
+        best.clear();
+        nodes.clear();
+        if k == 0 {
+            return;
+        }
+        if let Some(extent) = self.extent {
+            let distance = metric.min_distance(&point, &extent);
+            if distance <= max_distance {
+                nodes.push(Reverse(Entry {
+                    distance,
+                    value: self,
+                }));
+            }
+        }
+        while let Some(Reverse(Entry {
+            distance,
+            value: node,
+        })) = nodes.pop()
+        {
+            if best.len() == k && best.peek().is_some_and(|b| distance >= b.distance) {
+                break;
+            }
+            for item in &node.items {
+                let distance = metric.distance(&point, &item.position());
+                if distance > max_distance {
+                    continue;
+                }
+                if best.len() < k {
+                    best.push(Entry {
+                        distance,
+                        value: item,
+                    });
+                } else if best.peek().is_some_and(|b| distance < b.distance) {
+                    best.pop();
+                    best.push(Entry {
+                        distance,
+                        value: item,
+                    });
+                }
+            }
+            for child in node.children.iter().flatten() {
+                if let Some(extent) = child.extent {
+                    let distance = metric.min_distance(&point, &extent);
+                    if distance <= max_distance {
+                        nodes.push(Reverse(Entry {
+                            distance,
+                            value: &**child,
+                        }));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the item farthest away from `from`, pruning nodes whose item
+    /// extent cannot hold anything farther than the best match so far.
+    pub fn farthest(&self, from: Point) -> Option<&T> {
+        let mut best = None;
+        self.search_farthest(&from, &mut best);
+        best.map(|(_, item)| item)
+    }
+
+    fn search_farthest<'a>(&'a self, from: &Point, best: &mut Option<(f64, &'a T)>) {
+        let extent = match self.extent {
+            Some(extent) => extent,
+            None => return,
+        };
+        if let Some((distance, _)) = *best {
+            if max_distance(from, &extent) <= distance {
+                return;
+            }
+        }
+        for item in &self.items {
+            let point = item.position();
+            let distance = (point.x - from.x).hypot(point.y - from.y);
+            if best.is_none_or(|(d, _)| distance > d) {
+                *best = Some((distance, item));
+            }
+        }
+        if let Some(ref children) = self.children {
+            let mut order: Vec<(f64, &Quadtree<T, D>)> = children
+                .iter()
+                .filter_map(|child| child.extent.map(|e| (max_distance(from, &e), &**child)))
+                .collect();
+            order.sort_by(|a, b| b.0.total_cmp(&a.0));
+            for (_, child) in order {
+                child.search_farthest(from, best);
+            }
+        }
+    }
+}
+
+fn max_distance(point: &Point, rect: &Rectangle) -> f64 {
+    let dx = (point.x - rect.x)
+        .abs()
+        .max((point.x - rect.x - rect.width).abs());
+    let dy = (point.y - rect.y)
+        .abs()
+        .max((point.y - rect.y - rect.height).abs());
+    dx.hypot(dy)
+}
+
+pub(crate) fn gap(point: &Point, rect: &Rectangle) -> (f64, f64) {
+    let dx = (rect.x - point.x)
+        .max(point.x - rect.x - rect.width)
+        .max(0.0);
+    let dy = (rect.y - point.y)
+        .max(point.y - rect.y - rect.height)
+        .max(0.0);
+    (dx, dy)
+}
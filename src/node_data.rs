@@ -0,0 +1,99 @@
+use crate::{Point, Position, Quadtree, Rectangle};
+
+const MAX_REF_DEPTH: u8 = 64;
+
+/// Addresses a node by its path from the root. A reference keeps pointing at
+/// the same cell across edits, and resolves to nothing while that cell is not
+/// subdivided out of the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeRef {
+    path: u128,
+    depth: u8,
+}
+
+impl NodeRef {
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// The quadrants taken from the root to reach the node, numbered as in
+    /// `Cursor::descend`.
+    pub fn quadrants(&self) -> Vec<usize> {
+        (0..self.depth).map(|level| self.quadrant(level)).collect()
+    }
+
+    fn child(&self, quadrant: usize) -> NodeRef {
+        NodeRef {
+            path: self.path | (quadrant as u128) << (2 * u32::from(self.depth)),
+            depth: self.depth + 1,
+        }
+    }
+
+    fn quadrant(&self, level: u8) -> usize {
+        (self.path >> (2 * u32::from(level)) & 3) as usize
+    }
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Lists the nodes of the tree in pre-order. Nodes more than 64 levels
+    /// deep cannot be addressed and are left out.
+    pub fn node_refs(&self) -> Vec<NodeRef> {
+        let mut refs = Vec::new();
+        self.collect_refs(NodeRef { path: 0, depth: 0 }, &mut refs);
+        refs
+    }
+
+    /// Returns the deepest node whose cell contains `point`, or `None` if the
+    /// point lies outside the tree. Its bounds are given by `node_bounds`.
+    /// The search stops 64 levels deep, where nodes cannot be addressed.
+    pub fn leaf_at(&self, point: Point) -> Option<NodeRef> {
+        if !self._contains(&point, &self.bounds()) {
+            return None;
+        }
+        let mut node = NodeRef { path: 0, depth: 0 };
+        let mut current = self;
+        while let Some(ref children) = current.children {
+            if node.depth >= MAX_REF_DEPTH {
+                break;
+            }
+            let quadrant = current.quadrant(&point);
+            node = node.child(quadrant);
+            current = &children[quadrant];
+        }
+        Some(node)
+    }
+
+    pub fn node_bounds(&self, node: NodeRef) -> Option<Rectangle> {
+        self.resolve(node).map(Quadtree::bounds)
+    }
+
+    pub fn node_data(&self, node: NodeRef) -> Option<&D> {
+        self.resolve(node).map(|node| &node.data)
+    }
+
+    pub fn node_data_mut(&mut self, node: NodeRef) -> Option<&mut D> {
+        let mut current = self;
+        for level in 0..node.depth {
+            current = current.children.as_mut()?[node.quadrant(level)].as_mut();
+        }
+        Some(&mut current.data)
+    }
+
+    fn collect_refs(&self, node: NodeRef, refs: &mut Vec<NodeRef>) {
+        refs.push(node);
+        if node.depth >= MAX_REF_DEPTH {
+            return;
+        }
+        for (quadrant, child) in self.children.iter().flatten().enumerate() {
+            child.collect_refs(node.child(quadrant), refs);
+        }
+    }
+
+    fn resolve(&self, node: NodeRef) -> Option<&Quadtree<T, D>> {
+        let mut current = self;
+        for level in 0..node.depth {
+            current = &current.children.as_ref()?[node.quadrant(level)];
+        }
+        Some(current)
+    }
+}
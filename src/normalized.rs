@@ -0,0 +1,132 @@
+use crate::nearest::gap;
+use crate::{Metric, Options, Point, Position, Quadtree, Rectangle};
+
+/// An item stored with its position mapped into the unit square.
+struct Unit<T> {
+    point: Point,
+    item: T,
+}
+
+impl<T> Position for Unit<T> {
+    fn position(&self) -> Point {
+        self.point
+    }
+}
+
+/// Euclidean distance between world positions, measured on unit-square
+/// coordinates.
+struct WorldDistance {
+    width: f64,
+    height: f64,
+}
+
+impl Metric for WorldDistance {
+    fn distance(&self, a: &Point, b: &Point) -> f64 {
+        ((a.x - b.x) * self.width).hypot((a.y - b.y) * self.height)
+    }
+
+    fn min_distance(&self, point: &Point, rect: &Rectangle) -> f64 {
+        let (dx, dy) = gap(point, rect);
+        (dx * self.width).hypot(dy * self.height)
+    }
+}
+
+/// A quadtree over the unit square that takes and returns world
+/// coordinates. Every node splits at an exact binary fraction and positions
+/// keep the same relative precision wherever the world bounds lie.
+/// `Options::epsilon` is measured in unit-square coordinates.
+pub struct NormalizedQuadtree<T> {
+    tree: Quadtree<Unit<T>>,
+    world: Rectangle,
+}
+
+impl<T: Position> NormalizedQuadtree<T> {
+    pub fn new(world: Rectangle) -> Self {
+        Self::with_options(world, Default::default())
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `world` does not have a finite, positive width and height.
+    pub fn with_options(world: Rectangle, options: Options) -> Self {
+        assert!(
+            world.has_area(),
+            "quadtree boundary must have a finite, positive area: {}",
+            world
+        );
+        Self {
+            tree: Quadtree::with_options(Rectangle::new(0.0, 0.0, 1.0, 1.0), options),
+            world,
+        }
+    }
+
+    pub fn to_unit(&self, point: Point) -> Point {
+        Point::new(
+            (point.x - self.world.x) / self.world.width,
+            (point.y - self.world.y) / self.world.height,
+        )
+    }
+
+    pub fn to_world(&self, point: Point) -> Point {
+        Point::new(
+            self.world.x + point.x * self.world.width,
+            self.world.y + point.y * self.world.height,
+        )
+    }
+
+    /// Stores `item`. Items outside the world bounds are skipped, as with
+    /// `Quadtree::put`.
+    pub fn put(&mut self, item: T) {
+        let point = self.to_unit(item.position());
+        self.tree.put(Unit { point, item });
+    }
+
+    /// Returns the items inside the world-space rectangle `range`, checked
+    /// against their world positions.
+    pub fn query(&self, range: Rectangle) -> Vec<&T> {
+        let unit = Rectangle::from_corners(
+            self.to_unit(Point::new(range.x, range.y)),
+            self.to_unit(Point::new(range.x + range.width, range.y + range.height)),
+        );
+        let mut items = Vec::new();
+        self.tree.visit(&unit.inflated(f64::EPSILON), &mut |unit| {
+            if self.tree._contains(&unit.item.position(), &range) {
+                items.push(&unit.item);
+            }
+        });
+        items
+    }
+
+    /// Returns the item closest to `point` in world space.
+    pub fn nearest(&self, point: Point) -> Option<&T> {
+        let metric = WorldDistance {
+            width: self.world.width,
+            height: self.world.height,
+        };
+        self.tree
+            .nearest_by(self.to_unit(point), &metric)
+            .map(|unit| &unit.item)
+    }
+
+    /// Removes and returns the first item stored at the world position
+    /// `point`.
+    pub fn remove(&mut self, point: Point) -> Option<T> {
+        self.tree.remove(self.to_unit(point)).map(|unit| unit.item)
+    }
+
+    pub fn world_bounds(&self) -> Rectangle {
+        self.world
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.tree.clear();
+    }
+}
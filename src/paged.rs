@@ -0,0 +1,509 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::persist::{invalid, read_f64, read_u64, write_f64, write_u64, Persist};
+use crate::{Options, Point, Position, Rectangle};
+
+const MAGIC: &[u8; 4] = b"QDTP";
+const VERSION: u16 = 1;
+const PAGE_SIZE: usize = 4096;
+const PAGE_HEADER_LEN: usize = 56;
+const ROOT: u64 = 1;
+const NO_CHILDREN: u64 = u64::MAX;
+const NIL: u64 = u64::MAX;
+
+/// A cached page, linked into the cache's recency list.
+struct Page<T> {
+    bounds: Rectangle,
+    depth: u8,
+    first_child: u64,
+    items: Vec<T>,
+    dirty: bool,
+    newer: u64,
+    older: u64,
+}
+
+/// A page laid out by `PagedQuadtree::plan_split`, holding the indices of
+/// the items that go into it.
+struct Planned {
+    id: u64,
+    bounds: Rectangle,
+    depth: u8,
+    first_child: u64,
+    items: Vec<usize>,
+}
+
+/// A quadtree stored in a file of fixed-size 4 KiB pages, one page per
+/// node. Pages are loaded on demand into an LRU cache of `cache_pages`
+/// pages; modified pages are written back when they are evicted, on
+/// `flush` and when the tree is dropped.
+///
+/// Page 0 holds the header; the root is page 1 and the four children of a
+/// node are stored in consecutive pages. A leaf whose items no longer fit
+/// in a page is split, and an insert fails if the leaf is already at
+/// `max_depth`.
+pub struct PagedQuadtree<T: Position + Persist> {
+    file: File,
+    options: Options,
+    cache: HashMap<u64, Page<T>>,
+    cache_pages: usize,
+    /// Most and least recently used cached pages.
+    newest: u64,
+    oldest: u64,
+    page_count: u64,
+    count: u64,
+}
+
+impl<T: Position + Persist> PagedQuadtree<T> {
+    /// Creates a new, empty store at `path`, replacing any existing file.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `boundary` does not have a finite, positive width and height.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        boundary: Rectangle,
+        options: Options,
+        cache_pages: usize,
+    ) -> io::Result<Self> {
+        assert!(
+            boundary.has_area(),
+            "quadtree boundary must have a finite, positive area: {}",
+            boundary
+        );
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let mut tree = Self {
+            file,
+            options,
+            cache: HashMap::new(),
+            cache_pages: cache_pages.max(1),
+            newest: NIL,
+            oldest: NIL,
+            page_count: ROOT + 1,
+            count: 0,
+        };
+        tree.cache_page(ROOT, Page::leaf(boundary, options.depth))?;
+        tree.flush()?;
+        Ok(tree)
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P, cache_pages: usize) -> io::Result<Self> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        let mut magic = [0; 4];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a paged quadtree file"));
+        }
+        let mut version = [0; 2];
+        file.read_exact(&mut version)?;
+        let version = u16::from_le_bytes(version);
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported format version {}", version)));
+        }
+        let mut depth = [0; 2];
+        file.read_exact(&mut depth)?;
+        let page_count = read_u64(&mut file)?;
+        let count = read_u64(&mut file)?;
+        let max_items = read_u64(&mut file)? as usize;
+        if page_count <= ROOT || file.metadata()?.len() < page_count * PAGE_SIZE as u64 {
+            return Err(invalid("truncated page file"));
+        }
+        Ok(Self {
+            file,
+            options: Options {
+                max_items,
+                max_depth: depth[0],
+                depth: depth[1],
+                ..Default::default()
+            },
+            cache: HashMap::new(),
+            cache_pages: cache_pages.max(1),
+            newest: NIL,
+            oldest: NIL,
+            page_count,
+            count,
+        })
+    }
+
+    pub fn bounds(&mut self) -> io::Result<Rectangle> {
+        Ok(self.page(ROOT)?.bounds)
+    }
+
+    /// Inserts `item`, returning `false` if it lies outside the boundary.
+    pub fn put(&mut self, item: T) -> io::Result<bool> {
+        let point = item.position();
+        if !(self.page(ROOT)?.bounds).contains_point(&point) {
+            return Ok(false);
+        }
+        let leaf = self.leaf_for(&point)?;
+        self.insert_at(leaf, item)?;
+        self.count += 1;
+        Ok(true)
+    }
+
+    /// Removes and returns an item located exactly at `point`. Nodes are not
+    /// merged back after removals.
+    pub fn remove(&mut self, point: Point) -> io::Result<Option<T>> {
+        if !(self.page(ROOT)?.bounds).contains_point(&point) {
+            return Ok(None);
+        }
+        let leaf = self.leaf_for(&point)?;
+        let page = self.page(leaf)?;
+        let removed = page
+            .items
+            .iter()
+            .position(|item| item.position() == point)
+            .map(|i| page.items.swap_remove(i));
+        if removed.is_some() {
+            page.dirty = true;
+            self.count -= 1;
+        }
+        Ok(removed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns the number of pages currently held in memory.
+    pub fn cached_pages(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Writes every modified page and the header to disk.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let mut dirty: Vec<u64> = self
+            .cache
+            .iter()
+            .filter(|(_, page)| page.dirty)
+            .map(|(&id, _)| id)
+            .collect();
+        dirty.sort_unstable();
+        for id in dirty {
+            self.write_page(id)?;
+        }
+        self.write_header()?;
+        self.file.flush()
+    }
+
+    fn leaf_for(&mut self, point: &Point) -> io::Result<u64> {
+        let mut id = ROOT;
+        loop {
+            let page = self.page(id)?;
+            if page.first_child == NO_CHILDREN {
+                return Ok(id);
+            }
+            id = page.first_child + page.bounds.quadrant(point) as u64;
+        }
+    }
+
+    /// Adds `item` to the leaf `id`, splitting the leaf when it overflows.
+    /// The new pages are written to disk before the leaf gives up its items,
+    /// so a failed split leaves the leaf as it was.
+    fn insert_at(&mut self, id: u64, item: T) -> io::Result<()> {
+        let (max_items, max_depth) = (self.options.max_items, self.options.max_depth);
+        let page = self.page(id)?;
+        page.items.push(item);
+        page.dirty = true;
+        let fits = page.fits()?;
+        if fits && (page.items.len() <= max_items || page.depth >= max_depth) {
+            return Ok(());
+        }
+        let split = if page.depth >= max_depth {
+            Err(io::Error::other("leaf page is full at max_depth"))
+        } else {
+            self.split(id)
+        };
+        if split.is_err() {
+            self.page(id)?.items.pop();
+        }
+        split
+    }
+
+    fn split(&mut self, id: u64) -> io::Result<()> {
+        let first = self.page_count;
+        let mut next = first;
+        let mut planned = Vec::new();
+        let page = &self.cache[&id];
+        let sizes = page
+            .items
+            .iter()
+            .map(|item| {
+                let mut bytes = Vec::new();
+                item.write_to(&mut bytes).map(|()| bytes.len())
+            })
+            .collect::<io::Result<Vec<usize>>>()?;
+        self.plan_split(
+            page.bounds,
+            page.depth,
+            &page.items,
+            (0..page.items.len()).collect(),
+            &sizes,
+            &mut next,
+            &mut planned,
+        )?;
+
+        let pages = planned
+            .iter()
+            .map(|planned| {
+                let items = planned.items.iter().map(|&i| &page.items[i]);
+                encode(planned.bounds, planned.depth, planned.first_child, items)
+                    .map(|bytes| (planned.id, bytes))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        for (id, bytes) in pages {
+            self.write_at(id, bytes)?;
+        }
+
+        self.page_count = next;
+        let page = self.cache.get_mut(&id).unwrap();
+        page.first_child = first;
+        page.items.clear();
+        page.dirty = true;
+        Ok(())
+    }
+
+    /// Lays out the pages below a node at `bounds` whose items overflow it,
+    /// numbering them from `next`, and returns the first child page.
+    #[allow(clippy::too_many_arguments)]
+    fn plan_split(
+        &self,
+        bounds: Rectangle,
+        depth: u8,
+        items: &[T],
+        indices: Vec<usize>,
+        sizes: &[usize],
+        next: &mut u64,
+        planned: &mut Vec<Planned>,
+    ) -> io::Result<u64> {
+        let first = *next;
+        *next += 4;
+        let mut parts: [Vec<usize>; 4] = Default::default();
+        for i in indices {
+            parts[bounds.quadrant(&items[i].position())].push(i);
+        }
+        let depth = depth + 1;
+        for (q, part) in (0..4u64).zip(parts) {
+            let cell = bounds.quadrant_cell(q as usize);
+            let size = PAGE_HEADER_LEN + part.iter().map(|&i| sizes[i]).sum::<usize>();
+            let first_child = if size <= PAGE_SIZE
+                && (part.len() <= self.options.max_items || depth >= self.options.max_depth)
+            {
+                NO_CHILDREN
+            } else if depth >= self.options.max_depth {
+                return Err(io::Error::other("leaf page is full at max_depth"));
+            } else {
+                let first_child =
+                    self.plan_split(cell, depth, items, part, sizes, next, planned)?;
+                planned.push(Planned {
+                    id: first + q,
+                    bounds: cell,
+                    depth,
+                    first_child,
+                    items: Vec::new(),
+                });
+                continue;
+            };
+            planned.push(Planned {
+                id: first + q,
+                bounds: cell,
+                depth,
+                first_child,
+                items: part,
+            });
+        }
+        Ok(first)
+    }
+
+    fn page(&mut self, id: u64) -> io::Result<&mut Page<T>> {
+        if self.cache.contains_key(&id) {
+            self.unlink(id);
+            self.link_newest(id);
+        } else {
+            if id >= self.page_count {
+                return Err(invalid("page index out of bounds"));
+            }
+            let page = self.read_page(id)?;
+            self.cache_page(id, page)?;
+        }
+        Ok(self.cache.get_mut(&id).unwrap())
+    }
+
+    fn cache_page(&mut self, id: u64, page: Page<T>) -> io::Result<()> {
+        while self.cache.len() >= self.cache_pages {
+            let oldest = self.oldest;
+            if self.cache[&oldest].dirty {
+                self.write_page(oldest)?;
+            }
+            self.unlink(oldest);
+            self.cache.remove(&oldest);
+        }
+        self.cache.insert(id, page);
+        self.link_newest(id);
+        Ok(())
+    }
+
+    fn unlink(&mut self, id: u64) {
+        let page = &self.cache[&id];
+        let (newer, older) = (page.newer, page.older);
+        match newer {
+            NIL => self.newest = older,
+            newer => self.cache.get_mut(&newer).unwrap().older = older,
+        }
+        match older {
+            NIL => self.oldest = newer,
+            older => self.cache.get_mut(&older).unwrap().newer = newer,
+        }
+    }
+
+    fn link_newest(&mut self, id: u64) {
+        let previous = self.newest;
+        let page = self.cache.get_mut(&id).unwrap();
+        page.newer = NIL;
+        page.older = previous;
+        match previous {
+            NIL => self.oldest = id,
+            previous => self.cache.get_mut(&previous).unwrap().newer = id,
+        }
+        self.newest = id;
+    }
+
+    fn read_page(&mut self, id: u64) -> io::Result<Page<T>> {
+        let mut bytes = vec![0; PAGE_SIZE];
+        self.file.seek(SeekFrom::Start(id * PAGE_SIZE as u64))?;
+        self.file.read_exact(&mut bytes)?;
+        let mut reader = bytes.as_slice();
+        let bounds = Rectangle::new(
+            read_f64(&mut reader)?,
+            read_f64(&mut reader)?,
+            read_f64(&mut reader)?,
+            read_f64(&mut reader)?,
+        );
+        let depth = reader[0];
+        reader = &reader[8..];
+        let first_child = read_u64(&mut reader)?;
+        let len = read_u64(&mut reader)? as usize;
+        let mut items = Vec::with_capacity(len.min(PAGE_SIZE - PAGE_HEADER_LEN));
+        for _ in 0..len {
+            items.push(T::read_from(&mut reader)?);
+        }
+        Ok(Page {
+            bounds,
+            depth,
+            first_child,
+            items,
+            dirty: false,
+            newer: NIL,
+            older: NIL,
+        })
+    }
+
+    fn write_page(&mut self, id: u64) -> io::Result<()> {
+        let page = &self.cache[&id];
+        let bytes = encode(page.bounds, page.depth, page.first_child, page.items.iter())?;
+        self.write_at(id, bytes)?;
+        self.cache.get_mut(&id).unwrap().dirty = false;
+        Ok(())
+    }
+
+    fn write_at(&mut self, id: u64, mut bytes: Vec<u8>) -> io::Result<()> {
+        bytes.resize(PAGE_SIZE, 0);
+        self.file.seek(SeekFrom::Start(id * PAGE_SIZE as u64))?;
+        self.file.write_all(&bytes)
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(PAGE_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&[self.options.max_depth, self.options.depth]);
+        write_u64(&mut bytes, self.page_count)?;
+        write_u64(&mut bytes, self.count)?;
+        write_u64(&mut bytes, self.options.max_items as u64)?;
+        bytes.resize(PAGE_SIZE, 0);
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&bytes)?;
+        self.file.set_len(self.page_count * PAGE_SIZE as u64)
+    }
+}
+
+impl<T: Position + Persist + Clone> PagedQuadtree<T> {
+    pub fn query(&mut self, range: Rectangle) -> io::Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut stack = vec![ROOT];
+        while let Some(id) = stack.pop() {
+            let page = self.page(id)?;
+            if page.bounds.intersection(&range).is_none() {
+                continue;
+            }
+            items.extend(
+                page.items
+                    .iter()
+                    .filter(|item| range.contains_point(&item.position()))
+                    .cloned(),
+            );
+            if page.first_child != NO_CHILDREN {
+                let first = page.first_child;
+                stack.extend((first..first + 4).rev());
+            }
+        }
+        Ok(items)
+    }
+}
+
+impl<T: Position + Persist> Drop for PagedQuadtree<T> {
+    fn drop(&mut self) {
+        // Errors cannot be reported from here; call `flush` to observe them.
+        let _ = self.flush();
+    }
+}
+
+impl<T: Persist> Page<T> {
+    fn leaf(bounds: Rectangle, depth: u8) -> Self {
+        Page {
+            bounds,
+            depth,
+            first_child: NO_CHILDREN,
+            items: Vec::new(),
+            dirty: true,
+            newer: NIL,
+            older: NIL,
+        }
+    }
+
+    fn fits(&self) -> io::Result<bool> {
+        let bytes = encode(self.bounds, self.depth, self.first_child, self.items.iter())?;
+        Ok(bytes.len() <= PAGE_SIZE)
+    }
+}
+
+fn encode<'a, T: Persist + 'a, I: ExactSizeIterator<Item = &'a T>>(
+    bounds: Rectangle,
+    depth: u8,
+    first_child: u64,
+    items: I,
+) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(PAGE_SIZE);
+    for &value in &[bounds.x, bounds.y, bounds.width, bounds.height] {
+        write_f64(&mut bytes, value)?;
+    }
+    bytes.extend_from_slice(&[depth, 0, 0, 0, 0, 0, 0, 0]);
+    write_u64(&mut bytes, first_child)?;
+    write_u64(&mut bytes, items.len() as u64)?;
+    debug_assert_eq!(bytes.len(), PAGE_HEADER_LEN);
+    for item in items {
+        item.write_to(&mut bytes)?;
+    }
+    Ok(bytes)
+}
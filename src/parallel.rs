@@ -0,0 +1,47 @@
+use rayon::prelude::*;
+
+use crate::{Point, Position, Quadtree, Rectangle};
+
+const QUERY_CHUNK: usize = 64;
+
+impl<T: Position + Sync, D: Default> Quadtree<T, D> {
+    /// Iterates over all stored items in parallel, splitting work by node.
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = &T> {
+        let mut slices = Vec::new();
+        self.collect_slices(&mut slices);
+        slices.into_par_iter().flat_map(|items| items.par_iter())
+    }
+
+    /// Like `query_many`, but spreads chunks of the ranges over the rayon
+    /// thread pool.
+    pub fn par_query_many(&self, rects: &[Rectangle]) -> Vec<Vec<&T>>
+    where
+        D: Sync,
+    {
+        rects
+            .par_chunks(QUERY_CHUNK)
+            .flat_map_iter(|chunk| self.query_many(chunk))
+            .collect()
+    }
+
+    /// Like `knn_batch`, but spreads chunks of the probes over the rayon
+    /// thread pool.
+    pub fn par_knn_batch(&self, points: &[Point], k: usize) -> Vec<Vec<&T>>
+    where
+        D: Sync,
+    {
+        points
+            .par_chunks(QUERY_CHUNK)
+            .flat_map_iter(|chunk| self.knn_batch(chunk, k))
+            .collect()
+    }
+
+    fn collect_slices<'a>(&'a self, slices: &mut Vec<&'a [T]>) {
+        if !self.items.is_empty() {
+            slices.push(&self.items);
+        }
+        for child in self.children.iter().flatten() {
+            child.collect_slices(slices);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::{Options, Point, Position, Quadtree, Rectangle};
+
+const MAGIC: &[u8; 4] = b"QDTR";
+const VERSION: u16 = 1;
+
+/// Item types that can be written to and read back from the on-disk format.
+pub trait Persist: Sized {
+    fn write_to(&self, writer: &mut dyn Write) -> io::Result<()>;
+
+    fn read_from(reader: &mut dyn Read) -> io::Result<Self>;
+}
+
+impl Persist for Point {
+    fn write_to(&self, writer: &mut dyn Write) -> io::Result<()> {
+        write_f64(writer, self.x)?;
+        write_f64(writer, self.y)
+    }
+
+    fn read_from(reader: &mut dyn Read) -> io::Result<Self> {
+        Ok(Point::new(read_f64(reader)?, read_f64(reader)?))
+    }
+}
+
+impl Persist for (f64, f64) {
+    fn write_to(&self, writer: &mut dyn Write) -> io::Result<()> {
+        write_f64(writer, self.0)?;
+        write_f64(writer, self.1)
+    }
+
+    fn read_from(reader: &mut dyn Read) -> io::Result<Self> {
+        Ok((read_f64(reader)?, read_f64(reader)?))
+    }
+}
+
+impl Persist for [f64; 2] {
+    fn write_to(&self, writer: &mut dyn Write) -> io::Result<()> {
+        write_f64(writer, self[0])?;
+        write_f64(writer, self[1])
+    }
+
+    fn read_from(reader: &mut dyn Read) -> io::Result<Self> {
+        Ok([read_f64(reader)?, read_f64(reader)?])
+    }
+}
+
+impl<T: Position + Persist, D: Default> Quadtree<T, D> {
+    /// Writes the boundary, options and items behind a versioned header.
+    /// Observers, metrics and node data are not persisted.
+    pub fn write_to(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        write_settings(writer, &self.bounds(), &self.options)?;
+        write_u64(writer, self.count as u64)?;
+        self.write_items(writer)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    fn write_items(&self, writer: &mut dyn Write) -> io::Result<()> {
+        for item in &self.items {
+            item.write_to(writer)?;
+        }
+        for child in self.children.iter().flatten() {
+            child.write_items(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Position + Persist> Quadtree<T> {
+    /// Rebuilds a tree written by `write_to`, accepting every format version
+    /// this crate has produced.
+    pub fn read_from(reader: &mut dyn Read) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a quadtree file"));
+        }
+        let mut version = [0; 2];
+        reader.read_exact(&mut version)?;
+        match u16::from_le_bytes(version) {
+            1 => Self::read_v1(reader),
+            version => Err(invalid(&format!("unsupported format version {}", version))),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    fn read_v1(reader: &mut dyn Read) -> io::Result<Self> {
+        let (boundary, options) = read_settings(reader)?;
+        let mut tree = Quadtree::with_options(boundary, options);
+        for _ in 0..read_u64(reader)? {
+            tree.put(T::read_from(reader)?);
+        }
+        tree.clear_dirty();
+        Ok(tree)
+    }
+}
+
+/// Writes the boundary and every option but `depth`, which is always zero at
+/// the root, as laid out by version 1.
+pub(crate) fn write_settings(
+    writer: &mut dyn Write,
+    boundary: &Rectangle,
+    options: &Options,
+) -> io::Result<()> {
+    for &value in &[boundary.x, boundary.y, boundary.width, boundary.height] {
+        write_f64(writer, value)?;
+    }
+    write_u64(writer, options.max_items as u64)?;
+    writer.write_all(&[
+        options.max_depth,
+        options.track_dirty as u8,
+        options.collect_metrics as u8,
+        options.defer_collapse as u8,
+    ])?;
+    write_f64(writer, options.epsilon)?;
+    write_f64(writer, options.snap)?;
+    write_f64(writer, options.max_radius)?;
+    write_u64(writer, options.max_total_items as u64)?;
+    writer.write_all(&[options.reuse_nodes as u8])
+}
+
+pub(crate) fn read_settings(reader: &mut dyn Read) -> io::Result<(Rectangle, Options)> {
+    let boundary = Rectangle::new(
+        read_f64(reader)?,
+        read_f64(reader)?,
+        read_f64(reader)?,
+        read_f64(reader)?,
+    );
+    if !boundary.has_area() {
+        return Err(invalid("degenerate boundary"));
+    }
+    let max_items = read_u64(reader)? as usize;
+    let mut flags = [0; 4];
+    reader.read_exact(&mut flags)?;
+    let epsilon = read_f64(reader)?;
+    let snap = read_f64(reader)?;
+    let max_radius = read_f64(reader)?;
+    let max_total_items = read_u64(reader)? as usize;
+    let mut reuse_nodes = [0];
+    reader.read_exact(&mut reuse_nodes)?;
+    if !(snap.is_finite() && snap >= 0.0) {
+        return Err(invalid("invalid snap cell"));
+    }
+    if !(max_radius.is_finite() && max_radius >= 0.0) {
+        return Err(invalid("invalid max radius"));
+    }
+    let options = Options {
+        max_items,
+        max_depth: flags[0],
+        track_dirty: flags[1] != 0,
+        collect_metrics: flags[2] != 0,
+        defer_collapse: flags[3] != 0,
+        epsilon,
+        snap,
+        max_radius,
+        reuse_nodes: reuse_nodes[0] != 0,
+        max_total_items,
+        ..Default::default()
+    };
+    Ok((boundary, options))
+}
+
+pub(crate) fn write_f64(writer: &mut dyn Write, value: f64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub(crate) fn write_u64(writer: &mut dyn Write, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub(crate) fn read_f64(reader: &mut dyn Read) -> io::Result<f64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_u64(reader: &mut dyn Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+pub(crate) fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
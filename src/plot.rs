@@ -0,0 +1,83 @@
+use plotters::coord::Shift;
+use plotters::drawing::{DrawingArea, DrawingAreaErrorKind};
+use plotters::element::{Circle, Rectangle as Rect};
+use plotters::prelude::DrawingBackend;
+use plotters::style::{Color, ShapeStyle, BLACK, RED};
+
+use crate::{DebugDraw, Point, Position, Quadtree, Rectangle};
+
+/// Styles used by `Quadtree::plot`.
+#[derive(Debug, Clone, Copy)]
+pub struct PlotStyle {
+    pub node: ShapeStyle,
+    pub point: ShapeStyle,
+    /// Radius of the point markers, in pixels.
+    pub point_size: u32,
+}
+
+impl Default for PlotStyle {
+    fn default() -> PlotStyle {
+        PlotStyle {
+            node: BLACK.stroke_width(1),
+            point: RED.filled(),
+            point_size: 2,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Shapes {
+    nodes: Vec<Rectangle>,
+    points: Vec<Point>,
+}
+
+impl DebugDraw for Shapes {
+    fn draw_rect(&mut self, bounds: Rectangle, _depth: u8) {
+        self.nodes.push(bounds);
+    }
+
+    fn draw_point(&mut self, point: Point) {
+        self.points.push(point);
+    }
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Draws the outline of every node and a marker for every item onto
+    /// `area`, with the tree boundary stretched over the whole area and `y`
+    /// growing downwards.
+    pub fn plot<DB: DrawingBackend>(
+        &self,
+        area: &DrawingArea<DB, Shift>,
+        style: &PlotStyle,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        let mut shapes = Shapes::default();
+        self.debug_draw(&mut shapes);
+
+        let bounds = self.bounds();
+        let (width, height) = area.dim_in_pixel();
+        let to_pixel = |x: f64, y: f64| {
+            (
+                ((x - bounds.x) / bounds.width * f64::from(width.saturating_sub(1))).round() as i32,
+                ((y - bounds.y) / bounds.height * f64::from(height.saturating_sub(1))).round()
+                    as i32,
+            )
+        };
+        for node in &shapes.nodes {
+            area.draw(&Rect::new(
+                [
+                    to_pixel(node.x, node.y),
+                    to_pixel(node.x + node.width, node.y + node.height),
+                ],
+                style.node,
+            ))?;
+        }
+        for point in &shapes.points {
+            area.draw(&Circle::new(
+                to_pixel(point.x, point.y),
+                style.point_size,
+                style.point,
+            ))?;
+        }
+        area.present()
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+
+use crate::{Position, Quadtree, Rectangle};
+
+/// How finely the coordinates of a tree can be subdivided, as reported by
+/// `Quadtree::precision_report`.
+#[derive(Debug, Clone, Copy)]
+pub struct PrecisionReport {
+    /// Gap between adjacent `f64` values at the boundary coordinate farthest
+    /// from zero.
+    pub ulp: f64,
+    /// Deepest level whose cells are still at least `ulp` wide and high.
+    pub max_exact_depth: u32,
+    /// The cells at `max_exact_depth`, the smallest the boundary can
+    /// represent, anchored at the boundary origin.
+    pub smallest_cell: Rectangle,
+    /// Whether `Options::max_depth` goes deeper than `max_exact_depth`, in
+    /// which case the deepest nodes split at rounded midpoints. Moving the
+    /// data next to the origin, a `NormalizedQuadtree` or a `ScaledQuadtree`
+    /// avoids it.
+    pub lossy: bool,
+}
+
+impl fmt::Display for PrecisionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ulp {:e}, exact to depth {} (cells {:e} x {:e}){}",
+            self.ulp,
+            self.max_exact_depth,
+            self.smallest_cell.width,
+            self.smallest_cell.height,
+            if self.lossy {
+                ", max_depth is lossy"
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Reports the smallest cell the boundary coordinates can represent and
+    /// whether `Options::max_depth` subdivides past it.
+    pub fn precision_report(&self) -> PrecisionReport {
+        let bounds = self.bounds();
+        let magnitude = [
+            bounds.x,
+            bounds.y,
+            bounds.x + bounds.width,
+            bounds.y + bounds.height,
+        ]
+        .iter()
+        .fold(0.0f64, |max, coordinate| max.max(coordinate.abs()));
+        let ulp = f64::from_bits(magnitude.to_bits() + 1) - magnitude;
+
+        let (mut width, mut height) = (bounds.width, bounds.height);
+        let mut depth = 0;
+        while width / 2.0 >= ulp && height / 2.0 >= ulp {
+            width /= 2.0;
+            height /= 2.0;
+            depth += 1;
+        }
+        PrecisionReport {
+            ulp,
+            max_exact_depth: depth,
+            smallest_cell: Rectangle::new(bounds.x, bounds.y, width, height),
+            lossy: u32::from(self.options.max_depth) > depth,
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{Options, Point, Position, Quadtree, Rectangle};
+
+pub trait Moving {
+    /// Displacement per unit of time as `(dx, dy)`.
+    fn velocity(&self) -> (f64, f64);
+}
+
+/// An item stored with how far it may move over one tick, which the tree
+/// reports as its reach so every node tracks the longest sweep below it.
+struct Swept<T> {
+    item: T,
+    reach: f64,
+}
+
+impl<T: Position> Position for Swept<T> {
+    fn position(&self) -> Point {
+        self.item.position()
+    }
+
+    fn reach(&self) -> f64 {
+        self.reach
+    }
+}
+
+/// A quadtree whose queries match items by the area they sweep during the
+/// next tick instead of their current position only.
+pub struct PredictiveQuadtree<T> {
+    tree: Quadtree<Swept<T>>,
+    tick: f64,
+}
+
+impl<T: Position + Moving> PredictiveQuadtree<T> {
+    pub fn new(boundary: Rectangle, tick: f64) -> Self {
+        Self::with_options(boundary, Default::default(), tick)
+    }
+
+    pub fn with_options(boundary: Rectangle, options: Options, tick: f64) -> Self {
+        Self {
+            tree: Quadtree::with_options(boundary, options),
+            tick,
+        }
+    }
+
+    pub fn put(&mut self, item: T) {
+        let (dx, dy) = item.velocity();
+        let reach = (dx * self.tick).abs().max((dy * self.tick).abs());
+        self.tree.put(Swept { item, reach });
+    }
+
+    /// Removes and returns an item stored exactly at `point`.
+    pub fn remove(&mut self, point: Point) -> Option<T> {
+        self.tree.remove(point).map(|swept| swept.item)
+    }
+
+    /// Replaces an item stored exactly at `point` with `item`, such as the
+    /// same unit after it moved or changed velocity, and returns the item
+    /// it replaced. `item` is stored even if nothing was at `point`.
+    pub fn update(&mut self, point: Point, item: T) -> Option<T> {
+        let old = self.remove(point);
+        self.put(item);
+        old
+    }
+
+    /// Returns the items whose path over the next tick touches `range`.
+    /// Nodes are skipped unless their items, grown by the longest sweep
+    /// stored below them, reach `range`.
+    pub fn query(&self, range: Rectangle) -> Vec<&T> {
+        let mut items = Vec::new();
+        self.visit_swept(&self.tree, &range, &mut items);
+        items
+    }
+
+    pub fn swept_bounds(&self, item: &T) -> Rectangle {
+        let Point { x, y } = item.position();
+        let (dx, dy) = item.velocity();
+        let (tx, ty) = (x + dx * self.tick, y + dy * self.tick);
+        Rectangle::new(x.min(tx), y.min(ty), (tx - x).abs(), (ty - y).abs())
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.tree.clear();
+    }
+
+    fn visit_swept<'a>(
+        &self,
+        node: &'a Quadtree<Swept<T>>,
+        range: &Rectangle,
+        items: &mut Vec<&'a T>,
+    ) {
+        match node.extent {
+            Some(extent) if extent.inflated(node.reach).intersects_closed(range) => {}
+            _ => return,
+        }
+        for swept in &node.items {
+            if self.swept_bounds(&swept.item).intersects_closed(range) {
+                items.push(&swept.item);
+            }
+        }
+        for child in node.children.iter().flatten() {
+            self.visit_swept(child, range, items);
+        }
+    }
+}
+
+impl Rectangle {
+    fn intersects_closed(&self, other: &Rectangle) -> bool {
+        self.x <= other.x + other.width
+            && self.x + self.width >= other.x
+            && self.y <= other.y + other.height
+            && self.y + self.height >= other.y
+    }
+}
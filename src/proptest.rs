@@ -0,0 +1,131 @@
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use crate::{Options, Point, Position, Quadtree, Rectangle};
+
+const COORDINATE_RANGE: f64 = 1e6;
+const MAX_ITEMS: usize = 256;
+
+impl Arbitrary for Point {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Point>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        let coordinate = -COORDINATE_RANGE..COORDINATE_RANGE;
+        (coordinate.clone(), coordinate)
+            .prop_map(|(x, y)| Point::new(x, y))
+            .boxed()
+    }
+}
+
+impl Arbitrary for Rectangle {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Rectangle>;
+
+    /// Generates rectangles with a positive area, usable as tree boundaries.
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        let coordinate = -COORDINATE_RANGE..COORDINATE_RANGE;
+        let size = 1e-3..COORDINATE_RANGE;
+        (coordinate.clone(), coordinate, size.clone(), size)
+            .prop_map(|(x, y, width, height)| Rectangle::new(x, y, width, height))
+            .boxed()
+    }
+}
+
+impl Arbitrary for Options {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Options>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            1..32usize,
+            0..8u8,
+            any::<bool>(),
+            any::<bool>(),
+            any::<bool>(),
+        )
+            .prop_map(
+                |(max_items, max_depth, track_dirty, defer_collapse, reuse_nodes)| Options {
+                    max_items,
+                    max_depth,
+                    track_dirty,
+                    defer_collapse,
+                    reuse_nodes,
+                    ..Default::default()
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for Quadtree<Point> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Quadtree<Point>>;
+
+    /// Generates a tree holding up to 256 points inside its boundary.
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (any::<Rectangle>(), any::<Options>())
+            .prop_flat_map(|(boundary, options)| {
+                let x = boundary.x..=boundary.x + boundary.width;
+                let y = boundary.y..=boundary.y + boundary.height;
+                vec((x, y), 0..MAX_ITEMS).prop_map(move |points| {
+                    let mut tree = Quadtree::with_options(boundary, options);
+                    for (x, y) in points {
+                        tree.put(Point::new(x, y));
+                    }
+                    tree
+                })
+            })
+            .boxed()
+    }
+}
+
+/// A brute-force index with the same semantics as `Quadtree`, to check
+/// spatial logic against in property tests.
+#[derive(Debug, Clone)]
+pub struct NaiveIndex<T> {
+    boundary: Rectangle,
+    items: Vec<T>,
+}
+
+impl<T: Position> NaiveIndex<T> {
+    pub fn new(boundary: Rectangle) -> Self {
+        Self {
+            boundary,
+            items: Vec::new(),
+        }
+    }
+
+    pub fn put(&mut self, item: T) {
+        if self.boundary.contains_point(&item.position()) {
+            self.items.push(item);
+        }
+    }
+
+    pub fn query(&self, range: Rectangle) -> Vec<&T> {
+        self.items
+            .iter()
+            .filter(|item| range.contains_point(&item.position()))
+            .collect()
+    }
+
+    /// Returns an item closest to `point`; ties may resolve to a different
+    /// item than `Quadtree::nearest`, but never at a different distance.
+    pub fn nearest(&self, point: Point) -> Option<&T> {
+        let distance = |item: &&T| {
+            let at = item.position();
+            (at.x - point.x).hypot(at.y - point.y)
+        };
+        self.items
+            .iter()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
@@ -0,0 +1,131 @@
+use numpy::{PyReadonlyArray1, PyReadonlyArray2};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::{Options, Point, Position, Quadtree, Rectangle};
+
+struct Entry {
+    point: Point,
+    id: u64,
+}
+
+impl Position for Entry {
+    fn position(&self) -> Point {
+        self.point
+    }
+}
+
+/// Python view of a quadtree storing integer ids, exported as
+/// `quadtree.Quadtree`.
+#[pyclass(name = "Quadtree", module = "quadtree")]
+pub struct PyQuadtree {
+    tree: Quadtree<Entry>,
+}
+
+#[pymethods]
+impl PyQuadtree {
+    #[new]
+    #[pyo3(signature = (x, y, width, height, max_items = 20, max_depth = 3))]
+    fn new(
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        max_items: usize,
+        max_depth: u8,
+    ) -> PyResult<Self> {
+        let boundary = Rectangle::new(x, y, width, height);
+        if !boundary.has_area() {
+            return Err(PyValueError::new_err("boundary must have a positive area"));
+        }
+        let options = Options {
+            max_items,
+            max_depth,
+            ..Default::default()
+        };
+        Ok(PyQuadtree {
+            tree: Quadtree::with_options(boundary, options),
+        })
+    }
+
+    /// Stores `id` at (`x`, `y`) and returns whether the point was inside
+    /// the boundary.
+    fn insert(&mut self, x: f64, y: f64, id: u64) -> bool {
+        let len = self.tree.len();
+        self.tree.put(Entry {
+            point: Point::new(x, y),
+            id,
+        });
+        self.tree.len() > len
+    }
+
+    /// Inserts the rows of an `(n, 2)` float array with the matching ids and
+    /// returns how many landed inside the boundary.
+    fn insert_many(
+        &mut self,
+        points: PyReadonlyArray2<'_, f64>,
+        ids: PyReadonlyArray1<'_, u64>,
+    ) -> PyResult<usize> {
+        let (points, ids) = (points.as_array(), ids.as_array());
+        if points.ncols() != 2 || points.nrows() != ids.len() {
+            return Err(PyValueError::new_err(
+                "expected an (n, 2) array of points and n ids",
+            ));
+        }
+        let len = self.tree.len();
+        for (row, &id) in points.outer_iter().zip(ids.iter()) {
+            self.tree.put(Entry {
+                point: Point::new(row[0], row[1]),
+                id,
+            });
+        }
+        Ok(self.tree.len() - len)
+    }
+
+    fn query(&self, x: f64, y: f64, width: f64, height: f64) -> Vec<u64> {
+        self.tree
+            .query(Rectangle::new(x, y, width, height))
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect()
+    }
+
+    fn nearest(&self, x: f64, y: f64) -> Option<u64> {
+        self.tree.nearest(Point::new(x, y)).map(|entry| entry.id)
+    }
+
+    fn __len__(&self) -> usize {
+        self.tree.len()
+    }
+}
+
+#[pymodule]
+fn quadtree(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyQuadtree>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_python_quadtree() {
+        Python::initialize();
+        Python::attach(|py| {
+            let tree =
+                Bound::new(py, PyQuadtree::new(0.0, 0.0, 100.0, 100.0, 1, 3).unwrap()).unwrap();
+            pyo3::py_run!(
+                py,
+                tree,
+                r#"
+assert tree.insert(10.0, 10.0, 1)
+assert tree.insert(60.0, 60.0, 2)
+assert not tree.insert(160.0, 60.0, 3)
+assert len(tree) == 2
+assert tree.query(0.0, 0.0, 50.0, 50.0) == [1]
+assert tree.nearest(70.0, 70.0) == 2
+"#
+            );
+        });
+    }
+}
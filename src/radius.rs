@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use crate::{Point, Position, Quadtree, Radius, Rectangle};
+
+impl<T: Position + Radius, D: Default> Quadtree<T, D> {
+    /// Returns the items whose footprint overlaps the circle at `center`.
+    pub fn query_circle(&self, center: Point, radius: f64) -> Vec<&T> {
+        let bound = self.max_reach();
+        let reach = radius + bound;
+        let range = Rectangle::new(center.x - reach, center.y - reach, 2.0 * reach, 2.0 * reach);
+        let mut items = Vec::new();
+        self.visit(&range, &mut |item| {
+            debug_assert_radius(item, bound);
+            let point = item.position();
+            if (point.x - center.x).hypot(point.y - center.y) <= radius + item.radius() {
+                items.push(item);
+            }
+        });
+        items
+    }
+
+    /// Returns the items whose footprint touches the segment from `a` to `b`.
+    pub fn query_segment(&self, a: Point, b: Point) -> Vec<&T> {
+        let bound = self.max_reach();
+        let range = Rectangle::from_corners(a, b).inflated(bound);
+        let mut items = Vec::new();
+        self.visit(&range, &mut |item| {
+            debug_assert_radius(item, bound);
+            if segment_distance(&item.position(), &a, &b) <= item.radius() {
+                items.push(item);
+            }
+        });
+        items
+    }
+
+    /// Returns every pair of items whose footprints overlap, each pair once
+    /// and in traversal order. Each item is matched against the items found
+    /// around it rather than against every other item.
+    pub fn overlapping_pairs(&self) -> Vec<(&T, &T)> {
+        let items: Vec<&T> = self.iter().collect();
+        let index: HashMap<*const T, usize> = items
+            .iter()
+            .enumerate()
+            .map(|(i, &item)| (item as *const T, i))
+            .collect();
+        let mut pairs = Vec::new();
+        for (i, &item) in items.iter().enumerate() {
+            for other in self.query_circle(item.position(), item.radius()) {
+                let j = index[&(other as *const T)];
+                if i < j {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs.sort_unstable();
+        pairs
+            .into_iter()
+            .map(|(i, j)| (items[i], items[j]))
+            .collect()
+    }
+}
+
+fn debug_assert_radius<T: Radius>(item: &T, bound: f64) {
+    debug_assert!(
+        item.radius() <= bound,
+        "item radius {} exceeds Options::max_radius and Position::reach ({})",
+        item.radius(),
+        bound
+    );
+}
+
+fn segment_distance(point: &Point, a: &Point, b: &Point) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length = dx * dx + dy * dy;
+    let t = if length > 0.0 {
+        (((point.x - a.x) * dx + (point.y - a.y) * dy) / length).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (point.x - a.x - t * dx).hypot(point.y - a.y - t * dy)
+}
@@ -0,0 +1,139 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::nearest::Entry;
+use crate::visibility::ray_distance;
+use crate::{Point, Position, Quadtree, Radius, Rectangle};
+
+impl<T: Position + Radius, D: Default> Quadtree<T, D> {
+    /// Returns the first item whose footprint the ray from `origin` along
+    /// `direction` enters within `max_t`, with its `t` parameter. Nodes are
+    /// visited in the order the ray reaches them, so the search stops as soon
+    /// as no closer hit is possible.
+    pub fn raycast(&self, origin: Point, direction: (f64, f64), max_t: f64) -> Option<(f64, &T)> {
+        let mut nodes = BinaryHeap::new();
+        if let Some(t) = self.entry_t(&origin, direction, max_t) {
+            nodes.push(Reverse(Entry {
+                distance: t,
+                value: self,
+            }));
+        }
+        let mut best: Option<(f64, &T)> = None;
+        while let Some(Reverse(Entry {
+            distance,
+            value: node,
+        })) = nodes.pop()
+        {
+            if best.is_some_and(|(t, _)| distance > t) {
+                break;
+            }
+            for item in &node.items {
+                if let Some(t) = hit(item, &origin, direction, max_t) {
+                    if best.is_none_or(|(b, _)| t < b) {
+                        best = Some((t, item));
+                    }
+                }
+            }
+            for child in node.children.iter().flatten() {
+                if let Some(t) = child.entry_t(&origin, direction, max_t) {
+                    nodes.push(Reverse(Entry {
+                        distance: t,
+                        value: &**child,
+                    }));
+                }
+            }
+        }
+        best
+    }
+
+    /// Returns every item whose footprint the ray enters within `max_t`,
+    /// sorted by `t`. An item containing `origin` is hit at `t = 0`.
+    pub fn raycast_all(&self, origin: Point, direction: (f64, f64), max_t: f64) -> Vec<(f64, &T)> {
+        let mut hits = Vec::new();
+        self.collect_hits(&origin, direction, max_t, &mut hits);
+        hits.sort_by(|a, b| a.0.total_cmp(&b.0));
+        hits
+    }
+
+    fn collect_hits<'a>(
+        &'a self,
+        origin: &Point,
+        direction: (f64, f64),
+        max_t: f64,
+        hits: &mut Vec<(f64, &'a T)>,
+    ) {
+        if self.entry_t(origin, direction, max_t).is_none() {
+            return;
+        }
+        for item in &self.items {
+            if let Some(t) = hit(item, origin, direction, max_t) {
+                hits.push((t, item));
+            }
+        }
+        for child in self.children.iter().flatten() {
+            child.collect_hits(origin, direction, max_t, hits);
+        }
+    }
+
+    /// The `t` at which the ray reaches the footprints of the items below
+    /// this node, if it does so within `max_t`.
+    fn entry_t(&self, origin: &Point, (dx, dy): (f64, f64), max_t: f64) -> Option<f64> {
+        let reach = self.extent?.inflated(self.max_reach());
+        ray_distance(origin, dx, dy, &reach).filter(|&t| t <= max_t)
+    }
+}
+
+fn hit<T: Position + Radius>(
+    item: &T,
+    origin: &Point,
+    (dx, dy): (f64, f64),
+    max_t: f64,
+) -> Option<f64> {
+    let center = item.position();
+    let (ox, oy) = (origin.x - center.x, origin.y - center.y);
+    let radius = item.radius();
+    let c = ox * ox + oy * oy - radius * radius;
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+    let a = dx * dx + dy * dy;
+    let b = ox * dx + oy * dy;
+    let discriminant = b * b - a * c;
+    if a == 0.0 || discriminant < 0.0 {
+        return None;
+    }
+    let t = (-b - discriminant.sqrt()) / a;
+    (t >= 0.0 && t <= max_t).then_some(t)
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Clips the segment from `a` to `b` to the boundary of the tree.
+    pub fn clip_segment(&self, a: Point, b: Point) -> Option<(Point, Point)> {
+        self.bounds().clip_segment(a, b)
+    }
+
+    /// Lists the bounds of the leaf nodes the segment from `a` to `b` passes
+    /// through or touches, in the order the segment reaches them.
+    pub fn cells_crossed_by(&self, a: Point, b: Point) -> Vec<Rectangle> {
+        let mut cells = Vec::new();
+        self.collect_crossed(&a, &b, &mut cells);
+        cells.sort_by(|x, y| x.0.total_cmp(&y.0));
+        cells.into_iter().map(|(_, cell)| cell).collect()
+    }
+
+    fn collect_crossed(&self, a: &Point, b: &Point, cells: &mut Vec<(f64, Rectangle)>) {
+        let bounds = self.bounds();
+        let (t0, _) = match bounds.segment_span(a, b) {
+            Some(span) => span,
+            None => return,
+        };
+        match self.children {
+            Some(ref children) => {
+                for child in children.iter() {
+                    child.collect_crossed(a, b, cells);
+                }
+            }
+            None => cells.push((t0, bounds)),
+        }
+    }
+}
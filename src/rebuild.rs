@@ -0,0 +1,181 @@
+use std::mem;
+use std::sync::{Arc, RwLock};
+use std::thread::{self, JoinHandle};
+
+use crate::{Options, Point, Position, Quadtree, Rectangle};
+
+#[derive(Clone)]
+enum Update<T> {
+    Put(T),
+    Remove(Point),
+}
+
+type Current<T> = Arc<RwLock<Arc<Quadtree<T>>>>;
+
+/// The tree published before the current one, along with the changes it
+/// lacks.
+struct Spare<T> {
+    tree: Arc<Quadtree<T>>,
+    behind: Vec<Update<T>>,
+}
+
+/// A quadtree updated on a worker thread. Changes are buffered until
+/// `rebuild`, which applies them to a second tree and swaps it in. Readers
+/// only ever see a complete tree: `snapshot` returns the published tree,
+/// which stays valid for as long as they hold it.
+///
+/// The two trees take turns: each rebuild brings the tree published before
+/// the current one up to date in place, so a rebuild costs the buffered
+/// changes rather than a copy of the tree. Only when a reader still holds
+/// that older tree is the published one copied instead.
+pub struct RebuildingQuadtree<T> {
+    boundary: Rectangle,
+    options: Options,
+    current: Current<T>,
+    pending: Vec<Update<T>>,
+    spare: Option<Spare<T>>,
+    worker: Option<JoinHandle<Spare<T>>>,
+}
+
+/// A handle for other threads to read the trees published by a
+/// `RebuildingQuadtree`.
+pub struct SnapshotReader<T> {
+    current: Current<T>,
+}
+
+impl<T> Clone for SnapshotReader<T> {
+    fn clone(&self) -> Self {
+        Self {
+            current: Arc::clone(&self.current),
+        }
+    }
+}
+
+impl<T> SnapshotReader<T> {
+    pub fn snapshot(&self) -> Arc<Quadtree<T>> {
+        Arc::clone(&self.current.read().unwrap())
+    }
+}
+
+impl<T: Position + Clone + Send + Sync + 'static> RebuildingQuadtree<T> {
+    pub fn new(boundary: Rectangle) -> Self {
+        Self::with_options(boundary, Default::default())
+    }
+
+    pub fn with_options(boundary: Rectangle, options: Options) -> Self {
+        Self {
+            boundary,
+            options,
+            current: Arc::new(RwLock::new(Arc::new(Quadtree::with_options(
+                boundary, options,
+            )))),
+            pending: Vec::new(),
+            spare: None,
+            worker: None,
+        }
+    }
+
+    /// Buffers an insertion until the next rebuild.
+    pub fn put(&mut self, item: T) {
+        self.pending.push(Update::Put(item));
+    }
+
+    /// Buffers the removal of the first item stored at `point` until the next
+    /// rebuild. Changes are applied in the order they were buffered, so a
+    /// removal only sees the items published or put before it: removing an
+    /// item ahead of its own `put` does nothing.
+    pub fn remove(&mut self, point: Point) {
+        self.pending.push(Update::Remove(point));
+    }
+
+    /// The number of buffered changes not yet handed to a rebuild.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns the most recently published tree.
+    pub fn snapshot(&self) -> Arc<Quadtree<T>> {
+        Arc::clone(&self.current.read().unwrap())
+    }
+
+    pub fn reader(&self) -> SnapshotReader<T> {
+        SnapshotReader {
+            current: Arc::clone(&self.current),
+        }
+    }
+
+    /// Starts rebuilding with the buffered changes on a worker thread.
+    /// Returns `false` without doing anything while a previous rebuild is
+    /// still running.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the previous rebuild panicked.
+    pub fn rebuild(&mut self) -> bool {
+        if let Some(worker) = self.worker.take() {
+            if !worker.is_finished() {
+                self.worker = Some(worker);
+                return false;
+            }
+            self.spare = Some(worker.join().expect("quadtree rebuild panicked"));
+        }
+        let updates = mem::take(&mut self.pending);
+        let spare = self.spare.take();
+        let current = Arc::clone(&self.current);
+        let (boundary, options) = (self.boundary, self.options);
+        self.worker = Some(thread::spawn(move || {
+            let reclaimed = spare.and_then(|spare| {
+                let tree = Arc::try_unwrap(spare.tree).ok()?;
+                Some((tree, spare.behind))
+            });
+            let (mut tree, behind) = match reclaimed {
+                Some(reclaimed) => reclaimed,
+                None => {
+                    let base = Arc::clone(&current.read().unwrap());
+                    let mut tree = Quadtree::with_options(boundary, options);
+                    tree.extend_streaming(base.iter().cloned(), base.len());
+                    (tree, Vec::new())
+                }
+            };
+            for update in behind.into_iter().chain(updates.iter().cloned()) {
+                match update {
+                    Update::Put(item) => tree.put(item),
+                    Update::Remove(point) => {
+                        tree.remove(point);
+                    }
+                }
+            }
+            let published = mem::replace(&mut *current.write().unwrap(), Arc::new(tree));
+            Spare {
+                tree: published,
+                behind: updates,
+            }
+        }));
+        true
+    }
+
+    pub fn is_rebuilding(&self) -> bool {
+        self.worker
+            .as_ref()
+            .is_some_and(|worker| !worker.is_finished())
+    }
+
+    /// Blocks until the running rebuild, if any, has been published.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rebuild panicked.
+    pub fn wait(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            self.spare = Some(worker.join().expect("quadtree rebuild panicked"));
+        }
+    }
+}
+
+impl<T> Drop for RebuildingQuadtree<T> {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{Point, Rectangle};
+
+type Vertex = (u32, u32);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Leaf(bool),
+    Split(Box<[Node; 4]>),
+}
+
+/// A region quadtree over a `width` by `height` grid of occupied or free
+/// cells. Homogeneous blocks are stored as a single leaf, so large uniform
+/// areas of a tilemap or costmap cost one node.
+///
+/// Cell `(col, row)` covers the unit square with its top-left corner at
+/// `(col, row)`. The tree spans the smallest power-of-two square holding the
+/// grid; cells outside the grid are free.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionQuadtree {
+    width: u32,
+    height: u32,
+    size: u32,
+    root: Node,
+}
+
+impl RegionQuadtree {
+    /// Builds the tree from `cells`, given row by row, merging homogeneous
+    /// blocks bottom-up.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cells` does not hold `width * height` values.
+    pub fn from_grid(cells: &[bool], width: u32, height: u32) -> Self {
+        assert_eq!(
+            cells.len(),
+            width as usize * height as usize,
+            "grid must hold width * height cells"
+        );
+        let size = width.max(height).max(1).next_power_of_two();
+        let cell =
+            |col: u32, row: u32| col < width && row < height && cells[(row * width + col) as usize];
+        RegionQuadtree {
+            width,
+            height,
+            size,
+            root: build(0, 0, size, &cell),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Whether cell `(col, row)` is occupied. Cells outside the grid are free.
+    pub fn get(&self, col: u32, row: u32) -> bool {
+        col < self.width && row < self.height && self.leaf_at(col, row).3
+    }
+
+    /// Whether the cell containing `point` is occupied.
+    pub fn is_occupied(&self, point: Point) -> bool {
+        point.x >= 0.0 && point.y >= 0.0 && self.get(point.x as u32, point.y as u32)
+    }
+
+    /// Expands the tree back into a grid, row by row.
+    pub fn to_grid(&self) -> Vec<bool> {
+        let mut cells = Vec::with_capacity(self.width as usize * self.height as usize);
+        for row in 0..self.height {
+            for col in 0..self.width {
+                cells.push(self.get(col, row));
+            }
+        }
+        cells
+    }
+
+    /// Returns the blocks of the tree and whether they are occupied, in
+    /// pre-order. Blocks may extend past the grid.
+    pub fn leaves(&self) -> Vec<(Rectangle, bool)> {
+        let mut leaves = Vec::new();
+        visit_leaves(&self.root, 0, 0, self.size, &mut |x, y, size, value| {
+            leaves.push((
+                Rectangle::new(f64::from(x), f64::from(y), f64::from(size), f64::from(size)),
+                value,
+            ))
+        });
+        leaves
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        let mut count = 0;
+        visit_leaves(&self.root, 0, 0, self.size, &mut |_, _, _, _| count += 1);
+        count
+    }
+
+    /// Traces the outlines of the occupied areas, walking the free sides of
+    /// occupied leaves. Each polygon lists its corners once, clockwise on
+    /// screen (with `y` growing downwards) for outer boundaries and
+    /// counter-clockwise for holes. Areas touching only at a corner get
+    /// separate outlines.
+    pub fn contours(&self) -> Vec<Vec<Point>> {
+        let mut outgoing: BTreeMap<Vertex, Vec<Vertex>> = BTreeMap::new();
+        let mut edge = |from: Vertex, to: Vertex| outgoing.entry(from).or_default().push(to);
+        visit_leaves(&self.root, 0, 0, self.size, &mut |x, y, size, occupied| {
+            if !occupied {
+                return;
+            }
+            for i in 0..size {
+                if y == 0 || !self.get(x + i, y - 1) {
+                    edge((x + i, y), (x + i + 1, y));
+                }
+                if !self.get(x + size, y + i) {
+                    edge((x + size, y + i), (x + size, y + i + 1));
+                }
+                if !self.get(x + i, y + size) {
+                    edge((x + i + 1, y + size), (x + i, y + size));
+                }
+                if x == 0 || !self.get(x - 1, y + i) {
+                    edge((x, y + i + 1), (x, y + i));
+                }
+            }
+        });
+
+        let mut used = HashSet::new();
+        let mut contours = Vec::new();
+        for (&start, targets) in &outgoing {
+            for &target in targets {
+                if used.contains(&(start, target)) {
+                    continue;
+                }
+                let first = (start, target);
+                let mut corners = Vec::new();
+                let mut current = first;
+                loop {
+                    used.insert(current);
+                    let (from, to) = current;
+                    let next = (to, turn(&outgoing[&to], from, to));
+                    if direction(from, to) != direction(next.0, next.1) {
+                        corners.push(Point::new(f64::from(to.0), f64::from(to.1)));
+                    }
+                    if next == first {
+                        break;
+                    }
+                    current = next;
+                }
+                contours.push(corners);
+            }
+        }
+        contours
+    }
+
+    /// Sets every cell of the 4-connected area sharing the value of the cell
+    /// under `seed` to `value`, moving between neighboring leaves rather than
+    /// single cells. Returns the number of cells changed.
+    pub fn flood_fill(&mut self, seed: Point, value: bool) -> usize {
+        if seed.x < 0.0 || seed.y < 0.0 {
+            return 0;
+        }
+        let (col, row) = (seed.x as u32, seed.y as u32);
+        if col >= self.width || row >= self.height {
+            return 0;
+        }
+        let start = self.leaf_at(col, row);
+        let target = start.3;
+        if target == value {
+            return 0;
+        }
+
+        let mut seen = HashSet::new();
+        seen.insert(start);
+        let mut stack = vec![start];
+        let mut blocks = Vec::new();
+        while let Some((x, y, size, _)) = stack.pop() {
+            blocks.push((x, y, size));
+            let (right, bottom) = ((x + size).min(self.width), (y + size).min(self.height));
+            let mut visit = |leaf: (u32, u32, u32, bool)| {
+                if leaf.3 == target && seen.insert(leaf) {
+                    stack.push(leaf);
+                }
+            };
+            let mut i = y;
+            while x > 0 && i < bottom {
+                let leaf = self.leaf_at(x - 1, i);
+                visit(leaf);
+                i = leaf.1 + leaf.2;
+            }
+            let mut i = y;
+            while x + size < self.width && i < bottom {
+                let leaf = self.leaf_at(x + size, i);
+                visit(leaf);
+                i = leaf.1 + leaf.2;
+            }
+            let mut i = x;
+            while y > 0 && i < right {
+                let leaf = self.leaf_at(i, y - 1);
+                visit(leaf);
+                i = leaf.0 + leaf.2;
+            }
+            let mut i = x;
+            while y + size < self.height && i < right {
+                let leaf = self.leaf_at(i, y + size);
+                visit(leaf);
+                i = leaf.0 + leaf.2;
+            }
+        }
+
+        let mut filled = 0;
+        for (x, y, size) in blocks {
+            let area = (
+                x,
+                y,
+                (x + size).min(self.width),
+                (y + size).min(self.height),
+            );
+            filled += ((area.2 - area.0) * (area.3 - area.1)) as usize;
+            paint(&mut self.root, 0, 0, self.size, area, value);
+        }
+        filled
+    }
+
+    /// Returns the cells occupied in either tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the trees cover grids of different sizes; so do
+    /// `intersection` and `subtract`.
+    pub fn union(&self, other: &RegionQuadtree) -> RegionQuadtree {
+        self.combine(other, |a, b| a || b)
+    }
+
+    /// Returns the cells occupied in both trees.
+    pub fn intersection(&self, other: &RegionQuadtree) -> RegionQuadtree {
+        self.combine(other, |a, b| a && b)
+    }
+
+    /// Returns the cells occupied in `self` but not in `other`.
+    pub fn subtract(&self, other: &RegionQuadtree) -> RegionQuadtree {
+        self.combine(other, |a, b| a && !b)
+    }
+
+    fn combine<F: Fn(bool, bool) -> bool>(&self, other: &RegionQuadtree, op: F) -> RegionQuadtree {
+        assert!(
+            self.width == other.width && self.height == other.height,
+            "region quadtrees must cover the same grid: {}x{} and {}x{}",
+            self.width,
+            self.height,
+            other.width,
+            other.height
+        );
+        RegionQuadtree {
+            root: combine(&self.root, &other.root, &op),
+            ..*self
+        }
+    }
+
+    /// Finds the leaf holding cell `(col, row)`, as its corner, size and
+    /// value.
+    fn leaf_at(&self, col: u32, row: u32) -> (u32, u32, u32, bool) {
+        let (mut node, mut x, mut y, mut size) = (&self.root, 0, 0, self.size);
+        loop {
+            match node {
+                Node::Leaf(value) => return (x, y, size, *value),
+                Node::Split(children) => {
+                    size /= 2;
+                    let q = quadrant(col - x >= size, row - y >= size);
+                    let (dx, dy) = offset(q);
+                    x += dx * size;
+                    y += dy * size;
+                    node = &children[q];
+                }
+            }
+        }
+    }
+}
+
+fn build<F: Fn(u32, u32) -> bool>(x: u32, y: u32, size: u32, cell: &F) -> Node {
+    if size == 1 {
+        return Node::Leaf(cell(x, y));
+    }
+    let half = size / 2;
+    let children = [0, 1, 2, 3].map(|q| {
+        let (dx, dy) = offset(q);
+        build(x + dx * half, y + dy * half, half, cell)
+    });
+    merge(children)
+}
+
+/// Collapses four leaves holding the same value into one.
+fn merge(children: [Node; 4]) -> Node {
+    match children {
+        [Node::Leaf(a), Node::Leaf(b), Node::Leaf(c), Node::Leaf(d)]
+            if a == b && b == c && c == d =>
+        {
+            Node::Leaf(a)
+        }
+        children => Node::Split(Box::new(children)),
+    }
+}
+
+/// Walks both trees in step, stopping wherever one side alone decides the
+/// result.
+fn combine<F: Fn(bool, bool) -> bool>(a: &Node, b: &Node, op: &F) -> Node {
+    match (a, b) {
+        (&Node::Leaf(x), &Node::Leaf(y)) => Node::Leaf(op(x, y)),
+        (&Node::Leaf(x), _) if op(x, false) == op(x, true) => Node::Leaf(op(x, false)),
+        (_, &Node::Leaf(y)) if op(false, y) == op(true, y) => Node::Leaf(op(false, y)),
+        _ => merge([0, 1, 2, 3].map(|q| combine(child(a, q), child(b, q), op))),
+    }
+}
+
+/// A leaf stands for each of its own quadrants.
+fn child(node: &Node, quadrant: usize) -> &Node {
+    match node {
+        Node::Leaf(_) => node,
+        Node::Split(children) => &children[quadrant],
+    }
+}
+
+/// Sets the cells in `area`, given as `(left, top, right, bottom)` with the
+/// right and bottom edges excluded, to `value`.
+fn paint(node: &mut Node, x: u32, y: u32, size: u32, area: (u32, u32, u32, u32), value: bool) {
+    let (left, top, right, bottom) = area;
+    if x >= right || y >= bottom || x + size <= left || y + size <= top {
+        return;
+    }
+    if x >= left && y >= top && x + size <= right && y + size <= bottom {
+        *node = Node::Leaf(value);
+        return;
+    }
+    if let Node::Leaf(current) = *node {
+        if current == value {
+            return;
+        }
+        *node = Node::Split(Box::new([0; 4].map(|_| Node::Leaf(current))));
+    }
+    if let Node::Split(children) = node {
+        let half = size / 2;
+        for (q, child) in children.iter_mut().enumerate() {
+            let (dx, dy) = offset(q);
+            paint(child, x + dx * half, y + dy * half, half, area, value);
+        }
+    }
+    if let Node::Split(children) = std::mem::replace(node, Node::Leaf(false)) {
+        *node = merge(*children);
+    }
+}
+
+fn visit_leaves<F: FnMut(u32, u32, u32, bool)>(node: &Node, x: u32, y: u32, size: u32, f: &mut F) {
+    match node {
+        Node::Leaf(value) => f(x, y, size, *value),
+        Node::Split(children) => {
+            let half = size / 2;
+            for (q, child) in children.iter().enumerate() {
+                let (dx, dy) = offset(q);
+                visit_leaves(child, x + dx * half, y + dy * half, half, f);
+            }
+        }
+    }
+}
+
+/// Picks the edge leaving `to` after arriving from `from`. Where two
+/// occupied cells touch at a corner, the walk turns right to stay around the
+/// cell it came along.
+fn turn(targets: &[Vertex], from: Vertex, to: Vertex) -> Vertex {
+    if let [target] = targets {
+        return *target;
+    }
+    let (dx, dy) = direction(from, to);
+    targets
+        .iter()
+        .copied()
+        .find(|&target| direction(to, target) == (-dy, dx))
+        .unwrap_or(targets[0])
+}
+
+fn direction(from: Vertex, to: Vertex) -> (i64, i64) {
+    (
+        i64::from(to.0) - i64::from(from.0),
+        i64::from(to.1) - i64::from(from.1),
+    )
+}
+
+/// Children are ordered north-west, north-east, south-east, south-west.
+fn quadrant(east: bool, south: bool) -> usize {
+    match (east, south) {
+        (false, false) => 0,
+        (true, false) => 1,
+        (true, true) => 2,
+        (false, true) => 3,
+    }
+}
+
+fn offset(quadrant: usize) -> (u32, u32) {
+    match quadrant {
+        0 => (0, 0),
+        1 => (1, 0),
+        2 => (1, 1),
+        _ => (0, 1),
+    }
+}
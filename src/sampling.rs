@@ -0,0 +1,151 @@
+use std::f64::consts::PI;
+
+use rand::seq::index;
+use rand::Rng;
+
+use crate::{Options, Point, Position, Quadtree, Rectangle};
+
+/// Candidates tried around an active point before it is retired.
+const POISSON_ATTEMPTS: usize = 30;
+
+enum Block<'a, T, D> {
+    Node(&'a Quadtree<T, D>),
+    Item(&'a T),
+}
+
+impl<'a, T, D> Block<'a, T, D> {
+    fn len(&self) -> usize {
+        match self {
+            Block::Node(node) => node.count,
+            Block::Item(_) => 1,
+        }
+    }
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Picks up to `n` distinct items inside `range` uniformly at random.
+    ///
+    /// Nodes lying entirely inside `range` are sampled through their item
+    /// counts, so only the partially covered leaves are scanned.
+    pub fn sample_in_region(&self, range: Rectangle, n: usize, rng: &mut impl Rng) -> Vec<&T> {
+        let mut blocks = Vec::new();
+        self.collect_blocks(&range, &mut blocks);
+
+        let total = blocks.iter().map(Block::len).sum();
+        let mut indices = index::sample(rng, total, n.min(total)).into_vec();
+        indices.sort_unstable();
+
+        let mut items = Vec::with_capacity(indices.len());
+        let mut blocks = blocks.iter();
+        let mut block = blocks.next();
+        let mut offset = 0;
+        for index in indices {
+            while let Some(b) = block {
+                if index < offset + b.len() {
+                    break;
+                }
+                offset += b.len();
+                block = blocks.next();
+            }
+            match block {
+                Some(Block::Node(node)) => items.extend(node.nth(index - offset)),
+                Some(Block::Item(item)) => items.push(*item),
+                None => break,
+            }
+        }
+        items
+    }
+
+    fn collect_blocks<'a>(&'a self, range: &Rectangle, blocks: &mut Vec<Block<'a, T, D>>) {
+        let bounds = self.bounds();
+        if self.count == 0 || !self.intersects(range, &bounds) {
+            return;
+        }
+        if bounds.x >= range.x
+            && bounds.x + bounds.width <= range.x + range.width
+            && bounds.y >= range.y
+            && bounds.y + bounds.height <= range.y + range.height
+        {
+            blocks.push(Block::Node(self));
+            return;
+        }
+        for item in &self.items {
+            if self._contains(&item.position(), range) {
+                blocks.push(Block::Item(item));
+            }
+        }
+        if let Some(ref children) = self.children {
+            for child in children {
+                child.collect_blocks(range, blocks);
+            }
+        }
+    }
+
+    fn nth(&self, mut index: usize) -> Option<&T> {
+        if index < self.items.len() {
+            return self.items.get(index);
+        }
+        index -= self.items.len();
+        for child in self.children.iter().flatten() {
+            if index < child.count {
+                return child.nth(index);
+            }
+            index -= child.count;
+        }
+        None
+    }
+}
+
+/// Scatters points over `boundary` with Bridson's algorithm so that no two
+/// lie closer than `min_dist`, using a quadtree of the points placed so far
+/// to reject candidates.
+///
+/// # Panics
+///
+/// Panics if `boundary` has no area or `min_dist` is not positive.
+pub fn poisson_disk(boundary: Rectangle, min_dist: f64, rng: &mut impl Rng) -> Vec<Point> {
+    assert!(
+        min_dist > 0.0,
+        "poisson disk distance must be positive: {}",
+        min_dist
+    );
+    let expected = (boundary.width * boundary.height / (min_dist * min_dist)) as usize;
+    let mut tree = Quadtree::with_options(boundary, Options::auto(expected, boundary));
+
+    let first = Point::new(
+        boundary.x + rng.gen::<f64>() * boundary.width,
+        boundary.y + rng.gen::<f64>() * boundary.height,
+    );
+    let mut points = vec![first];
+    let mut active = vec![first];
+    tree.put(first);
+
+    while !active.is_empty() {
+        let index = rng.gen_range(0..active.len());
+        let origin = active[index];
+        let found = (0..POISSON_ATTEMPTS)
+            .map(|_| {
+                let angle = rng.gen::<f64>() * 2.0 * PI;
+                let distance = min_dist * (1.0 + rng.gen::<f64>());
+                Point::new(
+                    origin.x + distance * angle.cos(),
+                    origin.y + distance * angle.sin(),
+                )
+            })
+            .find(|candidate| {
+                tree._contains(candidate, &boundary)
+                    && tree.query_radius(*candidate, min_dist).is_empty()
+            });
+        match found {
+            Some(point) => {
+                tree.put(point);
+                points.push(point);
+                active.push(point);
+            }
+            None => {
+                active.swap_remove(index);
+            }
+        }
+    }
+    points
+}
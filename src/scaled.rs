@@ -0,0 +1,140 @@
+use crate::{
+    IntOptions, IntPoint, IntPosition, IntQuadtree, IntRectangle, Point, Position, Rectangle,
+};
+
+/// An item stored with its position rounded to the lattice.
+struct Scaled<T> {
+    point: IntPoint,
+    item: T,
+}
+
+impl<T> IntPosition for Scaled<T> {
+    fn position(&self) -> IntPoint {
+        self.point
+    }
+}
+
+/// A quadtree that takes and returns world coordinates but routes items by
+/// integer lattice coordinates, counted in steps of `resolution` from the
+/// world origin. Cells split at exact integer midpoints, so subdividing
+/// stays exact at any depth however far from zero the world lies, down to
+/// cells one step wide. Items keep their own coordinates, which range
+/// queries check.
+pub struct ScaledQuadtree<T> {
+    tree: IntQuadtree<Scaled<T>>,
+    world: Rectangle,
+    resolution: f64,
+}
+
+impl<T: Position> ScaledQuadtree<T> {
+    pub fn new(world: Rectangle, resolution: f64) -> Self {
+        Self::with_options(world, resolution, Default::default())
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `world` does not have a finite, positive width and height,
+    /// if `resolution` is not finite and positive, or if the lattice would
+    /// need more than `i64::MAX` steps along an axis.
+    pub fn with_options(world: Rectangle, resolution: f64, options: IntOptions) -> Self {
+        assert!(
+            world.has_area(),
+            "quadtree boundary must have a finite, positive area: {}",
+            world
+        );
+        assert!(
+            resolution.is_finite() && resolution > 0.0,
+            "resolution must be finite and positive: {}",
+            resolution
+        );
+        let steps = |length: f64| {
+            let steps = (length / resolution).ceil() + 1.0;
+            assert!(
+                steps < i64::MAX as f64,
+                "world is too large for a resolution of {}: {}",
+                resolution,
+                world
+            );
+            steps as i64
+        };
+        let lattice = IntRectangle::new(0, 0, steps(world.width), steps(world.height));
+        Self {
+            tree: IntQuadtree::with_options(lattice, options),
+            world,
+            resolution,
+        }
+    }
+
+    /// The lattice point nearest to the world position `point`.
+    pub fn to_lattice(&self, point: Point) -> IntPoint {
+        IntPoint::new(
+            ((point.x - self.world.x) / self.resolution).round() as i64,
+            ((point.y - self.world.y) / self.resolution).round() as i64,
+        )
+    }
+
+    pub fn to_world(&self, point: IntPoint) -> Point {
+        Point::new(
+            self.world.x + point.x() as f64 * self.resolution,
+            self.world.y + point.y() as f64 * self.resolution,
+        )
+    }
+
+    /// Stores `item`. Items outside the world bounds are skipped, as with
+    /// `Quadtree::put`.
+    pub fn put(&mut self, item: T) {
+        let position = item.position();
+        if !self.world.contains_point(&position) {
+            return;
+        }
+        let point = self.to_lattice(position);
+        self.tree.put(Scaled { point, item });
+    }
+
+    /// Returns the items inside the world-space rectangle `range`, checked
+    /// against their world positions.
+    pub fn query(&self, range: Rectangle) -> Vec<&T> {
+        let clipped = match range.intersection(&self.world) {
+            Some(clipped) => clipped,
+            None => return Vec::new(),
+        };
+        let low = self.to_lattice(Point::new(clipped.x, clipped.y));
+        let high = self.to_lattice(Point::new(
+            clipped.x + clipped.width,
+            clipped.y + clipped.height,
+        ));
+        let lattice = IntRectangle::new(
+            low.x(),
+            low.y(),
+            high.x() - low.x() + 1,
+            high.y() - low.y() + 1,
+        );
+        self.tree
+            .query(lattice)
+            .into_iter()
+            .map(|scaled| &scaled.item)
+            .filter(|item| range.contains_point(&item.position()))
+            .collect()
+    }
+
+    pub fn world_bounds(&self) -> Rectangle {
+        self.world
+    }
+
+    /// The world length of one lattice step.
+    pub fn resolution(&self) -> f64 {
+        self.resolution
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.tree.clear();
+    }
+}
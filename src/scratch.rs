@@ -0,0 +1,48 @@
+use crate::{Position, Quadtree, Rectangle};
+
+/// A result buffer passed to `Quadtree::query_scratch`, so that repeated
+/// queries stop allocating once it has grown to the largest result. It
+/// keeps references into the tree it was used with, which stays borrowed
+/// for as long as the scratch is alive.
+pub struct QueryScratch<'a, T> {
+    items: Vec<&'a T>,
+}
+
+impl<'a, T> Default for QueryScratch<'a, T> {
+    fn default() -> Self {
+        Self { items: Vec::new() }
+    }
+}
+
+impl<'a, T> QueryScratch<'a, T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            items: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// The number of results the buffer holds without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.items.capacity()
+    }
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Same as `query`, but collects the results into the buffer of
+    /// `scratch`, replacing those of the previous query, instead of a new
+    /// vector.
+    pub fn query_scratch<'s, 'a>(
+        &'a self,
+        range: Rectangle,
+        scratch: &'s mut QueryScratch<'a, T>,
+    ) -> &'s [&'a T] {
+        let items = &mut scratch.items;
+        items.clear();
+        self.visit(&range, &mut |item| items.push(item));
+        items
+    }
+}
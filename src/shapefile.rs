@@ -0,0 +1,37 @@
+use std::path::Path;
+
+use shapefile::dbase::Record;
+use shapefile::{Error, Reader, Shape};
+
+use crate::{Point, Position, Quadtree, Rectangle};
+
+impl Quadtree<Point> {
+    /// Indexes the point geometries of the shapefile at `path`.
+    pub fn from_shapefile<P: AsRef<Path>>(path: P, boundary: Rectangle) -> Result<Self, Error> {
+        Quadtree::from_shapefile_with(path, boundary, |point, _| point)
+    }
+}
+
+impl<T: Position> Quadtree<T> {
+    /// Like `from_shapefile`, but builds each item from its point and the
+    /// matching .dbf attribute record. Shapes other than points are skipped.
+    pub fn from_shapefile_with<P: AsRef<Path>, F: FnMut(Point, &Record) -> T>(
+        path: P,
+        boundary: Rectangle,
+        mut item: F,
+    ) -> Result<Self, Error> {
+        let mut reader = Reader::from_path(path)?;
+        let mut tree = Quadtree::new(boundary);
+        for shape_record in reader.iter_shapes_and_records() {
+            let (shape, record) = shape_record?;
+            let point = match shape {
+                Shape::Point(point) => Point::new(point.x, point.y),
+                Shape::PointM(point) => Point::new(point.x, point.y),
+                Shape::PointZ(point) => Point::new(point.x, point.y),
+                _ => continue,
+            };
+            tree.put(item(point, &record));
+        }
+        Ok(tree)
+    }
+}
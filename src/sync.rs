@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+
+use crate::persist::{read_u64, write_u64, Persist};
+use crate::{Options, Point, Position, Quadtree, Rectangle};
+
+/// A position in the change history of a `VersionedQuadtree`. The default
+/// version precedes every change.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version(pub u64);
+
+/// The changes made to a `VersionedQuadtree` after some version: for every
+/// position that changed, the complete set of items now stored there.
+#[derive(Debug, Clone)]
+pub struct Delta<T> {
+    version: Version,
+    changes: Vec<(Point, Vec<T>)>,
+}
+
+impl<T> Delta<T> {
+    /// The version the replica is at once the delta is applied.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn changes(&self) -> &[(Point, Vec<T>)] {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl<T: Persist> Delta<T> {
+    pub fn write_to(&self, writer: &mut dyn Write) -> io::Result<()> {
+        write_u64(writer, self.version.0)?;
+        write_u64(writer, self.changes.len() as u64)?;
+        for (point, items) in &self.changes {
+            point.write_to(writer)?;
+            write_u64(writer, items.len() as u64)?;
+            for item in items {
+                item.write_to(writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut dyn Read) -> io::Result<Self> {
+        let version = Version(read_u64(reader)?);
+        let mut changes = Vec::new();
+        for _ in 0..read_u64(reader)? {
+            let point = Point::read_from(reader)?;
+            let mut items = Vec::new();
+            for _ in 0..read_u64(reader)? {
+                items.push(T::read_from(reader)?);
+            }
+            changes.push((point, items));
+        }
+        Ok(Delta { version, changes })
+    }
+}
+
+/// A quadtree that numbers its changes so that replicas can be brought up to
+/// date with `export_delta` and `Quadtree::apply_delta`, sending only the
+/// positions that changed.
+///
+/// Only the latest version of each position is remembered, so the history
+/// grows with the number of distinct positions ever changed.
+pub struct VersionedQuadtree<T> {
+    tree: Quadtree<T>,
+    version: Version,
+    changed: BTreeMap<Point, Version>,
+}
+
+impl<T: Position + Clone> VersionedQuadtree<T> {
+    pub fn new(boundary: Rectangle) -> Self {
+        Self::with_options(boundary, Default::default())
+    }
+
+    pub fn with_options(boundary: Rectangle, options: Options) -> Self {
+        Self {
+            tree: Quadtree::with_options(boundary, options),
+            version: Version::default(),
+            changed: BTreeMap::new(),
+        }
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Stores `item`. Items evicted to stay within `Options::max_total_items`
+    /// are recorded as changes too.
+    pub fn put(&mut self, item: T) {
+        let point = item.position();
+        if !self.tree.contains(&item) {
+            return;
+        }
+        let evicted = self.tree.put_evicting(item);
+        self.touch(point);
+        for item in evicted {
+            self.touch(item.position());
+        }
+    }
+
+    /// Removes the first item stored at `point`, within `Options::epsilon`.
+    pub fn remove(&mut self, point: Point) -> Option<T> {
+        let removed = self.tree.remove(point)?;
+        self.touch(removed.position());
+        Some(removed)
+    }
+
+    pub fn clear(&mut self) {
+        let points: Vec<Point> = self.tree.positions().collect();
+        self.tree.clear();
+        for point in points {
+            self.touch(point);
+        }
+    }
+
+    pub fn tree(&self) -> &Quadtree<T> {
+        &self.tree
+    }
+
+    /// Returns the changes made after `since`.
+    pub fn export_delta(&self, since: Version) -> Delta<T> {
+        let changes = self
+            .changed
+            .iter()
+            .filter(|&(_, &version)| version > since)
+            .map(|(&point, _)| {
+                let items = self
+                    .tree
+                    .entries_at(point)
+                    .into_iter()
+                    .filter(|item| item.position() == point)
+                    .cloned()
+                    .collect();
+                (point, items)
+            })
+            .collect();
+        Delta {
+            version: self.version,
+            changes,
+        }
+    }
+
+    fn touch(&mut self, point: Point) {
+        self.version.0 += 1;
+        self.changed.insert(point, self.version);
+    }
+}
+
+impl<T: Position + Clone, D: Default> Quadtree<T, D> {
+    /// Brings a replica up to date: the items stored at each changed position
+    /// are replaced by the ones in `delta`.
+    pub fn apply_delta(&mut self, delta: &Delta<T>) {
+        for (point, items) in &delta.changes {
+            while self
+                .remove_where(point, &mut |item| item.position() == *point)
+                .is_some()
+            {}
+            for item in items {
+                self.put(item.clone());
+            }
+        }
+    }
+}
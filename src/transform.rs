@@ -0,0 +1,177 @@
+use std::ops::Deref;
+
+use crate::{Point, Position, Quadtree, Rectangle};
+
+/// A 2D affine transform mapping `(x, y)` to
+/// `(a * x + b * y + tx, c * x + d * y + ty)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    tx: f64,
+    ty: f64,
+}
+
+impl Default for Affine {
+    fn default() -> Affine {
+        Affine::identity()
+    }
+}
+
+impl Affine {
+    pub fn identity() -> Affine {
+        Affine {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            tx: 0.0,
+            ty: 0.0,
+        }
+    }
+
+    pub fn translation(dx: f64, dy: f64) -> Affine {
+        Affine {
+            tx: dx,
+            ty: dy,
+            ..Affine::identity()
+        }
+    }
+
+    /// Rotates by `angle` radians around the origin.
+    pub fn rotation(angle: f64) -> Affine {
+        let (sin, cos) = angle.sin_cos();
+        Affine {
+            a: cos,
+            b: -sin,
+            c: sin,
+            d: cos,
+            ..Affine::identity()
+        }
+    }
+
+    pub fn scale(sx: f64, sy: f64) -> Affine {
+        Affine {
+            a: sx,
+            d: sy,
+            ..Affine::identity()
+        }
+    }
+
+    /// Returns the transform applying `self` first and `next` second.
+    pub fn then(&self, next: &Affine) -> Affine {
+        Affine {
+            a: next.a * self.a + next.b * self.c,
+            b: next.a * self.b + next.b * self.d,
+            c: next.c * self.a + next.d * self.c,
+            d: next.c * self.b + next.d * self.d,
+            tx: next.a * self.tx + next.b * self.ty + next.tx,
+            ty: next.c * self.tx + next.d * self.ty + next.ty,
+        }
+    }
+
+    /// Returns the inverse transform, or `None` if this one is singular.
+    pub fn inverse(&self) -> Option<Affine> {
+        let det = self.a * self.d - self.b * self.c;
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let (a, b, c, d) = (self.d / det, -self.b / det, -self.c / det, self.a / det);
+        Some(Affine {
+            a,
+            b,
+            c,
+            d,
+            tx: -(a * self.tx + b * self.ty),
+            ty: -(c * self.tx + d * self.ty),
+        })
+    }
+
+    pub fn apply(&self, point: Point) -> Point {
+        Point::new(
+            self.a * point.x + self.b * point.y + self.tx,
+            self.c * point.x + self.d * point.y + self.ty,
+        )
+    }
+
+    /// Returns the smallest axis-aligned rectangle holding the image of
+    /// `rect`.
+    pub fn apply_rect(&self, rect: &Rectangle) -> Rectangle {
+        let corners = [
+            self.apply(Point::new(rect.x, rect.y)),
+            self.apply(Point::new(rect.x + rect.width, rect.y)),
+            self.apply(Point::new(rect.x + rect.width, rect.y + rect.height)),
+            self.apply(Point::new(rect.x, rect.y + rect.height)),
+        ];
+        corners[1..].iter().fold(
+            Rectangle::new(corners[0].x, corners[0].y, 0.0, 0.0),
+            |bounds, corner| bounds.union(&Rectangle::new(corner.x, corner.y, 0.0, 0.0)),
+        )
+    }
+}
+
+/// A view of a tree built in local coordinates that answers queries given in
+/// world coordinates, through the `to_world` transform. Moving the parent
+/// object only means replacing the transform; the tree is not rebuilt.
+pub struct TransformedQuadtree<'a, T, D = ()> {
+    tree: &'a Quadtree<T, D>,
+    to_world: Affine,
+    to_local: Affine,
+}
+
+impl<T: Position, D: Default> Quadtree<T, D> {
+    /// Views the tree through `to_world`, mapping its local coordinates to
+    /// world coordinates.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `to_world` is not invertible.
+    pub fn transformed(&self, to_world: Affine) -> TransformedQuadtree<'_, T, D> {
+        TransformedQuadtree {
+            tree: self,
+            to_world,
+            to_local: to_world.inverse().expect("transform must be invertible"),
+        }
+    }
+
+    /// Returns the items whose position mapped through `to_world` lies
+    /// inside the world-space rectangle `range`.
+    pub fn query_transformed(&self, range: Rectangle, to_world: Affine) -> Vec<&T> {
+        self.transformed(to_world).query(range)
+    }
+}
+
+impl<'a, T: Position, D: Default> TransformedQuadtree<'a, T, D> {
+    pub fn to_world(&self, point: Point) -> Point {
+        self.to_world.apply(point)
+    }
+
+    pub fn to_local(&self, point: Point) -> Point {
+        self.to_local.apply(point)
+    }
+
+    /// Returns the items inside the world-space rectangle `range`. The tree
+    /// is searched with the local bounding box of `range`, and the hits are
+    /// then checked in world space.
+    pub fn query(&self, range: Rectangle) -> Vec<&'a T> {
+        let local = self.to_local.apply_rect(&range);
+        let mut items = Vec::new();
+        self.tree.visit(&local, &mut |item| {
+            let point = self.to_world.apply(item.position());
+            if range.contains_point(&point) {
+                items.push(item);
+            }
+        });
+        items
+    }
+}
+
+impl<'a, T, D> Deref for TransformedQuadtree<'a, T, D> {
+    type Target = Quadtree<T, D>;
+
+    fn deref(&self) -> &Quadtree<T, D> {
+        self.tree
+    }
+}
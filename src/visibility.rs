@@ -0,0 +1,124 @@
+use std::f64::consts::PI;
+
+use crate::{Point, Position, Quadtree, Rectangle};
+
+const ARC_SEGMENTS: usize = 64;
+const CORNER_OFFSET: f64 = 1e-6;
+
+/// The shape with which an item blocks sight in `visibility_polygon`.
+#[derive(Debug, Clone, Copy)]
+pub enum Occlusion {
+    /// A wall from one point to another.
+    Segment(Point, Point),
+    /// A solid box, such as a blocked grid cell. A box holding the origin
+    /// of the polygon does not block it.
+    Box(Rectangle),
+}
+
+/// Items that block sight. Shapes extending farther from the item's
+/// position than `Options::max_radius` must also be reported as
+/// `Position::reach`, or `visibility_polygon` may miss them.
+pub trait Occluder {
+    fn occlusion(&self) -> Occlusion;
+}
+
+impl<T: Position + Occluder, D: Default> Quadtree<T, D> {
+    /// Computes the area visible from `origin` up to `radius`, blocked by the
+    /// `Occluder` shapes of the items around it.
+    ///
+    /// Vertices are returned in counter-clockwise order starting at angle zero.
+    pub fn visibility_polygon(&self, origin: Point, radius: f64) -> Vec<Point> {
+        let reach = radius + self.max_reach();
+        let range = Rectangle::new(origin.x - reach, origin.y - reach, 2.0 * reach, 2.0 * reach);
+        let mut walls = Vec::new();
+        self.visit(&range, &mut |item| match item.occlusion() {
+            Occlusion::Segment(a, b) => walls.push((a, b)),
+            Occlusion::Box(rect) if !rect.contains_point(&origin) => {
+                let corners = corners(&rect);
+                for i in 0..4 {
+                    walls.push((corners[i], corners[(i + 1) % 4]));
+                }
+            }
+            Occlusion::Box(_) => {}
+        });
+
+        let mut angles: Vec<f64> = (0..ARC_SEGMENTS)
+            .map(|i| 2.0 * PI * i as f64 / ARC_SEGMENTS as f64)
+            .collect();
+        for &(a, b) in &walls {
+            for end in &[a, b] {
+                let angle = (end.y - origin.y).atan2(end.x - origin.x);
+                for &a in &[angle - CORNER_OFFSET, angle, angle + CORNER_OFFSET] {
+                    angles.push(a.rem_euclid(2.0 * PI));
+                }
+            }
+        }
+        angles.sort_by(|a, b| a.total_cmp(b));
+        angles.dedup();
+
+        let mut polygon: Vec<Point> = Vec::with_capacity(angles.len());
+        for angle in angles {
+            let (dx, dy) = (angle.cos(), angle.sin());
+            let t = walls
+                .iter()
+                .filter_map(|(a, b)| segment_distance(&origin, dx, dy, a, b))
+                .fold(radius, f64::min);
+            let point = Point::new(origin.x + dx * t, origin.y + dy * t);
+            if polygon.last() != Some(&point) {
+                polygon.push(point);
+            }
+        }
+        polygon
+    }
+}
+
+fn corners(rect: &Rectangle) -> [Point; 4] {
+    let (right, bottom) = (rect.x + rect.width, rect.y + rect.height);
+    [
+        Point::new(rect.x, rect.y),
+        Point::new(right, rect.y),
+        Point::new(right, bottom),
+        Point::new(rect.x, bottom),
+    ]
+}
+
+/// Distance along the ray from `origin` in direction `(dx, dy)` to the
+/// segment from `a` to `b`, or `None` if the ray misses it or runs along it.
+fn segment_distance(origin: &Point, dx: f64, dy: f64, a: &Point, b: &Point) -> Option<f64> {
+    let (ex, ey) = (b.x - a.x, b.y - a.y);
+    let denom = dx * ey - dy * ex;
+    if denom.abs() < f64::EPSILON {
+        return None;
+    }
+    let (ox, oy) = (a.x - origin.x, a.y - origin.y);
+    let t = (ox * ey - oy * ex) / denom;
+    let s = (ox * dy - oy * dx) / denom;
+    if t >= 0.0 && (0.0..=1.0).contains(&s) {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+pub(crate) fn ray_distance(origin: &Point, dx: f64, dy: f64, rect: &Rectangle) -> Option<f64> {
+    let mut t_min = 0.0_f64;
+    let mut t_max = f64::INFINITY;
+    for &(o, d, lo, hi) in &[
+        (origin.x, dx, rect.x, rect.x + rect.width),
+        (origin.y, dy, rect.y, rect.y + rect.height),
+    ] {
+        if d.abs() < f64::EPSILON {
+            if o < lo || o > hi {
+                return None;
+            }
+            continue;
+        }
+        let (t1, t2) = ((lo - o) / d, (hi - o) / d);
+        t_min = t_min.max(t1.min(t2));
+        t_max = t_max.min(t1.max(t2));
+        if t_min > t_max {
+            return None;
+        }
+    }
+    Some(t_min)
+}
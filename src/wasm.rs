@@ -0,0 +1,69 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{Point, Position, Quadtree, Rectangle};
+
+struct Entry {
+    point: Point,
+    id: u32,
+}
+
+impl Position for Entry {
+    fn position(&self) -> Point {
+        self.point
+    }
+}
+
+/// A quadtree of numeric ids for JavaScript callers, who keep the payloads
+/// on their side.
+#[wasm_bindgen]
+pub struct WasmQuadtree {
+    tree: Quadtree<Entry>,
+}
+
+#[wasm_bindgen]
+impl WasmQuadtree {
+    /// Fails if the boundary does not have a finite, positive width and
+    /// height.
+    #[wasm_bindgen(constructor)]
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Result<WasmQuadtree, JsValue> {
+        let boundary = Rectangle::new(x, y, width, height);
+        if !boundary.has_area() {
+            return Err(JsValue::from_str("boundary must have a positive area"));
+        }
+        Ok(WasmQuadtree {
+            tree: Quadtree::new(boundary),
+        })
+    }
+
+    /// Stores `id` at (`x`, `y`) and reports whether the point was inside
+    /// the tree boundary.
+    pub fn insert(&mut self, x: f64, y: f64, id: u32) -> bool {
+        let len = self.tree.len();
+        self.tree.put(Entry {
+            point: Point::new(x, y),
+            id,
+        });
+        self.tree.len() > len
+    }
+
+    pub fn query(&self, x: f64, y: f64, width: f64, height: f64) -> Vec<u32> {
+        self.tree
+            .query(Rectangle::new(x, y, width, height))
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    #[wasm_bindgen(js_name = isEmpty)]
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.tree.clear();
+    }
+}
@@ -0,0 +1,52 @@
+mod compressed_quadtree_tests {
+    use quadtree::*;
+
+    #[test]
+    fn test_clustered_points_skip_empty_levels() {
+        let options = Options {
+            max_items: 2,
+            max_depth: 20,
+            ..Default::default()
+        };
+        let boundary = Rectangle::new(0.0, 0.0, 1024.0, 1024.0);
+        let mut compressed = CompressedQuadtree::with_options(boundary, options);
+        let mut plain = Quadtree::with_options(boundary, options);
+        let mut points = Vec::new();
+        for i in 0..8 {
+            points.push(Point::new(1.0 + f64::from(i) * 0.01, 1.0));
+            points.push(Point::new(1000.0, 1000.0 - f64::from(i) * 0.01));
+        }
+        for &point in &points {
+            compressed.put(point);
+            plain.put(point);
+        }
+        compressed.put(Point::new(2000.0, 1.0));
+
+        assert_eq!(compressed.len(), 16);
+        assert!(compressed.node_count() * 3 < plain.node_refs().len());
+
+        let ranges = [
+            boundary,
+            Rectangle::new(0.0, 0.0, 1.035, 2.0),
+            Rectangle::new(999.0, 999.95, 2.0, 1.0),
+            Rectangle::new(400.0, 400.0, 100.0, 100.0),
+        ];
+        for range in &ranges {
+            let mut found = compressed.query(*range);
+            let mut expected = plain.query(*range);
+            found.sort();
+            expected.sort();
+            assert_eq!(found, expected);
+        }
+
+        compressed.put(Point::new(512.0, 300.0));
+        assert_eq!(
+            compressed
+                .query(Rectangle::new(500.0, 250.0, 50.0, 100.0))
+                .len(),
+            1
+        );
+        compressed.clear();
+        assert!(compressed.is_empty());
+    }
+}
@@ -0,0 +1,27 @@
+mod double_buffered_quadtree_tests {
+    use quadtree::*;
+
+    #[test]
+    fn test_swap() {
+        let entity = ();
+        let everything = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+
+        let mut qt = DoubleBufferedQuadtree::new(everything);
+        qt.write().put(Item::new(Point::new(10.0, 10.0), &entity));
+        assert!(qt.read().is_empty());
+
+        qt.swap();
+        assert_eq!(qt.read().len(), 1);
+        assert!(qt.write().is_empty());
+
+        let (read, write) = qt.split();
+        for item in read.query(everything) {
+            let p = item.position();
+            write.put(Item::new(Point::new(p.x() + 5.0, p.y()), &entity));
+        }
+        qt.swap();
+        let items = qt.read().query(everything);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].position(), Point::new(15.0, 10.0));
+    }
+}
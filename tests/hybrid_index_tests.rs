@@ -0,0 +1,93 @@
+mod hybrid_index_tests {
+    use quadtree::*;
+
+    #[test]
+    fn test_query_spans_cells() {
+        let boundary = Rectangle::new(-1000.0, -1000.0, 2000.0, 2000.0);
+        let mut index = HybridIndex::new(boundary, 100.0);
+        let mut plain = Quadtree::new(boundary);
+        for i in 0..50 {
+            for j in 0..50 {
+                let point = Point::new(f64::from(i) * 37.0 - 900.0, f64::from(j) * 29.0 - 700.0);
+                index.put(point);
+                plain.put(point);
+            }
+        }
+        index.put(Point::new(5000.0, 0.0));
+        assert_eq!(index.len(), 2500);
+
+        let sorted = |items: Vec<&Point>| {
+            let mut items: Vec<Point> = items.into_iter().copied().collect();
+            items.sort();
+            items
+        };
+        for range in [
+            Rectangle::new(-150.0, -150.0, 300.0, 300.0),
+            Rectangle::new(99.0, -1000.0, 2.0, 2000.0),
+            Rectangle::new(900.0, 900.0, 500.0, 500.0),
+            boundary,
+        ] {
+            assert_eq!(sorted(index.query(range)), sorted(plain.query(range)));
+        }
+    }
+
+    #[test]
+    fn test_trees_follow_occupied_cells() {
+        let mut index = HybridIndex::new(Rectangle::new(0.0, 0.0, 250.0, 250.0), 100.0);
+        index.put(Point::new(10.0, 10.0));
+        index.put(Point::new(20.0, 20.0));
+        index.put(Point::new(240.0, 240.0));
+        assert_eq!(index.tree_count(), 2);
+        assert_eq!(index.tree_at(Point::new(250.0, 250.0)).unwrap().len(), 1);
+        assert_eq!(index.tree_at(Point::new(99.0, 0.0)).unwrap().len(), 2);
+        assert!(index.tree_at(Point::new(150.0, 150.0)).is_none());
+
+        assert_eq!(
+            index.nearest(Point::new(150.0, 150.0)),
+            Some(&Point::new(240.0, 240.0))
+        );
+        assert_eq!(
+            index.nearest(Point::new(99.0, 99.0)),
+            Some(&Point::new(20.0, 20.0))
+        );
+
+        assert!(index.remove(Point::new(240.0, 240.0)).is_some());
+        assert_eq!(index.tree_count(), 1);
+        assert_eq!(
+            index.nearest(Point::new(150.0, 150.0)),
+            Some(&Point::new(20.0, 20.0))
+        );
+        index.clear();
+        assert!(index.is_empty());
+        assert!(index.nearest(Point::new(0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn test_rounding_does_not_add_a_column() {
+        let width = 0.1 + 0.2;
+        assert_eq!(width, 0.30000000000000004);
+        let mut index = HybridIndex::new(Rectangle::new(0.0, 0.0, width, 0.1), 0.1);
+        index.put(Point::new(width, 0.05));
+        index.put(Point::new(0.25, 0.1));
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.tree_count(), 1);
+        assert_eq!(index.tree_at(Point::new(width, 0.0)).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_remove_within_epsilon_across_cells() {
+        let options = Options {
+            epsilon: 0.5,
+            ..Default::default()
+        };
+        let mut index =
+            HybridIndex::with_options(Rectangle::new(0.0, 0.0, 200.0, 200.0), 100.0, options);
+        index.put(Point::new(100.2, 50.0));
+        assert_eq!(
+            index.remove(Point::new(99.9, 50.0)),
+            Some(Point::new(100.2, 50.0))
+        );
+        assert!(index.is_empty());
+        assert_eq!(index.tree_count(), 0);
+    }
+}
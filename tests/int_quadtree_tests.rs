@@ -0,0 +1,41 @@
+mod int_quadtree_tests {
+    use quadtree::*;
+
+    #[test]
+    fn test_put_and_query() {
+        let entity = ();
+
+        let mut qt = IntQuadtree::with_options(
+            IntRectangle::new(0, 0, 16, 16),
+            IntOptions {
+                max_items: 1,
+                max_depth: 8,
+                ..Default::default()
+            },
+        );
+        for x in 0..16 {
+            qt.put(IntItem::new(IntPoint::new(x, x), &entity));
+        }
+        qt.put(IntItem::new(IntPoint::new(16, 0), &entity));
+        assert_eq!(qt.len(), 16);
+
+        let points: Vec<IntPoint> = qt
+            .query(IntRectangle::new(4, 4, 4, 4))
+            .iter()
+            .map(|it| it.position())
+            .collect();
+        assert_eq!(
+            points,
+            vec![
+                IntPoint::new(4, 4),
+                IntPoint::new(5, 5),
+                IntPoint::new(6, 6),
+                IntPoint::new(7, 7),
+            ]
+        );
+        assert!(qt.query(IntRectangle::new(8, 0, 8, 8)).is_empty());
+
+        qt.clear();
+        assert!(qt.is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+mod keyed_quadtree_tests {
+    use quadtree::*;
+
+    #[test]
+    fn test_insert_and_get() {
+        let mut qt = KeyedQuadtree::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+        let id1 = qt.insert(Point::new(10.0, 10.0), "first").unwrap();
+        let id2 = qt.insert(Point::new(20.0, 20.0), "second").unwrap();
+        assert!(qt.insert(Point::new(200.0, 20.0), "outside").is_none());
+
+        assert_ne!(id1, id2);
+        assert_eq!(qt.len(), 2);
+        assert_eq!(qt.get_by_id(id1), Some(&"first"));
+        assert_eq!(qt.position_of(id2), Some(Point::new(20.0, 20.0)));
+
+        *qt.get_by_id_mut(id2).unwrap() = "renamed";
+        let hits = qt.query(Rectangle::new(15.0, 15.0, 10.0, 10.0));
+        assert_eq!(hits, vec![(id2, &"renamed")]);
+    }
+
+    #[test]
+    fn test_remove_by_id() {
+        let mut qt = KeyedQuadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        let id1 = qt.insert(Point::new(10.0, 10.0), 1).unwrap();
+        let id2 = qt.insert(Point::new(10.0, 10.0), 2).unwrap();
+        let id3 = qt.insert(Point::new(60.0, 60.0), 3).unwrap();
+
+        assert_eq!(qt.remove_by_id(id2), Some(2));
+        assert_eq!(qt.remove_by_id(id2), None);
+        assert_eq!(qt.len(), 2);
+        assert_eq!(qt.get_by_id(id1), Some(&1));
+        assert_eq!(qt.position_of(id2), None);
+
+        let all = qt.query(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+        assert_eq!(all, vec![(id1, &1), (id3, &3)]);
+    }
+
+    #[test]
+    fn test_eviction_frees_slots() {
+        let mut qt = KeyedQuadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_total_items: 2,
+                ..Default::default()
+            },
+        );
+        let area = qt.subscribe(Rectangle::new(0.0, 0.0, 50.0, 50.0));
+        let first = qt.insert(Point::new(10.0, 10.0), "first").unwrap();
+        let second = qt.insert(Point::new(60.0, 60.0), "second").unwrap();
+        let third = qt.insert(Point::new(70.0, 70.0), "third").unwrap();
+
+        assert_eq!(qt.len(), 2);
+        assert_eq!(qt.get_by_id(first), None);
+        assert_eq!(qt.position_of(first), None);
+        assert_eq!(
+            qt.query(Rectangle::new(0.0, 0.0, 100.0, 100.0)),
+            vec![(second, &"second"), (third, &"third")]
+        );
+        assert_eq!(
+            qt.take_events(),
+            vec![
+                (area, RegionEvent::Entered(first)),
+                (area, RegionEvent::Left(first))
+            ]
+        );
+
+        let fourth = qt.insert(Point::new(20.0, 20.0), "fourth").unwrap();
+        assert_ne!(fourth, first);
+        assert_eq!(qt.get_by_id(second), None);
+        assert_eq!(qt.get_by_id(fourth), Some(&"fourth"));
+    }
+
+    #[test]
+    fn test_stale_ids() {
+        let mut qt = KeyedQuadtree::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+        let old = qt.insert(Point::new(10.0, 10.0), "old").unwrap();
+        assert_eq!(qt.remove_by_id(old), Some("old"));
+
+        let new = qt.insert(Point::new(10.0, 10.0), "new").unwrap();
+        assert_ne!(old, new);
+        assert_eq!(qt.get_by_id(old), None);
+        assert_eq!(qt.get_by_id_mut(old), None);
+        assert_eq!(qt.position_of(old), None);
+        assert_eq!(qt.remove_by_id(old), None);
+        assert_eq!(qt.get_by_id(new), Some(&"new"));
+        assert_eq!(qt.len(), 1);
+    }
+
+    #[test]
+    fn test_apply_moves() {
+        let mut qt = KeyedQuadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        let id1 = qt.insert(Point::new(10.0, 10.0), 1).unwrap();
+        let id2 = qt.insert(Point::new(60.0, 10.0), 2).unwrap();
+        let id3 = qt.insert(Point::new(60.0, 60.0), 3).unwrap();
+        let stale = qt.insert(Point::new(10.0, 60.0), 4).unwrap();
+        qt.remove_by_id(stale);
+
+        qt.apply_moves(vec![
+            (id1, Point::new(90.0, 90.0)),
+            (id2, Point::new(70.0, 70.0)),
+            (id3, Point::new(500.0, 500.0)),
+            (stale, Point::new(20.0, 20.0)),
+        ]);
+
+        assert_eq!(qt.len(), 3);
+        assert_eq!(qt.position_of(id1), Some(Point::new(90.0, 90.0)));
+        assert_eq!(qt.position_of(id2), Some(Point::new(70.0, 70.0)));
+        assert_eq!(qt.position_of(id3), Some(Point::new(60.0, 60.0)));
+        assert!(qt.query(Rectangle::new(0.0, 0.0, 50.0, 50.0)).is_empty());
+
+        let mut south_east = qt.query(Rectangle::new(50.0, 50.0, 50.0, 50.0));
+        south_east.sort_by_key(|&(_, value)| *value);
+        assert_eq!(south_east, vec![(id1, &1), (id2, &2), (id3, &3)]);
+        assert!(qt.path_to(id3).unwrap().len() > 1);
+
+        qt.apply_moves(vec![
+            (id1, Point::new(10.0, 10.0)),
+            (id2, Point::new(10.0, 90.0)),
+        ]);
+        assert_eq!(qt.path_to(id3), Some(vec![2]));
+    }
+
+    #[test]
+    fn test_apply_moves_is_deterministic() {
+        let build = || {
+            let mut qt = KeyedQuadtree::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+            let ids: Vec<ItemId> = (0..32)
+                .map(|i| qt.insert(Point::new(i as f64, 10.0), i).unwrap())
+                .collect();
+            qt.apply_moves(ids.iter().map(|&id| (id, Point::new(50.0, 50.0))));
+            qt.query(Rectangle::new(0.0, 0.0, 100.0, 100.0))
+                .into_iter()
+                .map(|(_, &value)| value)
+                .collect::<Vec<i32>>()
+        };
+
+        let expected: Vec<i32> = (0..32).collect();
+        for _ in 0..4 {
+            assert_eq!(build(), expected);
+        }
+    }
+
+    #[test]
+    fn test_take_moves_owned_payloads() {
+        struct Job(String);
+
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let mut pending = KeyedQuadtree::new(boundary);
+        let mut running = KeyedQuadtree::new(boundary);
+        let id = pending
+            .insert(Point::new(25.0, 75.0), Job("render".to_string()))
+            .unwrap();
+
+        let (point, job) = pending.take(id).unwrap();
+        assert!(pending.take(id).is_none());
+        assert!(pending.is_empty());
+
+        let moved = running.insert(point, job).unwrap();
+        assert_eq!(running.position_of(moved), Some(Point::new(25.0, 75.0)));
+        assert_eq!(running.get_by_id(moved).unwrap().0, "render");
+    }
+}
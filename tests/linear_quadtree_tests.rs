@@ -0,0 +1,123 @@
+mod linear_quadtree_tests {
+    use quadtree::*;
+
+    #[test]
+    fn test_query_matches_plain_quadtree() {
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let options = Options {
+            max_depth: 6,
+            ..Default::default()
+        };
+        let mut linear = LinearQuadtree::with_options(boundary, options);
+        let mut plain = Quadtree::new(boundary);
+        let points: Vec<Point> = (0..1600)
+            .map(|i| Point::new(f64::from(i / 40) * 2.5 + 0.3, f64::from(i % 40) * 2.5 + 0.7))
+            .collect();
+        linear.extend(points[..800].iter().copied());
+        linear.extend(
+            points[800..]
+                .iter()
+                .copied()
+                .chain(Some(Point::new(150.0, 10.0))),
+        );
+        points.iter().for_each(|&point| plain.put(point));
+        assert_eq!(linear.len(), 1600);
+
+        let sorted = |items: Vec<&Point>| {
+            let mut items: Vec<(f64, f64)> = items.iter().map(|p| (p.x(), p.y())).collect();
+            items.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            items
+        };
+        for range in [
+            Rectangle::new(10.0, 20.0, 30.0, 5.0),
+            Rectangle::new(-5.0, -5.0, 12.0, 120.0),
+            Rectangle::new(49.0, 49.0, 2.0, 2.0),
+            boundary,
+        ] {
+            assert_eq!(sorted(linear.query(range)), sorted(plain.query(range)));
+        }
+    }
+
+    #[test]
+    fn test_key_ranges_cover_range() {
+        let boundary = Rectangle::new(0.0, 0.0, 64.0, 64.0);
+        assert_eq!(
+            MortonKey::ranges(&boundary, &boundary, 16),
+            vec![(MortonKey(0), MortonKey(u32::MAX))]
+        );
+        assert!(MortonKey::ranges(&boundary, &Rectangle::new(70.0, 0.0, 5.0, 5.0), 16).is_empty());
+
+        let range = Rectangle::new(3.0, 40.0, 20.0, 9.0);
+        let ranges = MortonKey::ranges(&boundary, &range, 8);
+        assert!(ranges.windows(2).all(|w| w[0].1 .0 + 1 < w[1].0 .0));
+        for x in 3..=23 {
+            for y in 40..=49 {
+                let key = MortonKey::of(&boundary, &Point::new(f64::from(x), f64::from(y)));
+                assert!(ranges.iter().any(|&(lo, hi)| lo <= key && key <= hi));
+            }
+        }
+    }
+
+    #[test]
+    fn test_query_key_range() {
+        let boundary = Rectangle::new(0.0, 0.0, 10.0, 10.0);
+        let mut tree = LinearQuadtree::new(boundary);
+        tree.put(Point::new(1.0, 1.0));
+        tree.put(Point::new(9.0, 1.0));
+        tree.put(Point::new(1.0, 9.0));
+        tree.put(Point::new(9.0, 9.0));
+
+        let lo = tree.key_of(&Point::new(9.0, 1.0));
+        let hi = tree.key_of(&Point::new(1.0, 9.0));
+        let items = tree.query_key_range(lo, hi);
+        assert_eq!(items.len(), 2);
+        assert_eq!((items[0].x(), items[0].y()), (9.0, 1.0));
+        assert_eq!((items[1].x(), items[1].y()), (1.0, 9.0));
+        assert!(tree.query_key_range(hi, lo).is_empty());
+
+        let key = tree.key_of(&Point::new(9.0, 9.0));
+        tree.extend(vec![
+            Point::new(9.0, 9.0),
+            Point::new(5.0, 5.0),
+            Point::new(9.000001, 9.000001),
+            Point::new(20.0, 5.0),
+        ]);
+        let same_key: Vec<(f64, f64)> = tree
+            .query_key_range(key, key)
+            .iter()
+            .map(|p| (p.x(), p.y()))
+            .collect();
+        assert_eq!(same_key, vec![(9.0, 9.0), (9.0, 9.0), (9.000001, 9.000001)]);
+        assert_eq!(tree.len(), 7);
+    }
+
+    #[test]
+    fn test_remove_and_nearest() {
+        let mut tree = LinearQuadtree::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+        tree.put(Point::new(10.0, 10.0));
+        tree.put(Point::new(10.0, 10.0));
+        tree.put(Point::new(90.0, 95.0));
+
+        let nearest = tree.nearest(Point::new(70.0, 70.0)).unwrap();
+        assert_eq!((nearest.x(), nearest.y()), (90.0, 95.0));
+        assert!(tree.remove(Point::new(10.0, 10.5)).is_none());
+        assert!(tree.remove(Point::new(10.0, 10.0)).is_some());
+        assert_eq!(tree.len(), 2);
+        assert!(tree.remove(Point::new(90.0, 95.0)).is_some());
+        let nearest = tree.nearest(Point::new(99.0, 0.0)).unwrap();
+        assert_eq!((nearest.x(), nearest.y()), (10.0, 10.0));
+        tree.clear();
+        assert!(tree.nearest(Point::new(50.0, 50.0)).is_none());
+    }
+
+    #[test]
+    fn test_nearest_non_finite() {
+        let mut tree = LinearQuadtree::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+        tree.put(Point::new(10.0, 10.0));
+
+        assert!(tree.nearest(Point::new(f64::NAN, 10.0)).is_none());
+        assert!(tree.nearest(Point::new(10.0, f64::INFINITY)).is_none());
+        let nearest = tree.nearest(Point::new(1e308, -1e308)).unwrap();
+        assert_eq!((nearest.x(), nearest.y()), (10.0, 10.0));
+    }
+}
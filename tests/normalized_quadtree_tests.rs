@@ -0,0 +1,49 @@
+mod normalized_quadtree_tests {
+    use quadtree::*;
+
+    #[test]
+    fn test_world_coordinates_round_trip() {
+        let world = Rectangle::new(1.0e9, -2.0e9, 1000.0, 4000.0);
+        let mut qt = NormalizedQuadtree::with_options(
+            world,
+            Options {
+                max_items: 2,
+                max_depth: 8,
+                ..Default::default()
+            },
+        );
+        for i in 0..10 {
+            qt.put(Point::new(1.0e9 + f64::from(i) * 100.0, -2.0e9 + 2000.0));
+        }
+        qt.put(Point::new(0.0, 0.0));
+        assert_eq!(qt.len(), 10);
+
+        let found = qt.query(Rectangle::new(1.0e9 + 150.0, -2.0e9, 300.0, 4000.0));
+        let mut xs: Vec<f64> = found.iter().map(|p| p.x() - 1.0e9).collect();
+        xs.sort_by(f64::total_cmp);
+        assert_eq!(xs, vec![200.0, 300.0, 400.0]);
+
+        assert_eq!(
+            qt.to_world(qt.to_unit(Point::new(1.0e9 + 250.0, -2.0e9 + 10.0))),
+            Point::new(1.0e9 + 250.0, -2.0e9 + 10.0)
+        );
+
+        let removed = qt.remove(Point::new(1.0e9 + 300.0, -2.0e9 + 2000.0));
+        assert_eq!(removed, Some(Point::new(1.0e9 + 300.0, -2.0e9 + 2000.0)));
+        assert_eq!(qt.len(), 9);
+    }
+
+    #[test]
+    fn test_nearest_uses_world_distance() {
+        let mut qt = NormalizedQuadtree::new(Rectangle::new(0.0, 0.0, 100.0, 10.0));
+        qt.put(Point::new(30.0, 0.0));
+        qt.put(Point::new(0.0, 10.0));
+
+        // In unit coordinates (0.3, 0) is closer to the origin than (0, 1),
+        // but in world coordinates it is the farther of the two.
+        assert_eq!(
+            qt.nearest(Point::new(0.0, 0.0)),
+            Some(&Point::new(0.0, 10.0))
+        );
+    }
+}
@@ -0,0 +1,94 @@
+mod predictive_quadtree_tests {
+    use quadtree::*;
+
+    struct Unit {
+        id: i64,
+        velocity: (f64, f64),
+    }
+
+    impl Moving for Unit {
+        fn velocity(&self) -> (f64, f64) {
+            self.velocity
+        }
+    }
+
+    #[test]
+    fn test_query_swept_bounds() {
+        let still = Unit {
+            id: 1,
+            velocity: (0.0, 0.0),
+        };
+        let fast = Unit {
+            id: 2,
+            velocity: (40.0, 0.0),
+        };
+        let away = Unit {
+            id: 3,
+            velocity: (-10.0, -10.0),
+        };
+
+        let mut qt = PredictiveQuadtree::new(Rectangle::new(0.0, 0.0, 200.0, 200.0), 1.0);
+        qt.put(Item::new(Point::new(10.0, 10.0), &still));
+        qt.put(Item::new(Point::new(20.0, 50.0), &fast));
+        qt.put(Item::new(Point::new(30.0, 40.0), &away));
+
+        let ids: Vec<i64> = qt
+            .query(Rectangle::new(50.0, 45.0, 10.0, 10.0))
+            .iter()
+            .map(|it| it.id)
+            .collect();
+        assert_eq!(ids, vec![2]);
+
+        let ids: Vec<i64> = qt
+            .query(Rectangle::new(15.0, 25.0, 5.0, 5.0))
+            .iter()
+            .map(|it| it.id)
+            .collect();
+        assert_eq!(ids, vec![3]);
+
+        let swept = qt.swept_bounds(&Item::new(Point::new(20.0, 50.0), &fast));
+        assert_eq!(
+            (swept.x(), swept.y(), swept.width(), swept.height()),
+            (20.0, 50.0, 40.0, 0.0)
+        );
+        assert_eq!(qt.len(), 3);
+    }
+
+    #[test]
+    fn test_remove_and_update() {
+        let slow = Unit {
+            id: 1,
+            velocity: (1.0, 0.0),
+        };
+        let fast = Unit {
+            id: 2,
+            velocity: (0.0, 80.0),
+        };
+        let turned = Unit {
+            id: 3,
+            velocity: (80.0, 0.0),
+        };
+
+        let mut qt = PredictiveQuadtree::new(Rectangle::new(0.0, 0.0, 200.0, 200.0), 1.0);
+        qt.put(Item::new(Point::new(10.0, 10.0), &slow));
+        qt.put(Item::new(Point::new(100.0, 10.0), &fast));
+        let ahead = Rectangle::new(95.0, 80.0, 10.0, 10.0);
+        let ids = |qt: &PredictiveQuadtree<Item<Unit>>, range| -> Vec<i64> {
+            qt.query(range).iter().map(|it| it.id).collect()
+        };
+        assert_eq!(ids(&qt, ahead), vec![2]);
+
+        let old = qt.update(
+            Point::new(100.0, 10.0),
+            Item::new(Point::new(100.0, 10.0), &turned),
+        );
+        assert_eq!(old.map(|it| it.id), Some(2));
+        assert!(ids(&qt, ahead).is_empty());
+        assert_eq!(ids(&qt, Rectangle::new(170.0, 5.0, 10.0, 10.0)), vec![3]);
+
+        assert_eq!(qt.remove(Point::new(100.0, 10.0)).map(|it| it.id), Some(3));
+        assert!(qt.remove(Point::new(100.0, 10.0)).is_none());
+        assert_eq!(ids(&qt, Rectangle::new(10.5, 5.0, 1.0, 10.0)), vec![1]);
+        assert_eq!(qt.len(), 1);
+    }
+}
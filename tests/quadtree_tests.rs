@@ -13,6 +13,14 @@ mod quadtree_tests {
         }
     }
 
+    struct Population(f64);
+
+    impl Weighted for Population {
+        fn weight(&self) -> f64 {
+            self.0
+        }
+    }
+
     #[test]
     fn test_it_works() {
         let entity1 = Entity::new(1, String::from("nickname1"));
@@ -84,4 +92,3360 @@ mod quadtree_tests {
         assert_eq!(items7.len(), 1);
         assert!(points7.contains(&Point::new(110.0, 110.0)));
     }
+
+    #[test]
+    fn test_visibility_polygon() {
+        struct Obstacle(Occlusion);
+        impl Position for Obstacle {
+            fn position(&self) -> Point {
+                match self.0 {
+                    Occlusion::Segment(a, b) => {
+                        Point::new((a.x() + b.x()) / 2.0, (a.y() + b.y()) / 2.0)
+                    }
+                    Occlusion::Box(rect) => Point::new(
+                        rect.x() + rect.width() / 2.0,
+                        rect.y() + rect.height() / 2.0,
+                    ),
+                }
+            }
+            fn reach(&self) -> f64 {
+                match self.0 {
+                    Occlusion::Segment(a, b) => (a.x() - b.x()).hypot(a.y() - b.y()) / 2.0,
+                    Occlusion::Box(rect) => rect.width().hypot(rect.height()) / 2.0,
+                }
+            }
+        }
+        impl Occluder for Obstacle {
+            fn occlusion(&self) -> Occlusion {
+                self.0
+            }
+        }
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        let wall = Occlusion::Segment(Point::new(50.0, 60.0), Point::new(50.0, 90.0));
+        qt.put(Obstacle(wall));
+        qt.put(Obstacle(Occlusion::Box(Rectangle::new(
+            20.0, 40.0, 2.0, 2.0,
+        ))));
+        qt.put(Obstacle(Occlusion::Box(Rectangle::new(
+            20.0, 70.0, 10.0, 10.0,
+        ))));
+
+        let origin = Point::new(25.0, 75.0);
+        let polygon = qt.visibility_polygon(origin, 40.0);
+        assert!(polygon.len() >= 64);
+        assert_eq!(polygon[0], Point::new(50.0, 75.0));
+        for point in &polygon {
+            let distance = (point.x() - origin.x()).hypot(point.y() - origin.y());
+            assert!(distance <= 40.0 + 1e-9);
+        }
+        // The small box does not shadow the whole leaf holding it.
+        assert!(polygon
+            .iter()
+            .any(|p| (p.x() - 25.0).abs() < 1e-9 && (p.y() - 35.0).abs() < 1e-9));
+        // Rays towards the box are stopped at its near edge.
+        for corner in &[Point::new(20.0, 42.0), Point::new(22.0, 42.0)] {
+            assert!(polygon
+                .iter()
+                .any(|p| (p.x() - corner.x()).hypot(p.y() - corner.y()) < 1e-3));
+        }
+
+        let open = Quadtree::<Obstacle>::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+        let circle = open.visibility_polygon(Point::new(50.0, 50.0), 10.0);
+        assert_eq!(circle.len(), 64);
+    }
+
+    #[test]
+    fn test_query_at_depth() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(30.0, 30.0), &entity));
+        qt.put(Item::new(Point::new(110.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(110.0, 110.0), &entity));
+
+        let root = qt.query_at_depth(Rectangle::new(0.0, 0.0, 200.0, 200.0), 0);
+        assert_eq!(root.len(), 1);
+        assert_eq!(root[0].count, 4);
+        assert_eq!(root[0].centroid, Point::new(65.0, 40.0));
+
+        let quadrants = qt.query_at_depth(Rectangle::new(0.0, 0.0, 200.0, 200.0), 1);
+        let counts: Vec<usize> = quadrants.iter().map(|s| s.count).collect();
+        assert_eq!(counts, vec![2, 1, 1]);
+        assert_eq!(quadrants[0].centroid, Point::new(20.0, 20.0));
+        assert_eq!(quadrants[0].bounds.width(), 100.0);
+
+        let west = qt.query_at_depth(Rectangle::new(0.0, 0.0, 50.0, 200.0), 1);
+        assert_eq!(west.len(), 1);
+        assert_eq!(west[0].count, 2);
+
+        qt.remove(Point::new(30.0, 30.0));
+        let root = qt.query_at_depth(Rectangle::new(0.0, 0.0, 200.0, 200.0), 0);
+        assert_eq!(root[0].count, 3);
+        assert_eq!(root[0].centroid, Point::new(230.0 / 3.0, 130.0 / 3.0));
+        qt.remove(Point::new(110.0, 10.0));
+        qt.remove(Point::new(110.0, 110.0));
+        let root = qt.query_at_depth(Rectangle::new(0.0, 0.0, 200.0, 200.0), 0);
+        assert_eq!(root[0].centroid, Point::new(10.0, 10.0));
+    }
+
+    #[test]
+    fn test_visible_items() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Item::new(Point::new(110.0, 110.0), &entity));
+        qt.put(Item::new(Point::new(10.0, 110.0), &entity));
+        qt.put(Item::new(Point::new(110.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(150.0, 150.0), &entity));
+
+        let items = qt.visible_items(Rectangle::new(20.0, 20.0, 100.0, 100.0), 10.0);
+        let points: Vec<Point> = items.iter().map(|&it| it.position()).collect();
+        assert_eq!(
+            points,
+            vec![
+                Point::new(10.0, 10.0),
+                Point::new(110.0, 10.0),
+                Point::new(10.0, 110.0),
+                Point::new(110.0, 110.0),
+            ]
+        );
+
+        let mut buffer = Vec::new();
+        qt.visible_items_into(Rectangle::new(140.0, 140.0, 20.0, 20.0), 0.0, &mut buffer);
+        assert_eq!(buffer.len(), 1);
+        qt.visible_items_into(Rectangle::new(0.0, 0.0, 5.0, 5.0), 5.0, &mut buffer);
+        assert_eq!(buffer.len(), 1);
+        assert_eq!(buffer[0].position(), Point::new(10.0, 10.0));
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(20.0, 20.0), &entity));
+        qt.put(Item::new(Point::new(110.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(10.0, 110.0), &entity));
+        qt.put(Item::new(Point::new(200.0, 200.0), &entity));
+
+        let cells = qt.heatmap(Rectangle::new(0.0, 0.0, 200.0, 200.0), 2, 2);
+        assert_eq!(cells, vec![2.0, 1.0, 1.0, 1.0]);
+
+        let cells = qt.heatmap(Rectangle::new(0.0, 0.0, 100.0, 100.0), 4, 1);
+        assert_eq!(cells, vec![2.0, 0.0, 0.0, 0.0]);
+
+        let cells = qt.heatmap_by(Rectangle::new(0.0, 0.0, 200.0, 200.0), 2, 2, |item| {
+            item.position().x()
+        });
+        assert_eq!(cells, vec![30.0, 110.0, 10.0, 200.0]);
+
+        assert!(qt
+            .heatmap(Rectangle::new(0.0, 0.0, 200.0, 200.0), 0, 3)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_len() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        assert!(qt.is_empty());
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(20.0, 20.0), &entity));
+        qt.put(Item::new(Point::new(300.0, 20.0), &entity));
+        qt.put(Item::new(Point::new(100.0, 100.0), &entity));
+        assert_eq!(qt.len(), 3);
+        assert_eq!(qt.query(Rectangle::new(0.0, 0.0, 200.0, 200.0)).len(), 3);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_sample_in_region() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let entities: Vec<i64> = (0..100).collect();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 4,
+                ..Default::default()
+            },
+        );
+        for (i, entity) in entities.iter().enumerate() {
+            let (x, y) = ((i % 10) as f64 * 10.0 + 5.0, (i / 10) as f64 * 10.0 + 5.0);
+            qt.put(Item::new(Point::new(x, y), entity));
+        }
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let region = Rectangle::new(0.0, 0.0, 50.0, 30.0);
+        let samples = qt.sample_in_region(region, 10, &mut rng);
+        assert_eq!(samples.len(), 10);
+        let mut ids: Vec<i64> = samples.iter().map(|it| ***it).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), 10);
+        for item in &samples {
+            let p = item.position();
+            assert!(p.x() <= 50.0 && p.y() <= 30.0);
+        }
+
+        let all = qt.sample_in_region(region, 100, &mut rng);
+        assert_eq!(all.len(), 15);
+    }
+
+    #[test]
+    fn test_weighted_queries() {
+        let small = Population(1.0);
+        let large = Population(3.0);
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Item::new(Point::new(10.0, 10.0), &small));
+        qt.put(Item::new(Point::new(50.0, 10.0), &large));
+        qt.put(Item::new(Point::new(150.0, 150.0), &large));
+
+        let region = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        assert_eq!(qt.total_weight_in_region(region), 4.0);
+        assert_eq!(qt.centroid_in_region(region), Some(Point::new(40.0, 10.0)));
+        assert_eq!(
+            qt.total_weight_in_region(Rectangle::new(0.0, 0.0, 200.0, 200.0)),
+            7.0
+        );
+        assert_eq!(
+            qt.centroid_in_region(Rectangle::new(100.0, 0.0, 50.0, 50.0)),
+            None
+        );
+        assert_eq!(
+            qt.weighted_heatmap(Rectangle::new(0.0, 0.0, 200.0, 200.0), 2, 2),
+            vec![4.0, 0.0, 0.0, 3.0]
+        );
+
+        let mut maintained = AggregateQuadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+            WeightedCentroid,
+        );
+        for item in qt.iter() {
+            maintained.put(Item::new(item.position(), &**item));
+        }
+        assert_eq!(maintained.total_weight_in_region(region), 4.0);
+        assert_eq!(
+            maintained.centroid_in_region(region),
+            Some(Point::new(40.0, 10.0))
+        );
+        assert_eq!(maintained.aggregate().total, 7.0);
+        assert_eq!(
+            maintained.centroid_in_region(Rectangle::new(100.0, 0.0, 50.0, 50.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_kde() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(12.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(90.0, 90.0), &entity));
+        qt.put(Item::new(Point::new(95.0, 90.0), &entity));
+
+        let uniform = qt.kde(Point::new(10.0, 10.0), 5.0, Kernel::Uniform);
+        let expected = 2.0 / std::f64::consts::PI / (4.0 * 25.0);
+        assert!((uniform - expected).abs() < 1e-12);
+
+        let dense = qt.kde(Point::new(11.0, 10.0), 5.0, Kernel::Epanechnikov);
+        let sparse = qt.kde(Point::new(92.5, 90.0), 5.0, Kernel::Epanechnikov);
+        assert!(dense > sparse);
+        assert!(sparse > 0.0);
+
+        assert_eq!(qt.kde(Point::new(50.0, 50.0), 5.0, Kernel::Gaussian), 0.0);
+        assert!(qt.kde(Point::new(11.0, 10.0), 5.0, Kernel::Gaussian) > 0.0);
+    }
+
+    #[test]
+    fn test_query_radius() {
+        let entity = ();
+
+        let mut qt = Quadtree::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(13.0, 14.0), &entity));
+        qt.put(Item::new(Point::new(14.0, 14.0), &entity));
+
+        let items = qt.query_radius(Point::new(10.0, 10.0), 5.0);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].position(), Point::new(13.0, 14.0));
+    }
+
+    #[test]
+    fn test_cluster_dbscan() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 2,
+                ..Default::default()
+            },
+        );
+        for &(x, y) in &[(10.0, 10.0), (11.0, 10.0), (12.0, 11.0), (10.0, 12.0)] {
+            qt.put(Item::new(Point::new(x, y), &entity));
+        }
+        for &(x, y) in &[(80.0, 80.0), (81.0, 81.0), (82.0, 80.0)] {
+            qt.put(Item::new(Point::new(x, y), &entity));
+        }
+        qt.put(Item::new(Point::new(50.0, 50.0), &entity));
+
+        let mut clusters = qt.cluster_dbscan(2.5, 3);
+        clusters.sort_by_key(|c| c.len());
+        let sizes: Vec<usize> = clusters.iter().map(|c| c.len()).collect();
+        assert_eq!(sizes, vec![3, 4]);
+        assert!(clusters[0].iter().all(|it| it.position().x() >= 80.0));
+
+        assert!(qt.cluster_dbscan(0.5, 2).is_empty());
+    }
+
+    #[test]
+    fn test_convex_hull_in_region() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 2,
+                ..Default::default()
+            },
+        );
+        for &(x, y) in &[
+            (10.0, 10.0),
+            (40.0, 10.0),
+            (20.0, 20.0),
+            (40.0, 40.0),
+            (25.0, 10.0),
+            (10.0, 40.0),
+            (90.0, 90.0),
+        ] {
+            qt.put(Item::new(Point::new(x, y), &entity));
+        }
+
+        let hull = qt.convex_hull_in_region(Rectangle::new(0.0, 0.0, 50.0, 50.0));
+        assert_eq!(
+            hull,
+            vec![
+                Point::new(10.0, 10.0),
+                Point::new(40.0, 10.0),
+                Point::new(40.0, 40.0),
+                Point::new(10.0, 40.0),
+            ]
+        );
+
+        let single = qt.convex_hull_in_region(Rectangle::new(80.0, 80.0, 20.0, 20.0));
+        assert_eq!(single, vec![Point::new(90.0, 90.0)]);
+
+        let mut grid = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 4,
+                max_depth: 6,
+                ..Default::default()
+            },
+        );
+        for i in 0..33 {
+            for j in 0..33 {
+                grid.put(Point::new(f64::from(2 + 3 * i), f64::from(2 + 3 * j)));
+            }
+        }
+        assert_eq!(
+            grid.convex_hull_in_region(Rectangle::new(10.0, 10.0, 50.0, 50.0)),
+            vec![
+                Point::new(11.0, 11.0),
+                Point::new(59.0, 11.0),
+                Point::new(59.0, 59.0),
+                Point::new(11.0, 59.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_items_bounds() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        assert!(qt.items_bounds().is_none());
+        qt.put(Item::new(Point::new(10.0, 20.0), &entity));
+        qt.put(Item::new(Point::new(30.0, 15.0), &entity));
+        qt.put(Item::new(Point::new(150.0, 120.0), &entity));
+
+        let bounds = qt.items_bounds().unwrap();
+        assert_eq!(
+            (bounds.x(), bounds.y(), bounds.width(), bounds.height()),
+            (10.0, 15.0, 140.0, 105.0)
+        );
+
+        let region = qt
+            .bounds_in_region(Rectangle::new(0.0, 0.0, 100.0, 100.0))
+            .unwrap();
+        assert_eq!(
+            (region.x(), region.y(), region.width(), region.height()),
+            (10.0, 15.0, 20.0, 5.0)
+        );
+        assert!(qt
+            .bounds_in_region(Rectangle::new(50.0, 50.0, 20.0, 20.0))
+            .is_none());
+    }
+
+    #[test]
+    fn test_farthest() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        assert!(qt.farthest(Point::new(0.0, 0.0)).is_none());
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(150.0, 20.0), &entity));
+        qt.put(Item::new(Point::new(120.0, 130.0), &entity));
+        qt.put(Item::new(Point::new(20.0, 160.0), &entity));
+
+        let far = qt.farthest(Point::new(0.0, 0.0)).unwrap();
+        assert_eq!(far.position(), Point::new(120.0, 130.0));
+        let far = qt.farthest(Point::new(200.0, 0.0)).unwrap();
+        assert_eq!(far.position(), Point::new(20.0, 160.0));
+    }
+
+    #[test]
+    fn test_nearest_and_knn() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        assert!(qt.nearest(Point::new(0.0, 0.0)).is_none());
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(40.0, 40.0), &entity));
+        qt.put(Item::new(Point::new(55.0, 0.0), &entity));
+        qt.put(Item::new(Point::new(150.0, 150.0), &entity));
+
+        let nearest = qt.nearest(Point::new(100.0, 100.0)).unwrap();
+        assert_eq!(nearest.position(), Point::new(150.0, 150.0));
+
+        let points: Vec<Point> = qt
+            .knn(Point::new(0.0, 0.0), 3)
+            .iter()
+            .map(|it| it.position())
+            .collect();
+        assert_eq!(
+            points,
+            vec![
+                Point::new(10.0, 10.0),
+                Point::new(55.0, 0.0),
+                Point::new(40.0, 40.0),
+            ]
+        );
+        assert_eq!(qt.knn(Point::new(0.0, 0.0), 10).len(), 4);
+        assert!(qt.knn(Point::new(0.0, 0.0), 0).is_empty());
+    }
+
+    #[test]
+    fn test_nearest_with_metrics() {
+        let entity = ();
+
+        let mut qt = Quadtree::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+        qt.put(Item::new(Point::new(30.0, 30.0), &entity));
+        qt.put(Item::new(Point::new(0.0, 45.0), &entity));
+
+        let origin = Point::new(0.0, 0.0);
+        let euclidean = qt.nearest_by(origin, &Euclidean).unwrap();
+        assert_eq!(euclidean.position(), Point::new(30.0, 30.0));
+        let manhattan = qt.nearest_by(origin, &Manhattan).unwrap();
+        assert_eq!(manhattan.position(), Point::new(0.0, 45.0));
+        let chebyshev = qt.nearest_by(origin, &Chebyshev).unwrap();
+        assert_eq!(chebyshev.position(), Point::new(30.0, 30.0));
+
+        let vertical = FnMetric::new(
+            |a: &Point, b: &Point| (a.y() - b.y()).abs(),
+            |p: &Point, r: &Rectangle| (r.y() - p.y()).max(p.y() - r.y() - r.height()).max(0.0),
+        );
+        let closest = qt.nearest_by(Point::new(100.0, 50.0), &vertical).unwrap();
+        assert_eq!(closest.position(), Point::new(0.0, 45.0));
+    }
+
+    #[test]
+    fn test_knn_within() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(13.0, 14.0), &entity));
+        qt.put(Item::new(Point::new(20.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(150.0, 150.0), &entity));
+
+        let items = qt.knn_within(Point::new(10.0, 10.0), 3, 6.0);
+        let points: Vec<Point> = items.iter().map(|it| it.position()).collect();
+        assert_eq!(points, vec![Point::new(10.0, 10.0), Point::new(13.0, 14.0)]);
+
+        assert_eq!(qt.knn_within(Point::new(10.0, 10.0), 1, 100.0).len(), 1);
+        assert!(qt.knn_within(Point::new(100.0, 100.0), 5, 10.0).is_empty());
+        let manhattan = qt.knn_within_by(Point::new(10.0, 10.0), 3, 7.0, &Manhattan);
+        assert_eq!(manhattan.len(), 2);
+    }
+
+    #[test]
+    fn test_take_dirty_regions() {
+        let entity = ();
+        let bounds = |r: &Rectangle| (r.x(), r.y(), r.width(), r.height());
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                track_dirty: true,
+                ..Default::default()
+            },
+        );
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        let regions: Vec<_> = qt.take_dirty_regions().iter().map(bounds).collect();
+        assert_eq!(regions, vec![(0.0, 0.0, 200.0, 200.0)]);
+        assert!(qt.take_dirty_regions().is_empty());
+
+        qt.put(Item::new(Point::new(110.0, 10.0), &entity));
+        qt.take_dirty_regions();
+        qt.put(Item::new(Point::new(110.0, 110.0), &entity));
+        qt.put(Item::new(Point::new(10.0, 110.0), &entity));
+        let regions: Vec<_> = qt.take_dirty_regions().iter().map(bounds).collect();
+        assert_eq!(
+            regions,
+            vec![(100.0, 100.0, 100.0, 100.0), (0.0, 100.0, 100.0, 100.0)]
+        );
+
+        let mut untracked = Quadtree::new(Rectangle::new(0.0, 0.0, 200.0, 200.0));
+        untracked.put(Item::new(Point::new(10.0, 10.0), &entity));
+        assert!(untracked.take_dirty_regions().is_empty());
+    }
+
+    #[test]
+    fn test_observer() {
+        use std::sync::{Arc, Mutex};
+
+        struct Recorder(Arc<Mutex<Vec<(&'static str, f64)>>>);
+
+        impl Observer for Recorder {
+            fn on_insert(&mut self, bounds: Rectangle) {
+                self.0.lock().unwrap().push(("insert", bounds.width()));
+            }
+
+            fn on_subdivide(&mut self, bounds: Rectangle) {
+                self.0.lock().unwrap().push(("subdivide", bounds.width()));
+            }
+        }
+
+        let entity = ();
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.set_observer(Box::new(Recorder(events.clone())));
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(110.0, 10.0), &entity));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![("insert", 200.0), ("subdivide", 200.0), ("insert", 100.0)]
+        );
+
+        assert!(qt.take_observer().is_some());
+        qt.put(Item::new(Point::new(110.0, 110.0), &entity));
+        assert_eq!(events.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                collect_metrics: true,
+                ..Default::default()
+            },
+        );
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(110.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(20.0, 20.0), &entity));
+        assert_eq!(qt.query(Rectangle::new(0.0, 0.0, 50.0, 50.0)).len(), 2);
+
+        let metrics = qt.metrics().unwrap();
+        assert_eq!(metrics.queries, 1);
+        assert_eq!(metrics.subdivisions, 3);
+        assert_eq!(metrics.max_depth, 3);
+        assert_eq!(metrics.nodes_visited, 13);
+        assert_eq!(metrics.items_scanned, 3);
+
+        qt.reset_metrics();
+        assert_eq!(qt.metrics(), Some(Metrics::default()));
+
+        let untracked = Quadtree::<Item<()>>::new(Rectangle::new(0.0, 0.0, 200.0, 200.0));
+        assert!(untracked.metrics().is_none());
+    }
+
+    #[test]
+    fn test_clear() {
+        let entity = ();
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Item::new(Point::new(10.0, 10.0), &entity));
+        qt.put(Item::new(Point::new(110.0, 10.0), &entity));
+        qt.clear();
+        assert!(qt.is_empty());
+        assert!(qt.items_bounds().is_none());
+        assert!(qt.query(Rectangle::new(0.0, 0.0, 200.0, 200.0)).is_empty());
+
+        qt.put(Item::new(Point::new(110.0, 10.0), &entity));
+        assert_eq!(qt.len(), 1);
+    }
+
+    #[test]
+    fn test_primitive_positions() {
+        use std::rc::Rc;
+        use std::sync::Arc;
+
+        let everything = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+
+        let mut tuples = Quadtree::new(everything);
+        tuples.put((10.0, 20.0));
+        assert_eq!(tuples.query(everything), vec![&(10.0, 20.0)]);
+
+        let mut arrays = Quadtree::new(everything);
+        arrays.put([30.0, 40.0]);
+        assert_eq!(arrays.nearest(Point::new(0.0, 0.0)), Some(&[30.0, 40.0]));
+
+        let mut points = Quadtree::new(everything);
+        points.put(Point::new(1.0, 2.0));
+        points.put(Point::new(200.0, 2.0));
+        assert_eq!(points.len(), 1);
+
+        let shared = Rc::new(Point::new(5.0, 5.0));
+        let mut rcs = Quadtree::new(everything);
+        rcs.put(shared.clone());
+        assert_eq!(Rc::strong_count(&shared), 2);
+
+        let mut arcs = Quadtree::new(everything);
+        arcs.put(Arc::new((7.0, 7.0)));
+        let mut boxes = Quadtree::new(everything);
+        boxes.put(Box::new([8.0, 8.0]));
+        let target = (9.0, 9.0);
+        let mut refs = Quadtree::new(everything);
+        refs.put(&target);
+        assert_eq!(arcs.len() + boxes.len() + refs.len(), 3);
+    }
+
+    #[test]
+    fn test_rectangle_constructors() {
+        let centered = Rectangle::from_center(Point::new(50.0, 40.0), 10.0, 5.0);
+        assert_eq!(
+            (
+                centered.x(),
+                centered.y(),
+                centered.width(),
+                centered.height()
+            ),
+            (40.0, 35.0, 20.0, 10.0)
+        );
+
+        let spanned = Rectangle::from_corners(Point::new(30.0, 10.0), Point::new(10.0, 25.0));
+        assert_eq!(
+            (spanned.x(), spanned.y(), spanned.width(), spanned.height()),
+            (10.0, 10.0, 20.0, 15.0)
+        );
+    }
+
+    #[test]
+    fn test_rectangle_set_operations() {
+        let a = Rectangle::new(0.0, 0.0, 20.0, 20.0);
+        let b = Rectangle::new(10.0, 5.0, 20.0, 10.0);
+
+        let overlap = a.intersection(&b).unwrap();
+        assert_eq!(
+            (overlap.x(), overlap.y(), overlap.width(), overlap.height()),
+            (10.0, 5.0, 10.0, 10.0)
+        );
+        assert!(a
+            .intersection(&Rectangle::new(30.0, 0.0, 5.0, 5.0))
+            .is_none());
+        assert_eq!(
+            a.intersection(&Rectangle::new(20.0, 0.0, 5.0, 5.0))
+                .map(|edge| edge.width()),
+            Some(0.0)
+        );
+
+        let both = a.union(&b);
+        assert_eq!(
+            (both.x(), both.y(), both.width(), both.height()),
+            (0.0, 0.0, 30.0, 20.0)
+        );
+    }
+
+    #[test]
+    fn test_rectangle_adjustments() {
+        let rect = Rectangle::new(10.0, 10.0, 20.0, 10.0);
+        let dims = |r: Rectangle| (r.x(), r.y(), r.width(), r.height());
+
+        assert_eq!(dims(rect.inflated(5.0)), (5.0, 5.0, 30.0, 20.0));
+        assert_eq!(dims(rect.translated(-10.0, 5.0)), (0.0, 15.0, 20.0, 10.0));
+        assert_eq!(dims(rect.scaled(2.0)), (0.0, 5.0, 40.0, 20.0));
+    }
+
+    #[test]
+    fn test_rectangle_quadrants() {
+        let rect = Rectangle::new(10.0, 10.0, 20.0, 10.0);
+        let dims = |r: Rectangle| (r.x(), r.y(), r.width(), r.height());
+
+        assert!(rect.contains_point(&Point::new(30.0, 20.0)));
+        assert!(!rect.contains_point(&Point::new(30.5, 20.0)));
+        assert_eq!(rect.quadrant(&Point::new(20.0, 15.0)), 0);
+        assert_eq!(rect.quadrant(&Point::new(25.0, 12.0)), 1);
+        assert_eq!(rect.quadrant(&Point::new(25.0, 18.0)), 2);
+        assert_eq!(rect.quadrant(&Point::new(12.0, 18.0)), 3);
+        assert_eq!(dims(rect.quadrant_cell(2)), (20.0, 15.0, 10.0, 5.0));
+        assert_eq!(dims(rect.quadrant_cell(3)), (10.0, 15.0, 10.0, 5.0));
+    }
+
+    #[test]
+    fn test_negative_rectangle_is_normalized() {
+        let flipped = Rectangle::new(30.0, 30.0, -20.0, -10.0);
+        assert_eq!(
+            (flipped.x(), flipped.y(), flipped.width(), flipped.height()),
+            (10.0, 20.0, 20.0, 10.0)
+        );
+
+        let mut qt = Quadtree::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+        qt.put(Point::new(15.0, 25.0));
+        assert_eq!(qt.query(flipped).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "positive area")]
+    fn test_zero_area_boundary_is_rejected() {
+        Quadtree::<Point>::new(Rectangle::new(0.0, 0.0, 100.0, 0.0));
+    }
+
+    #[test]
+    fn test_epsilon_point_matching() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                epsilon: 1e-9,
+                ..Default::default()
+            },
+        );
+        qt.put(Point::new(50.0, 50.0));
+        qt.put(Point::new(10.0, 10.0));
+        qt.put(Point::new(90.0, 90.0));
+
+        // Noise pushes the lookup across the midline into another quadrant.
+        let noisy = Point::new(50.0 + 1e-12, 50.0 + 1e-12);
+        assert_eq!(qt.get(noisy), Some(&Point::new(50.0, 50.0)));
+        assert_eq!(qt.get(Point::new(50.001, 50.0)), None);
+
+        assert_eq!(qt.remove(noisy), Some(Point::new(50.0, 50.0)));
+        assert_eq!(qt.remove(noisy), None);
+        assert_eq!(qt.len(), 2);
+    }
+
+    #[test]
+    fn test_insert_or_replace() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                epsilon: 1e-9,
+                ..Default::default()
+            },
+        );
+        let at = |x: f64, y: f64, id: &'static i32| Item::new(Point::new(x, y), id);
+
+        assert!(qt.insert_or_replace(at(10.0, 10.0, &1)).is_none());
+        assert!(qt.insert_or_replace(at(60.0, 60.0, &2)).is_none());
+        assert_eq!(
+            qt.insert_or_replace(at(10.0, 10.0, &3)).map(|e| *e),
+            Some(1)
+        );
+        assert_eq!(
+            qt.insert_or_replace(at(60.0 + 1e-12, 60.0, &4)).map(|e| *e),
+            Some(2)
+        );
+        assert!(qt.insert_or_replace(at(200.0, 10.0, &5)).is_none());
+
+        let mut stored: Vec<i32> = qt
+            .query(Rectangle::new(0.0, 0.0, 100.0, 100.0))
+            .iter()
+            .map(|e| ***e)
+            .collect();
+        stored.sort_unstable();
+        assert_eq!(stored, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_multimap() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 2,
+                ..Default::default()
+            },
+        );
+        let events = ["open", "close", "reopen", "move", "visit"];
+        let address = Point::new(50.0, 50.0);
+        qt.put(Item::new(address, &events[0]));
+        qt.put(Item::new(Point::new(20.0, 80.0), &events[1]));
+        qt.put(Item::new(address, &events[2]));
+        qt.put(Item::new(Point::new(20.0, 10.0), &events[3]));
+        qt.put(Item::new(address, &events[4]));
+
+        let at: Vec<&str> = qt.entries_at(address).iter().map(|e| ***e).collect();
+        assert_eq!(at, vec!["open", "reopen", "visit"]);
+
+        let groups: Vec<(Point, usize)> = qt
+            .group_by_position()
+            .map(|(point, items)| (point, items.len()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                (Point::new(20.0, 10.0), 1),
+                (Point::new(20.0, 80.0), 1),
+                (address, 3)
+            ]
+        );
+
+        assert_eq!(qt.remove_all_at(address).len(), 3);
+        assert!(qt.entries_at(address).is_empty());
+        assert_eq!(qt.len(), 2);
+    }
+
+    #[test]
+    fn test_node_data() {
+        let mut qt: Quadtree<Point, Option<&str>> = Quadtree::with_node_data(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Point::new(10.0, 10.0));
+        qt.put(Point::new(90.0, 90.0));
+
+        let refs = qt.node_refs();
+        assert_eq!(refs.len(), 5);
+        let south_east = refs[3];
+        assert_eq!(south_east.depth(), 1);
+        assert_eq!(
+            qt.node_bounds(south_east).map(|b| (b.x(), b.y())),
+            Some((50.0, 50.0))
+        );
+
+        *qt.node_data_mut(south_east).unwrap() = Some("buffer");
+        qt.put(Point::new(70.0, 60.0));
+        assert_eq!(qt.node_data(south_east), Some(&Some("buffer")));
+
+        qt.remove(Point::new(70.0, 60.0));
+        qt.remove(Point::new(90.0, 90.0));
+        assert_eq!(qt.node_data(south_east), None);
+        qt.put(Point::new(90.0, 90.0));
+        assert_eq!(qt.node_data(south_east), Some(&None));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 2,
+                epsilon: 0.5,
+                ..Default::default()
+            },
+        );
+        for &(x, y) in &[(10.0, 10.0), (60.0, 20.0), (70.0, 80.0), (15.0, 90.0)] {
+            qt.put(Point::new(x, y));
+        }
+
+        let path = std::env::temp_dir().join(format!("quadtree-{}.qdtr", std::process::id()));
+        qt.save(&path).unwrap();
+        let loaded: Quadtree<Point> = Quadtree::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let everything = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        assert_eq!(loaded.query(everything), qt.query(everything));
+        assert_eq!(
+            loaded.get(Point::new(60.2, 20.2)),
+            Some(&Point::new(60.0, 20.0))
+        );
+
+        let mut bytes = Vec::new();
+        qt.write_to(&mut bytes).unwrap();
+        bytes[4] = 99;
+        assert!(Quadtree::<Point>::read_from(&mut bytes.as_slice()).is_err());
+        assert!(Quadtree::<Point>::read_from(&mut &b"nope"[..]).is_err());
+    }
+
+    #[test]
+    fn test_save_and_replay_keep_options() {
+        let options = Options {
+            max_items: 2,
+            max_radius: 2.5,
+            reuse_nodes: true,
+            max_total_items: 3,
+            ..Default::default()
+        }
+        .snap_to_grid(1.0);
+        let mut qt = Quadtree::with_options(Rectangle::new(0.0, 0.0, 100.0, 100.0), options);
+        for &(x, y) in &[(10.0, 10.0), (60.0, 20.0), (70.0, 80.0)] {
+            qt.put(Point::new(x, y));
+        }
+
+        let mut bytes = Vec::new();
+        qt.write_to(&mut bytes).unwrap();
+        let mut loaded = Quadtree::<Point>::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(format!("{:?}", loaded), format!("{:?}", qt));
+        assert_eq!(
+            loaded.get(Point::new(60.3, 19.8)),
+            Some(&Point::new(60.0, 20.0))
+        );
+        loaded.put(Point::new(15.0, 90.0));
+        assert_eq!(loaded.len(), 3);
+        assert!(loaded.get(Point::new(10.0, 10.0)).is_none());
+
+        let path =
+            std::env::temp_dir().join(format!("quadtree-options-{}.qdtl", std::process::id()));
+        let logged = qt.with_log(&path).unwrap();
+        let replayed: Quadtree<Point> = Quadtree::replay(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(format!("{:?}", replayed), format!("{:?}", logged.tree()));
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_poisson_disk() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 50.0);
+        let mut rng = StdRng::seed_from_u64(11);
+        let points = poisson_disk(boundary, 5.0, &mut rng);
+
+        assert!(points.len() > 50);
+        for (i, a) in points.iter().enumerate() {
+            assert!(a.x() >= 0.0 && a.x() <= 100.0 && a.y() >= 0.0 && a.y() <= 50.0);
+            for b in &points[i + 1..] {
+                assert!((a.x() - b.x()).hypot(a.y() - b.y()) > 5.0);
+            }
+        }
+
+        let mut covered = Quadtree::new(boundary);
+        for &point in &points {
+            covered.put(point);
+        }
+        for x in (5..100).step_by(10) {
+            for y in (5..50).step_by(10) {
+                let probe = Point::new(f64::from(x), f64::from(y));
+                assert!(!covered.query_radius(probe, 10.0).is_empty());
+            }
+        }
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_from_csv() {
+        let data = "name,lon,lat\nfountain,10.5,20.0\nstation,80.0,75.25\nfar,500.0,1.0\n";
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+
+        let points = Quadtree::from_csv(data.as_bytes(), boundary, "lon", "lat").unwrap();
+        assert_eq!(points.len(), 2);
+        assert!(points.get(Point::new(80.0, 75.25)).is_some());
+
+        struct Place {
+            point: Point,
+            name: String,
+        }
+        impl Position for Place {
+            fn position(&self) -> Point {
+                self.point
+            }
+        }
+        let named =
+            Quadtree::from_csv_with(data.as_bytes(), boundary, "lon", "lat", |point, row| {
+                Place {
+                    point,
+                    name: row[0].to_string(),
+                }
+            })
+            .unwrap();
+        let names: Vec<&str> = named
+            .query(boundary)
+            .iter()
+            .map(|place| place.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["fountain", "station"]);
+
+        assert!(Quadtree::from_csv(data.as_bytes(), boundary, "x", "lat").is_err());
+        assert!(Quadtree::from_csv("x,y\n1,oops\n".as_bytes(), boundary, "x", "y").is_err());
+    }
+
+    #[cfg(feature = "shapefile")]
+    #[test]
+    fn test_from_shapefile() {
+        use shapefile::dbase::{FieldValue, Record, TableWriterBuilder};
+        use std::convert::TryInto;
+
+        let path = std::env::temp_dir().join(format!("quadtree-{}.shp", std::process::id()));
+        {
+            let table =
+                TableWriterBuilder::new().add_character_field("name".try_into().unwrap(), 20);
+            let mut writer = shapefile::Writer::from_path(&path, table).unwrap();
+            for &(x, y, name) in &[
+                (10.0, 20.0, "well"),
+                (70.0, 30.0, "mill"),
+                (150.0, 5.0, "far"),
+            ] {
+                let mut record = Record::default();
+                record.insert(
+                    "name".to_string(),
+                    FieldValue::Character(Some(name.to_string())),
+                );
+                writer
+                    .write_shape_and_record(&shapefile::Point::new(x, y), &record)
+                    .unwrap();
+            }
+        }
+
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let points = Quadtree::from_shapefile(&path, boundary).unwrap();
+        assert_eq!(points.len(), 2);
+        assert!(points.get(Point::new(70.0, 30.0)).is_some());
+
+        struct Site {
+            point: Point,
+            name: String,
+        }
+        impl Position for Site {
+            fn position(&self) -> Point {
+                self.point
+            }
+        }
+        let sites = Quadtree::from_shapefile_with(&path, boundary, |point, record| Site {
+            point,
+            name: match record.get("name") {
+                Some(FieldValue::Character(Some(name))) => name.clone(),
+                _ => String::new(),
+            },
+        })
+        .unwrap();
+        let names: Vec<&str> = sites
+            .query(boundary)
+            .iter()
+            .map(|site| site.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["well", "mill"]);
+
+        for extension in &["shp", "shx", "dbf"] {
+            std::fs::remove_file(path.with_extension(extension)).unwrap();
+        }
+    }
+
+    #[cfg(feature = "plotters")]
+    #[test]
+    fn test_plot() {
+        use plotters::prelude::{IntoDrawingArea, SVGBackend};
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Point::new(10.0, 10.0));
+        qt.put(Point::new(60.0, 70.0));
+
+        let mut svg = String::new();
+        {
+            let area = SVGBackend::with_string(&mut svg, (101, 101)).into_drawing_area();
+            qt.plot(&area, &PlotStyle::default()).unwrap();
+        }
+        assert_eq!(svg.matches("<rect").count(), 5);
+        assert_eq!(svg.matches("<circle").count(), 2);
+        assert!(svg.contains(r#"cx="60" cy="70""#));
+    }
+
+    #[cfg(feature = "egui")]
+    #[test]
+    fn test_egui_inspector() {
+        use egui::{Event, Modifiers, PointerButton, Pos2, RawInput, Rect};
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Point::new(10.0, 10.0));
+        qt.put(Point::new(30.0, 20.0));
+        qt.put(Point::new(80.0, 80.0));
+
+        let ctx = egui::Context::default();
+        let mut inspector = Inspector::new();
+        let mut frame = |events: Vec<Event>| {
+            let input = RawInput {
+                screen_rect: Some(Rect::from_min_size(Pos2::ZERO, egui::vec2(200.0, 200.0))),
+                events,
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::NONE)
+                    .show(ctx, |ui| inspector.show(ui, &qt));
+            });
+        };
+        let button = |pos, pressed| Event::PointerButton {
+            pos,
+            button: PointerButton::Secondary,
+            pressed,
+            modifiers: Modifiers::NONE,
+        };
+
+        frame(Vec::new());
+        frame(vec![
+            Event::PointerMoved(Pos2::new(0.0, 0.0)),
+            button(Pos2::new(0.0, 0.0), true),
+        ]);
+        frame(vec![Event::PointerMoved(Pos2::new(40.0, 30.0))]);
+        frame(vec![Event::PointerMoved(Pos2::new(80.0, 60.0))]);
+        frame(vec![button(Pos2::new(80.0, 60.0), false)]);
+
+        let query = inspector.query.expect("a secondary drag sets the query");
+        assert_eq!(qt.query(query).len(), 2);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_wasm_quadtree() {
+        let mut qt = WasmQuadtree::new(0.0, 0.0, 100.0, 100.0).unwrap();
+        assert!(qt.insert(10.0, 10.0, 7));
+        assert!(qt.insert(60.0, 60.0, 8));
+        assert!(!qt.insert(160.0, 60.0, 9));
+
+        assert_eq!(qt.query(0.0, 0.0, 50.0, 50.0), vec![7]);
+        assert_eq!(qt.len(), 2);
+        qt.clear();
+        assert!(qt.is_empty());
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn test_ffi() {
+        use quadtree::ffi::*;
+
+        assert!(quadtree_new(0.0, 0.0, 0.0, 10.0).is_null());
+        let tree = quadtree_new(0.0, 0.0, 100.0, 100.0);
+        unsafe {
+            assert!(quadtree_insert(tree, 10.0, 10.0, 1));
+            assert!(quadtree_insert(tree, 20.0, 20.0, 2));
+            assert!(quadtree_insert(tree, 80.0, 80.0, 3));
+            assert!(!quadtree_insert(tree, 180.0, 80.0, 4));
+            assert_eq!(quadtree_len(tree), 3);
+
+            let mut out = [0; 1];
+            let found = quadtree_query(tree, 0.0, 0.0, 50.0, 50.0, out.as_mut_ptr(), out.len());
+            assert_eq!((found, out), (2, [1]));
+            quadtree_free(tree);
+        }
+    }
+
+    #[cfg(feature = "bevy")]
+    #[test]
+    fn test_bevy_spatial_index() {
+        use bevy_app::App;
+        use bevy_transform::components::Transform;
+        use quadtree::bevy::{Indexed, SpatialIndex, SpatialIndexPlugin};
+
+        let mut app = App::new();
+        app.add_plugins(SpatialIndexPlugin::new(Rectangle::new(
+            0.0, 0.0, 100.0, 100.0,
+        )));
+        let near = app
+            .world_mut()
+            .spawn((Transform::from_xyz(10.0, 10.0, 0.0), Indexed))
+            .id();
+        let far = app
+            .world_mut()
+            .spawn((Transform::from_xyz(90.0, 90.0, 0.0), Indexed))
+            .id();
+        app.world_mut().spawn(Transform::from_xyz(20.0, 20.0, 0.0));
+        app.update();
+
+        let corner = Rectangle::new(0.0, 0.0, 50.0, 50.0);
+        assert_eq!(
+            app.world().resource::<SpatialIndex>().query(corner),
+            vec![near]
+        );
+
+        app.world_mut()
+            .get_mut::<Transform>(far)
+            .unwrap()
+            .translation
+            .x = 30.0;
+        app.world_mut()
+            .get_mut::<Transform>(far)
+            .unwrap()
+            .translation
+            .y = 30.0;
+        app.world_mut()
+            .get_mut::<Transform>(near)
+            .unwrap()
+            .translation
+            .x = 500.0;
+        app.update();
+        let index = app.world().resource::<SpatialIndex>();
+        assert_eq!(index.query(corner), vec![far]);
+        assert_eq!(index.len(), 1);
+
+        app.world_mut().despawn(far);
+        app.update();
+        assert!(app.world().resource::<SpatialIndex>().is_empty());
+    }
+
+    #[cfg(feature = "bevy")]
+    #[test]
+    fn test_bevy_indexed_added_later() {
+        use bevy_app::App;
+        use bevy_transform::components::Transform;
+        use quadtree::bevy::{Indexed, SpatialIndex, SpatialIndexPlugin};
+
+        let mut app = App::new();
+        app.add_plugins(SpatialIndexPlugin::new(Rectangle::new(
+            0.0, 0.0, 100.0, 100.0,
+        )));
+        let entity = app
+            .world_mut()
+            .spawn(Transform::from_xyz(20.0, 20.0, 0.0))
+            .id();
+        app.update();
+        assert!(app.world().resource::<SpatialIndex>().is_empty());
+
+        // The transform is unchanged; only the marker is new.
+        app.world_mut().entity_mut(entity).insert(Indexed);
+        app.update();
+        let index = app.world().resource::<SpatialIndex>();
+        assert_eq!(
+            index.query(Rectangle::new(0.0, 0.0, 50.0, 50.0)),
+            vec![entity]
+        );
+    }
+
+    #[test]
+    fn test_debug_draw() {
+        #[derive(Default)]
+        struct Recorder {
+            rects: Vec<(f64, f64, u8)>,
+            points: usize,
+        }
+        impl DebugDraw for Recorder {
+            fn draw_rect(&mut self, bounds: Rectangle, depth: u8) {
+                self.rects.push((bounds.x(), bounds.y(), depth));
+            }
+
+            fn draw_point(&mut self, _point: Point) {
+                self.points += 1;
+            }
+        }
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Point::new(10.0, 10.0));
+        qt.put(Point::new(90.0, 90.0));
+
+        let mut recorder = Recorder::default();
+        qt.debug_draw(&mut recorder);
+        assert_eq!(
+            recorder.rects,
+            vec![
+                (0.0, 0.0, 0),
+                (0.0, 0.0, 1),
+                (50.0, 0.0, 1),
+                (50.0, 50.0, 1),
+                (0.0, 50.0, 1)
+            ]
+        );
+        assert_eq!(recorder.points, 2);
+    }
+
+    #[test]
+    fn test_query_entries() {
+        let names = ["north", "south", "east"];
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Item::new(Point::new(50.0, 10.0), &names[0]));
+        qt.put(Item::new(Point::new(50.0, 90.0), &names[1]));
+        qt.put(Item::new(Point::new(90.0, 50.0), &names[2]));
+
+        let range = Rectangle::new(40.0, 0.0, 60.0, 60.0);
+        let entries: Vec<(Point, &str)> = qt
+            .query_entries(range)
+            .into_iter()
+            .map(|(point, item)| (point, **item))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (Point::new(50.0, 10.0), "north"),
+                (Point::new(90.0, 50.0), "east")
+            ]
+        );
+        let lazy: Vec<Point> = qt
+            .query_entries_iter(range)
+            .map(|(point, _)| point)
+            .collect();
+        let eager: Vec<Point> = qt.query(range).iter().map(|item| item.position()).collect();
+        assert_eq!(lazy, eager);
+    }
+
+    #[test]
+    fn test_positions_and_values() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        let labels = ["a", "b", "c"];
+        qt.put(Item::new(Point::new(80.0, 80.0), &labels[0]));
+        qt.put(Item::new(Point::new(10.0, 10.0), &labels[1]));
+        qt.put(Item::new(Point::new(80.0, 10.0), &labels[2]));
+
+        let positions: Vec<Point> = qt.positions().collect();
+        assert_eq!(
+            positions,
+            vec![
+                Point::new(10.0, 10.0),
+                Point::new(80.0, 10.0),
+                Point::new(80.0, 80.0)
+            ]
+        );
+        let values: Vec<&str> = qt.values().map(|item| **item).collect();
+        assert_eq!(values, vec!["b", "c", "a"]);
+        assert_eq!(qt.iter().count(), qt.len());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_iter() {
+        use rayon::prelude::*;
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 2,
+                ..Default::default()
+            },
+        );
+        for i in 0..50 {
+            qt.put(Point::new(f64::from(i * 2), f64::from(i)));
+        }
+
+        let sum: f64 = qt.par_iter().map(|point| point.x()).sum();
+        assert_eq!(sum, qt.positions().map(|point| point.x()).sum::<f64>());
+        assert_eq!(qt.par_iter().count(), 50);
+    }
+
+    #[test]
+    fn test_query_many() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 2,
+                ..Default::default()
+            },
+        );
+        for i in 0..20 {
+            qt.put(Point::new(f64::from(i * 5), f64::from(100 - i * 5)));
+        }
+
+        let rects = [
+            Rectangle::new(0.0, 0.0, 50.0, 50.0),
+            Rectangle::new(25.0, 25.0, 50.0, 50.0),
+            Rectangle::new(200.0, 200.0, 10.0, 10.0),
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+        ];
+        let batched = qt.query_many(&rects);
+        assert_eq!(batched.len(), rects.len());
+        for (rect, found) in rects.iter().zip(&batched) {
+            assert_eq!(found, &qt.query(*rect));
+        }
+        assert!(batched[2].is_empty());
+
+        #[cfg(feature = "rayon")]
+        assert_eq!(qt.par_query_many(&rects), batched);
+    }
+
+    #[test]
+    fn test_knn_batch() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 2,
+                ..Default::default()
+            },
+        );
+        for i in 0..30 {
+            qt.put(Point::new(f64::from(i * 3), f64::from((i * 7) % 100)));
+        }
+
+        let probes = [
+            Point::new(90.0, 90.0),
+            Point::new(5.0, 5.0),
+            Point::new(50.0, 20.0),
+            Point::new(-10.0, 40.0),
+        ];
+        let batched = qt.knn_batch(&probes, 3);
+        assert_eq!(batched.len(), probes.len());
+        for (probe, found) in probes.iter().zip(&batched) {
+            assert_eq!(found, &qt.knn(*probe, 3));
+        }
+        assert!(qt.knn_batch(&probes, 0).iter().all(Vec::is_empty));
+
+        #[cfg(feature = "rayon")]
+        assert_eq!(qt.par_knn_batch(&probes, 3), batched);
+    }
+
+    #[test]
+    fn test_extend_streaming() {
+        let options = Options {
+            max_items: 4,
+            max_depth: 5,
+            ..Default::default()
+        };
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let points = (0..500).map(|i| Point::new(f64::from(i % 110), f64::from((i * 37) % 100)));
+
+        let mut streamed = Quadtree::with_options(boundary, options);
+        streamed.extend_streaming(points.clone(), 64);
+        let mut one_by_one = Quadtree::with_options(boundary, options);
+        for point in points {
+            one_by_one.put(point);
+        }
+
+        assert_eq!(streamed.len(), one_by_one.len());
+        let dims = |r: Option<Rectangle>| r.map(|r| (r.x(), r.y(), r.width(), r.height()));
+        assert_eq!(
+            dims(streamed.items_bounds()),
+            dims(one_by_one.items_bounds())
+        );
+        let range = Rectangle::new(20.0, 30.0, 40.0, 25.0);
+        let mut found = streamed.query(range);
+        let mut expected = one_by_one.query(range);
+        found.sort();
+        expected.sort();
+        assert_eq!(found, expected);
+        assert_eq!(streamed.knn(Point::new(50.0, 50.0), 5).len(), 5);
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<S: Send + Sync>() {}
+        assert_send_sync::<Quadtree<Point>>();
+        assert_send_sync::<Quadtree<Point, Vec<u8>>>();
+        assert_send_sync::<KeyedQuadtree<String>>();
+        assert_send_sync::<DoubleBufferedQuadtree<Point>>();
+        assert_send_sync::<IntQuadtree<IntPoint>>();
+
+        let mut qt = Quadtree::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+        for i in 0..100 {
+            qt.put(Point::new(f64::from(i) + 0.5, f64::from(i) + 0.5));
+        }
+        let shared = std::sync::Arc::new(qt);
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let tree = std::sync::Arc::clone(&shared);
+                std::thread::spawn(move || {
+                    let start = f64::from(i * 25);
+                    tree.query(Rectangle::new(start, start, 25.0, 25.0)).len()
+                })
+            })
+            .collect();
+        let counts: Vec<usize> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(counts, vec![25, 25, 25, 25]);
+    }
+
+    #[test]
+    fn test_space_filling_curve_order() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        // One point per quadrant of a 2x2 grid, inserted out of order.
+        for &(x, y) in &[(75.0, 75.0), (25.0, 75.0), (75.0, 25.0), (25.0, 25.0)] {
+            qt.put(Point::new(x, y));
+        }
+
+        let zorder: Vec<Point> = qt.iter_zorder().copied().collect();
+        assert_eq!(
+            zorder,
+            vec![
+                Point::new(25.0, 25.0),
+                Point::new(75.0, 25.0),
+                Point::new(25.0, 75.0),
+                Point::new(75.0, 75.0)
+            ]
+        );
+        let hilbert: Vec<Point> = qt.iter_hilbert().copied().collect();
+        assert_eq!(
+            hilbert,
+            vec![
+                Point::new(25.0, 25.0),
+                Point::new(25.0, 75.0),
+                Point::new(75.0, 75.0),
+                Point::new(75.0, 25.0)
+            ]
+        );
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_mmap_quadtree() {
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let mut qt = Quadtree::with_options(
+            boundary,
+            Options {
+                max_items: 2,
+                ..Default::default()
+            },
+        );
+        for i in 0..50 {
+            qt.put(Point::new(
+                f64::from(i) * 1.9 + 0.5,
+                f64::from(i * 7 % 50) * 1.9 + 0.5,
+            ));
+        }
+
+        let path = std::env::temp_dir().join(format!("quadtree-{}.qdtm", std::process::id()));
+        MmapQuadtree::create(&path, &qt).unwrap();
+        let mapped = unsafe { MmapQuadtree::<Point>::open(&path) }.unwrap();
+        assert_eq!(mapped.len(), 50);
+        assert_eq!(mapped.bounds().width(), 100.0);
+
+        for range in [boundary, Rectangle::new(10.0, 30.0, 25.0, 40.0)] {
+            let mut expected: Vec<Point> = qt.query(range).into_iter().copied().collect();
+            let mut found = mapped.query(range).unwrap();
+            expected.sort_by(|a, b| a.x().total_cmp(&b.x()));
+            found.sort_by(|a, b| a.x().total_cmp(&b.x()));
+            assert_eq!(found, expected);
+        }
+        drop(mapped);
+
+        let built = path.with_extension("built.qdtm");
+        let options = Options {
+            max_items: 2,
+            ..Default::default()
+        };
+        let points = qt.iter().copied().chain(Some(Point::new(150.0, 1.0)));
+        MmapQuadtree::build(&built, boundary, options, points).unwrap();
+        let mapped = unsafe { MmapQuadtree::<Point>::open(&built) }.unwrap();
+        assert_eq!(mapped.len(), 50);
+        for range in [boundary, Rectangle::new(10.0, 30.0, 25.0, 40.0)] {
+            let mut expected: Vec<Point> = qt.query(range).into_iter().copied().collect();
+            let mut found = mapped.query(range).unwrap();
+            expected.sort_by(|a, b| a.x().total_cmp(&b.x()));
+            found.sort_by(|a, b| a.x().total_cmp(&b.x()));
+            assert_eq!(found, expected);
+        }
+        drop(mapped);
+        std::fs::remove_file(&built).unwrap();
+
+        // Point the root at itself as its own first child.
+        let mut bytes = std::fs::read(&path).unwrap();
+        let mut table = [0; 8];
+        table.copy_from_slice(&bytes[16..24]);
+        let root = u64::from_le_bytes(table) as usize;
+        bytes[root + 32..root + 40].copy_from_slice(&0u64.to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        let mapped = unsafe { MmapQuadtree::<Point>::open(&path) }.unwrap();
+        assert!(mapped.query(boundary).is_err());
+        drop(mapped);
+
+        std::fs::write(&path, b"QDTR").unwrap();
+        assert!(unsafe { MmapQuadtree::<Point>::open(&path) }.is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_paged_quadtree() {
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let options = Options {
+            max_items: 64,
+            max_depth: 8,
+            ..Default::default()
+        };
+        let path = std::env::temp_dir().join(format!("quadtree-{}.qdtp", std::process::id()));
+        let mut paged = PagedQuadtree::create(&path, boundary, options, 4).unwrap();
+        let mut qt = Quadtree::with_options(boundary, options);
+        for i in 0..2000 {
+            let point = Point::new(f64::from(i % 97) + 0.25, f64::from(i % 89) + 0.5);
+            assert!(paged.put(point).unwrap());
+            qt.put(point);
+        }
+        assert!(!paged.put(Point::new(150.0, 1.0)).unwrap());
+        assert_eq!(paged.len(), 2000);
+        assert!(paged.cached_pages() <= 4);
+
+        let range = Rectangle::new(20.0, 30.0, 15.0, 40.0);
+        let sorted = |mut points: Vec<Point>| {
+            points.sort_by(|a, b| (a.x(), a.y()).partial_cmp(&(b.x(), b.y())).unwrap());
+            points
+        };
+        let expected = sorted(qt.query(range).into_iter().copied().collect());
+        assert_eq!(sorted(paged.query(range).unwrap()), expected);
+        assert_eq!(
+            paged.remove(Point::new(3.25, 3.5)).unwrap(),
+            Some(Point::new(3.25, 3.5))
+        );
+        paged.flush().unwrap();
+        drop(paged);
+
+        let mut reopened = PagedQuadtree::<Point>::open(&path, 8).unwrap();
+        assert_eq!(reopened.len(), 1999);
+        assert_eq!(reopened.bounds().unwrap().width(), 100.0);
+        assert_eq!(sorted(reopened.query(range).unwrap()), expected);
+        assert_eq!(
+            reopened
+                .query(Rectangle::new(3.0, 3.0, 0.5, 0.6))
+                .unwrap()
+                .len(),
+            0
+        );
+        drop(reopened);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_paged_quadtree_failed_split_keeps_items() {
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let options = Options {
+            max_items: 1000,
+            max_depth: 1,
+            ..Default::default()
+        };
+        let path = std::env::temp_dir().join(format!("quadtree-{}-full.qdtp", std::process::id()));
+        let mut paged = PagedQuadtree::create(&path, boundary, options, 2).unwrap();
+        let mut stored = 0;
+        while let Ok(inserted) = paged.put(Point::new(10.0, 10.0)) {
+            assert!(inserted);
+            stored += 1;
+        }
+        assert!(stored > 0);
+        assert_eq!(paged.len(), stored);
+        assert_eq!(paged.query(boundary).unwrap().len(), stored);
+        // The full root splits once an item lands in another quadrant.
+        assert!(paged.put(Point::new(90.0, 90.0)).unwrap());
+        assert_eq!(paged.len(), stored + 1);
+        paged.flush().unwrap();
+        drop(paged);
+
+        let mut reopened = PagedQuadtree::<Point>::open(&path, 2).unwrap();
+        let points = reopened
+            .query(Rectangle::new(0.0, 0.0, 50.0, 50.0))
+            .unwrap();
+        assert_eq!(points.len(), stored);
+        drop(reopened);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_log_and_replay() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 2,
+                epsilon: 0.5,
+                ..Default::default()
+            },
+        );
+        qt.put(Point::new(5.0, 5.0));
+
+        let path = std::env::temp_dir().join(format!("quadtree-{}.qdtl", std::process::id()));
+        let mut logged = qt.with_log(&path).unwrap();
+        for &(x, y) in &[(10.0, 10.0), (60.0, 20.0), (70.0, 80.0), (15.0, 90.0)] {
+            logged.put(Point::new(x, y)).unwrap();
+        }
+        assert_eq!(
+            logged.remove(Point::new(60.2, 20.0)).unwrap(),
+            Some(Point::new(60.0, 20.0))
+        );
+        assert_eq!(logged.remove(Point::new(1.0, 1.0)).unwrap(), None);
+        logged.sync().unwrap();
+        let expected = logged.into_inner();
+
+        let everything = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let replayed: Quadtree<Point> = Quadtree::replay(&path).unwrap();
+        assert_eq!(replayed.len(), 4);
+        assert_eq!(replayed.query(everything), expected.query(everything));
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.extend_from_slice(&[1, 0, 0]);
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(Quadtree::<Point>::replay(&path).unwrap().len(), 4);
+        bytes[0] = b'X';
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(Quadtree::<Point>::replay(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_delta_sync() {
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let options = Options {
+            max_items: 2,
+            ..Default::default()
+        };
+        let mut server = VersionedQuadtree::with_options(boundary, options);
+        let mut replica = Quadtree::with_options(boundary, options);
+        for &(x, y) in &[(10.0, 10.0), (60.0, 20.0), (70.0, 80.0), (70.0, 80.0)] {
+            server.put(Point::new(x, y));
+        }
+        server.put(Point::new(500.0, 0.0));
+        assert_eq!(server.version(), Version(4));
+
+        let delta = server.export_delta(Version::default());
+        assert_eq!(delta.changes().len(), 3);
+        replica.apply_delta(&delta);
+        assert_eq!(replica.len(), 4);
+        let synced = delta.version();
+
+        server.remove(Point::new(70.0, 80.0));
+        server.put(Point::new(15.0, 90.0));
+        let delta = server.export_delta(synced);
+        assert_eq!(delta.changes().len(), 2);
+
+        let mut bytes = Vec::new();
+        delta.write_to(&mut bytes).unwrap();
+        let delta = Delta::<Point>::read_from(&mut bytes.as_slice()).unwrap();
+        replica.apply_delta(&delta);
+
+        assert_eq!(replica.len(), server.tree().len());
+        assert_eq!(replica.entries_at(Point::new(70.0, 80.0)).len(), 1);
+        assert!(replica.get(Point::new(15.0, 90.0)).is_some());
+        assert!(server.export_delta(delta.version()).is_empty());
+    }
+
+    #[test]
+    fn test_delta_sync_records_evictions() {
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let mut server = VersionedQuadtree::with_options(
+            boundary,
+            Options {
+                max_total_items: 2,
+                ..Default::default()
+            },
+        );
+        let mut replica = Quadtree::new(boundary);
+        server.put(Point::new(10.0, 10.0));
+        server.put(Point::new(20.0, 20.0));
+        replica.apply_delta(&server.export_delta(Version::default()));
+        let synced = server.version();
+
+        server.put(Point::new(30.0, 30.0));
+        let delta = server.export_delta(synced);
+        assert_eq!(delta.changes().len(), 2);
+        replica.apply_delta(&delta);
+        assert_eq!(replica.len(), 2);
+        assert!(replica.get(Point::new(10.0, 10.0)).is_none());
+    }
+
+    #[test]
+    fn test_query_top_by() {
+        struct Venue {
+            point: Point,
+            popularity: u32,
+        }
+        impl Position for Venue {
+            fn position(&self) -> Point {
+                self.point
+            }
+        }
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 4,
+                ..Default::default()
+            },
+        );
+        for i in 0..100 {
+            qt.put(Venue {
+                point: Point::new(
+                    f64::from(i % 10) * 10.0 + 1.0,
+                    f64::from(i / 10) * 10.0 + 1.0,
+                ),
+                popularity: (i * 37) % 100,
+            });
+        }
+
+        let viewport = Rectangle::new(0.0, 0.0, 50.0, 50.0);
+        let top = qt.query_top_by(viewport, 3, |v| f64::from(v.popularity));
+        let mut expected: Vec<u32> = qt.query(viewport).iter().map(|v| v.popularity).collect();
+        expected.sort_unstable_by(|a, b| b.cmp(a));
+        let found: Vec<u32> = top.iter().map(|v| v.popularity).collect();
+        assert_eq!(found, expected[..3]);
+
+        assert!(qt
+            .query_top_by(viewport, 0, |v| f64::from(v.popularity))
+            .is_empty());
+        assert_eq!(qt.query_top_by(viewport, 1000, |_| 0.0).len(), 25);
+
+        let mut ranked = AggregateQuadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 4,
+                ..Default::default()
+            },
+            Max(|v: &Venue| f64::from(v.popularity)),
+        );
+        for venue in qt.iter() {
+            ranked.put(Venue {
+                point: venue.point,
+                popularity: venue.popularity,
+            });
+        }
+        let pruned: Vec<u32> = ranked
+            .query_top(viewport, 3)
+            .iter()
+            .map(|v| v.popularity)
+            .collect();
+        assert_eq!(pruned, expected[..3]);
+        assert!(ranked.query_top(viewport, 0).is_empty());
+        assert_eq!(ranked.query_top(viewport, 1000).len(), 25);
+    }
+
+    #[test]
+    fn test_aggregate_in_region() {
+        #[derive(Debug, PartialEq)]
+        struct Sale {
+            point: Point,
+            amount: f64,
+        }
+        impl Position for Sale {
+            fn position(&self) -> Point {
+                self.point
+            }
+        }
+
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let options = Options {
+            max_items: 3,
+            ..Default::default()
+        };
+        let mut maintained =
+            AggregateQuadtree::with_options(boundary, options, Sum(|s: &Sale| s.amount));
+        let mut plain = Quadtree::with_options(boundary, options);
+        for i in 0..60 {
+            let point = Point::new(f64::from(i % 8) * 12.0 + 2.0, f64::from(i / 8) * 12.0 + 2.0);
+            let amount = f64::from(i % 7);
+            maintained.put(Sale { point, amount });
+            plain.put(Sale { point, amount });
+        }
+
+        let range = Rectangle::new(10.0, 10.0, 50.0, 45.0);
+        let expected: f64 = plain.query(range).iter().map(|s| s.amount).sum();
+        assert_eq!(maintained.aggregate_in_region(range), expected);
+        assert_eq!(
+            plain.aggregate_in_region(range, &Sum(|s: &Sale| s.amount)),
+            expected
+        );
+        assert_eq!(
+            plain.aggregate_in_region(range, &Count),
+            plain.query(range).len()
+        );
+        assert_eq!(
+            plain.aggregate_in_region(range, &Max(|s: &Sale| s.amount)),
+            Some(6.0)
+        );
+        assert_eq!(
+            plain.aggregate_in_region(
+                Rectangle::new(-9.0, -9.0, 1.0, 1.0),
+                &Min(|s: &Sale| s.amount)
+            ),
+            None
+        );
+
+        let removed = maintained.remove(Point::new(14.0, 14.0)).unwrap();
+        assert_eq!(maintained.len(), 59);
+        assert_eq!(
+            maintained.aggregate_in_region(range),
+            expected - removed.amount
+        );
+        assert_eq!(
+            maintained.aggregate(),
+            maintained.aggregate_in_region(boundary)
+        );
+
+        let mut stacked =
+            AggregateQuadtree::with_options(boundary, options, Sum(|s: &Sale| s.amount));
+        let point = Point::new(40.0, 40.0);
+        for amount in 1..=6 {
+            stacked.put(Sale {
+                point,
+                amount: f64::from(amount),
+            });
+        }
+        assert_eq!(stacked.remove(point).unwrap().amount, 1.0);
+        let amounts: Vec<f64> = stacked.query(boundary).iter().map(|s| s.amount).collect();
+        assert_eq!(amounts, vec![2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(stacked.aggregate(), 20.0);
+        while stacked.remove(point).is_some() {}
+        assert!(stacked.is_empty());
+        assert_eq!(stacked.aggregate_in_region(boundary), 0.0);
+    }
+
+    #[test]
+    fn test_histogram_in_region() {
+        struct Shop {
+            point: Point,
+            category: usize,
+        }
+        impl Position for Shop {
+            fn position(&self) -> Point {
+                self.point
+            }
+        }
+
+        let mut qt = Quadtree::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+        for i in 0..40 {
+            qt.put(Shop {
+                point: Point::new(f64::from(i) * 2.5, 50.0),
+                category: i as usize % 5,
+            });
+        }
+
+        let viewport = Rectangle::new(0.0, 0.0, 24.0, 100.0);
+        assert_eq!(
+            qt.histogram_in_region(viewport, |s| s.category, 5),
+            vec![2, 2, 2, 2, 2]
+        );
+        assert_eq!(
+            qt.histogram_in_region(viewport, |s| s.category, 3),
+            vec![2, 2, 2]
+        );
+        assert!(qt
+            .histogram_in_region(viewport, |s| s.category, 0)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_group_by_grid() {
+        let mut qt = Quadtree::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+        for &(x, y) in &[
+            (1.0, 1.0),
+            (4.0, 9.0),
+            (15.0, 2.0),
+            (12.0, 18.0),
+            (20.0, 20.0),
+            (50.0, 50.0),
+        ] {
+            qt.put(Point::new(x, y));
+        }
+
+        let cells = qt.group_by_grid(Rectangle::new(0.0, 0.0, 20.0, 20.0), 10.0);
+        let summary: Vec<((f64, f64), usize)> = cells
+            .iter()
+            .map(|(cell, items)| ((cell.x(), cell.y()), items.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![((0.0, 0.0), 2), ((10.0, 0.0), 1), ((10.0, 10.0), 2)]
+        );
+        assert!(qt
+            .group_by_grid(Rectangle::new(60.0, 60.0, 5.0, 5.0), 1.0)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_rasterize() {
+        let mut qt = Quadtree::new(Rectangle::new(0.0, 0.0, 30.0, 20.0));
+        for &(x, y) in &[(1.0, 1.0), (2.0, 3.0), (25.0, 5.0), (15.0, 20.0)] {
+            qt.put(Point::new(x, y));
+        }
+
+        let cells = qt.rasterize(Rectangle::new(0.0, 0.0, 30.0, 20.0), 3, 2);
+        assert_eq!(cells, vec![true, false, true, false, true, false]);
+        assert!(qt
+            .rasterize(Rectangle::new(0.0, 0.0, 30.0, 20.0), 3, 0)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_radius_queries() {
+        #[derive(Debug)]
+        struct Unit {
+            point: Point,
+            radius: f64,
+        }
+        impl Position for Unit {
+            fn position(&self) -> Point {
+                self.point
+            }
+        }
+        impl Radius for Unit {
+            fn radius(&self) -> f64 {
+                self.radius
+            }
+        }
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                max_radius: 10.0,
+                ..Default::default()
+            },
+        );
+        for &(x, y, radius) in &[
+            (10.0, 10.0, 10.0),
+            (22.0, 10.0, 4.0),
+            (60.0, 60.0, 1.0),
+            (62.0, 60.0, 1.0),
+        ] {
+            qt.put(Unit {
+                point: Point::new(x, y),
+                radius,
+            });
+        }
+
+        let hits = qt.query_circle(Point::new(10.0, 24.0), 4.5);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].radius, 10.0);
+        assert!(qt.query_radius(Point::new(10.0, 24.0), 4.5).is_empty());
+
+        let crossed = qt.query_segment(Point::new(0.0, 19.5), Point::new(30.0, 19.5));
+        assert_eq!(crossed.len(), 1);
+        assert_eq!(
+            qt.query_segment(Point::new(22.0, 0.0), Point::new(22.0, 7.0))
+                .len(),
+            1
+        );
+
+        let pairs: Vec<(f64, f64)> = qt
+            .overlapping_pairs()
+            .iter()
+            .map(|(a, b)| (a.point.x(), b.point.x()))
+            .collect();
+        assert_eq!(pairs.len(), 2);
+        assert!(pairs.contains(&(10.0, 22.0)) || pairs.contains(&(22.0, 10.0)));
+        assert!(pairs.contains(&(60.0, 62.0)) || pairs.contains(&(62.0, 60.0)));
+    }
+
+    #[test]
+    fn test_radius_queries_track_reach() {
+        struct Tower {
+            point: Point,
+            radius: f64,
+        }
+        impl Position for Tower {
+            fn position(&self) -> Point {
+                self.point
+            }
+
+            fn reach(&self) -> f64 {
+                self.radius
+            }
+        }
+        impl Radius for Tower {
+            fn radius(&self) -> f64 {
+                self.radius
+            }
+        }
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                max_radius: 1.0,
+                ..Default::default()
+            },
+        );
+        for &(x, radius) in &[(10.0, 1.0), (80.0, 40.0), (90.0, 1.0)] {
+            qt.put(Tower {
+                point: Point::new(x, 80.0),
+                radius,
+            });
+        }
+        let hits = qt.query_circle(Point::new(45.0, 80.0), 1.0);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].radius, 40.0);
+        assert_eq!(
+            qt.query_segment(Point::new(40.0, 0.0), Point::new(40.0, 100.0))
+                .len(),
+            1
+        );
+        assert_eq!(
+            qt.raycast(Point::new(0.0, 40.0), (0.0, 1.0), 100.0)
+                .map(|(_, tower)| tower.radius),
+            None
+        );
+        assert_eq!(
+            qt.raycast(Point::new(45.0, 0.0), (0.0, 1.0), 100.0)
+                .map(|(_, tower)| tower.radius),
+            Some(40.0)
+        );
+
+        qt.remove(Point::new(80.0, 80.0));
+        assert!(qt.query_circle(Point::new(45.0, 80.0), 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_raycast() {
+        struct Target {
+            point: Point,
+            radius: f64,
+        }
+        impl Position for Target {
+            fn position(&self) -> Point {
+                self.point
+            }
+        }
+        impl Radius for Target {
+            fn radius(&self) -> f64 {
+                self.radius
+            }
+        }
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                max_radius: 2.0,
+                ..Default::default()
+            },
+        );
+        for &(x, y, radius) in &[
+            (80.0, 10.0, 2.0),
+            (30.0, 11.0, 1.0),
+            (50.0, 40.0, 2.0),
+            (60.0, 10.0, 1.0),
+        ] {
+            qt.put(Target {
+                point: Point::new(x, y),
+                radius,
+            });
+        }
+
+        let origin = Point::new(0.0, 10.0);
+        let hits = qt.raycast_all(origin, (2.0, 0.0), 100.0);
+        let ts: Vec<f64> = hits.iter().map(|(t, _)| *t).collect();
+        assert_eq!(ts, vec![15.0, 29.5, 39.0]);
+        assert_eq!(hits[2].1.point.x(), 80.0);
+
+        let (t, first) = qt.raycast(origin, (2.0, 0.0), 100.0).unwrap();
+        assert_eq!(t, 15.0);
+        assert_eq!(first.point.x(), 30.0);
+        assert!(qt.raycast(origin, (2.0, 0.0), 10.0).is_none());
+        assert_eq!(qt.raycast_all(origin, (2.0, 0.0), 35.0).len(), 2);
+        assert_eq!(
+            qt.raycast(Point::new(50.0, 41.0), (0.0, 1.0), 5.0)
+                .unwrap()
+                .0,
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_segment_clipping() {
+        let rect = Rectangle::new(0.0, 0.0, 10.0, 10.0);
+        let (a, b) = rect
+            .clip_segment(Point::new(-5.0, 5.0), Point::new(15.0, 5.0))
+            .unwrap();
+        assert_eq!((a, b), (Point::new(0.0, 5.0), Point::new(10.0, 5.0)));
+        let (a, b) = rect
+            .clip_segment(Point::new(2.0, 2.0), Point::new(20.0, 20.0))
+            .unwrap();
+        assert_eq!((a, b), (Point::new(2.0, 2.0), Point::new(10.0, 10.0)));
+        assert!(rect
+            .clip_segment(Point::new(-5.0, 12.0), Point::new(15.0, 12.0))
+            .is_none());
+        assert!(rect
+            .clip_segment(Point::new(-5.0, -1.0), Point::new(-1.0, 5.0))
+            .is_none());
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Point::new(10.0, 10.0));
+        qt.put(Point::new(60.0, 10.0));
+        qt.put(Point::new(30.0, 30.0));
+        assert_eq!(
+            qt.clip_segment(Point::new(50.0, -50.0), Point::new(50.0, 150.0)),
+            Some((Point::new(50.0, 0.0), Point::new(50.0, 100.0)))
+        );
+
+        let cells: Vec<(f64, f64, f64)> = qt
+            .cells_crossed_by(Point::new(1.0, 20.0), Point::new(99.0, 70.0))
+            .iter()
+            .map(|cell| (cell.x(), cell.y(), cell.width()))
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                (0.0, 0.0, 25.0),
+                (0.0, 25.0, 25.0),
+                (25.0, 25.0, 25.0),
+                (50.0, 0.0, 50.0),
+                (50.0, 50.0, 50.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snap_to_grid() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            }
+            .snap_to_grid(0.5),
+        );
+        qt.put(Point::new(0.1 + 0.2, 10.0));
+        qt.put(Point::new(24.9999999, 25.0000001));
+        qt.put(Point::new(60.0, 60.0));
+
+        assert_eq!(
+            qt.get(Point::new(0.3, 10.0)),
+            Some(&Point::new(0.1 + 0.2, 10.0))
+        );
+        assert!(qt.get(Point::new(0.8, 10.0)).is_none());
+        assert_eq!(qt.entries_at(Point::new(25.1, 24.9)).len(), 1);
+        assert_eq!(
+            qt.remove(Point::new(25.0, 25.0)),
+            Some(Point::new(24.9999999, 25.0000001))
+        );
+        assert_eq!(qt.len(), 2);
+
+        let mut snapped = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options::default().snap_to_grid(0.5),
+        );
+        snapped.put_snapped(Point::new(0.1 + 0.2, 10.0));
+        snapped.put_snapped(Point::new(24.9999999, 25.0000001));
+        let found = snapped.query(Rectangle::new(0.0, 0.0, 0.5, 11.0));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].position(), Point::new(0.5, 10.0));
+        assert_eq!(**found[0], Point::new(0.1 + 0.2, 10.0));
+        assert_eq!(
+            snapped
+                .nearest(Point::new(26.0, 26.0))
+                .map(|s| s.position()),
+            Some(Point::new(25.0, 25.0))
+        );
+        let removed = snapped.remove(Point::new(25.0, 25.0)).unwrap();
+        assert_eq!(removed.into_inner(), Point::new(24.9999999, 25.0000001));
+    }
+
+    #[test]
+    #[should_panic(expected = "grid cell")]
+    fn test_snap_to_grid_rejects_negative_cells() {
+        Options::default().snap_to_grid(-1.0);
+    }
+
+    #[test]
+    fn test_query_transformed() {
+        let mut qt = Quadtree::new(Rectangle::new(-10.0, -10.0, 20.0, 20.0));
+        qt.put(Point::new(1.0, 0.0));
+        qt.put(Point::new(5.0, 0.0));
+        qt.put(Point::new(0.0, 5.0));
+
+        let to_world =
+            Affine::rotation(std::f64::consts::FRAC_PI_2).then(&Affine::translation(100.0, 100.0));
+        let view = qt.transformed(to_world);
+        assert_eq!(
+            view.query(Rectangle::new(99.0, 100.5, 2.0, 2.0)),
+            vec![&Point::new(1.0, 0.0)]
+        );
+        assert_eq!(
+            qt.query_transformed(Rectangle::new(94.0, 99.0, 2.0, 2.0), to_world),
+            vec![&Point::new(0.0, 5.0)]
+        );
+        assert!(view.query(Rectangle::new(0.0, 0.0, 10.0, 10.0)).is_empty());
+
+        let back = view.to_local(view.to_world(Point::new(5.0, 0.0)));
+        assert!((back.x() - 5.0).abs() < 1e-9 && back.y().abs() < 1e-9);
+        assert_eq!(view.len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "invertible")]
+    fn test_transformed_rejects_singular_transforms() {
+        let qt: Quadtree<Point> = Quadtree::new(Rectangle::new(0.0, 0.0, 1.0, 1.0));
+        qt.transformed(Affine::scale(0.0, 1.0));
+    }
+
+    #[test]
+    fn test_spatial_index_backends_agree() {
+        fn exercise<I: SpatialIndex<Point>>(mut index: I) -> (usize, Vec<(f64, f64)>, Point) {
+            index.extend(
+                (0..20).map(|i| Point::new(f64::from(i) * 5.0, f64::from(i % 4) * 20.0 + 1.0)),
+            );
+            index.put(Point::new(200.0, 0.0));
+            index.remove(Point::new(10.0, 41.0));
+            let mut found: Vec<(f64, f64)> = index
+                .query(Rectangle::new(0.0, 0.0, 30.0, 50.0))
+                .iter()
+                .map(|p| (p.x(), p.y()))
+                .collect();
+            found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let nearest = *index.nearest(Point::new(52.0, 58.0)).unwrap();
+            (index.len(), found, nearest)
+        }
+
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let plain = exercise(Quadtree::<Point>::new(boundary));
+        assert_eq!(plain, exercise(LinearQuadtree::new(boundary)));
+        assert_eq!(plain, exercise(UniformGrid::new(boundary, 8.0)));
+        assert_eq!(plain, exercise(HybridIndex::new(boundary, 30.0)));
+        assert_eq!(plain.0, 19);
+        assert_eq!(
+            plain.1,
+            vec![
+                (0.0, 1.0),
+                (5.0, 21.0),
+                (20.0, 1.0),
+                (25.0, 21.0),
+                (30.0, 41.0)
+            ]
+        );
+        assert_eq!(plain.2, Point::new(55.0, 61.0));
+    }
+
+    #[cfg(feature = "bumpalo")]
+    #[test]
+    fn test_arena_quadtree() {
+        use bumpalo::Bump;
+
+        let mut bump = Bump::new();
+        for round in 0..3 {
+            let mut tree = ArenaQuadtree::with_options_in(
+                Rectangle::new(0.0, 0.0, 100.0, 100.0),
+                Options {
+                    max_items: 2,
+                    ..Default::default()
+                },
+                &bump,
+            );
+            for i in 0..50 {
+                tree.put(Point::new(f64::from(i) * 2.0, f64::from(round) * 10.0));
+            }
+            tree.put(Point::new(200.0, 0.0));
+            assert_eq!(tree.len(), 50);
+            assert_eq!(tree.query(Rectangle::new(0.0, 0.0, 10.0, 100.0)).len(), 6);
+            tree.clear();
+            assert!(tree.is_empty());
+            drop(tree);
+            bump.reset();
+        }
+    }
+
+    #[test]
+    fn test_reuse_nodes_across_clear() {
+        let points: Vec<Point> = (0..40)
+            .map(|i| Point::new(f64::from(i * 7 % 100), f64::from(i * 13 % 100)))
+            .collect();
+        let build = |reuse_nodes| {
+            let mut qt = Quadtree::with_options(
+                Rectangle::new(0.0, 0.0, 100.0, 100.0),
+                Options {
+                    max_items: 2,
+                    max_depth: 4,
+                    collect_metrics: true,
+                    reuse_nodes,
+                    ..Default::default()
+                },
+            );
+            for &point in &points {
+                qt.put(point);
+            }
+            qt
+        };
+
+        let mut qt = build(true);
+        let first = qt.metrics().unwrap();
+        let expected = qt.query(Rectangle::new(10.0, 10.0, 50.0, 50.0)).len();
+        for _ in 0..3 {
+            qt.clear();
+            qt.reset_metrics();
+            for &point in &points {
+                qt.put(point);
+            }
+            let metrics = qt.metrics().unwrap();
+            assert_eq!(metrics.subdivisions, first.subdivisions);
+            assert_eq!(metrics.nodes_reused, 4 * first.subdivisions);
+            assert_eq!(qt.len(), points.len());
+            assert_eq!(
+                qt.query(Rectangle::new(10.0, 10.0, 50.0, 50.0)).len(),
+                expected
+            );
+        }
+
+        for &point in &points[2..] {
+            qt.remove(point);
+        }
+        qt.reset_metrics();
+        for &point in &points[2..] {
+            qt.put(point);
+        }
+        assert!(qt.metrics().unwrap().nodes_reused > 0);
+        assert_eq!(qt.len(), points.len());
+
+        let mut plain = build(false);
+        plain.clear();
+        for &point in &points {
+            plain.put(point);
+        }
+        assert_eq!(plain.metrics().unwrap().nodes_reused, 0);
+    }
+
+    #[test]
+    fn test_query_scratch() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 2,
+                ..Default::default()
+            },
+        );
+        for i in 0..50 {
+            qt.put(Point::new(f64::from(i * 2), f64::from(i * 2)));
+        }
+
+        let mut scratch = QueryScratch::new();
+        let range = Rectangle::new(10.0, 10.0, 30.0, 30.0);
+        let items = qt.query_scratch(range, &mut scratch);
+        assert_eq!(items.to_vec(), qt.query(range));
+        let capacity = scratch.capacity();
+        assert!(capacity >= 16);
+
+        for i in 0..10 {
+            let range = Rectangle::new(0.0, 0.0, f64::from(i * 2 + 1), f64::from(i * 2 + 1));
+            let items = qt.query_scratch(range, &mut scratch);
+            assert_eq!(items.len(), i as usize + 1);
+        }
+        assert_eq!(scratch.capacity(), capacity);
+    }
+
+    #[test]
+    fn test_flatten() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Point::new(10.0, 10.0));
+        qt.put(Point::new(90.0, 10.0));
+        qt.put(Point::new(20.0, 20.0));
+
+        let flat = qt.flatten();
+        assert_eq!(flat.nodes.len(), 13);
+        assert_eq!(flat.nodes[0].bounds, [0.0, 0.0, 100.0, 100.0]);
+        assert_eq!(flat.nodes[0].first_child, 1);
+        assert_eq!(flat.nodes[0].item_count, 0);
+        assert_eq!(flat.nodes[2].bounds, [50.0, 0.0, 100.0, 50.0]);
+        assert_eq!(flat.nodes[2].first_child, NO_CHILDREN);
+        assert_eq!(flat.nodes[1].first_child, 5);
+        assert_eq!(flat.nodes[5].first_child, 9);
+
+        let positions: Vec<(f32, f32)> = flat.items.iter().map(|item| (item.x, item.y)).collect();
+        let expected: Vec<(f32, f32)> = qt.iter().map(|p| (p.x() as f32, p.y() as f32)).collect();
+        assert_eq!(positions, expected);
+        for node in &flat.nodes {
+            for item in
+                &flat.items[node.item_start as usize..(node.item_start + node.item_count) as usize]
+            {
+                assert!(item.x >= node.bounds[0] && item.x <= node.bounds[2]);
+                assert!(item.y >= node.bounds[1] && item.y <= node.bounds[3]);
+            }
+        }
+        assert_eq!(flat.node_bytes().len(), 13 * 28);
+        assert_eq!(flat.item_bytes()[..8], [0, 0, 32, 65, 0, 0, 32, 65]);
+    }
+
+    #[test]
+    fn test_rebuilding_quadtree() {
+        let mut qt = RebuildingQuadtree::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+        let reader = qt.reader();
+        for i in 0..100 {
+            qt.put(Point::new(f64::from(i), f64::from(i)));
+        }
+        assert_eq!(qt.pending(), 100);
+        assert!(reader.snapshot().is_empty());
+
+        let before = reader.snapshot();
+        assert!(qt.rebuild());
+        assert_eq!(qt.pending(), 0);
+        qt.wait();
+        assert!(before.is_empty());
+        assert_eq!(reader.snapshot().len(), 100);
+
+        qt.remove(Point::new(10.0, 10.0));
+        qt.put(Point::new(5.5, 5.5));
+        let handle = {
+            let reader = reader.clone();
+            std::thread::spawn(move || {
+                let tree = reader.snapshot();
+                let len = tree.len();
+                assert_eq!(len, 100);
+                assert_eq!(
+                    tree.query(Rectangle::new(0.0, 0.0, 100.0, 100.0)).len(),
+                    len
+                );
+            })
+        };
+        assert!(qt.rebuild());
+        handle.join().unwrap();
+        qt.wait();
+        let tree = qt.snapshot();
+        assert_eq!(tree.len(), 100);
+        assert!(tree.get(Point::new(10.0, 10.0)).is_none());
+        assert!(tree.get(Point::new(5.5, 5.5)).is_some());
+        drop((tree, before));
+
+        // Each rebuild now updates the tree published two rebuilds ago.
+        qt.remove(Point::new(20.0, 20.0));
+        assert!(qt.rebuild());
+        qt.wait();
+        qt.remove(Point::new(30.0, 30.0));
+        qt.put(Point::new(30.0, 30.0));
+        qt.remove(Point::new(40.5, 40.5));
+        qt.put(Point::new(40.5, 40.5));
+        assert!(qt.rebuild());
+        qt.wait();
+        let tree = qt.snapshot();
+        assert_eq!(tree.len(), 100);
+        assert!(tree.get(Point::new(10.0, 10.0)).is_none());
+        assert!(tree.get(Point::new(20.0, 20.0)).is_none());
+        assert!(tree.get(Point::new(30.0, 30.0)).is_some());
+        assert!(tree.get(Point::new(40.5, 40.5)).is_some());
+        assert!(tree.get(Point::new(5.5, 5.5)).is_some());
+    }
+
+    #[test]
+    fn test_options_auto() {
+        let boundary = Rectangle::new(0.0, 0.0, 1000.0, 1000.0);
+        let small = Options::auto(0, boundary);
+        assert_eq!((small.max_items, small.max_depth), (16, 4));
+        let large = Options::auto(1_000_000, boundary);
+        assert_eq!((large.max_items, large.max_depth), (16, 13));
+        assert!(!large.track_dirty);
+
+        let far = Options::auto(1_000_000, Rectangle::new(1e15, 1e15, 1.0, 1.0));
+        assert_eq!(far.max_depth, 0);
+        let tiny = Options::auto(1_000_000, Rectangle::new(0.0, 0.0, 1e-3, 1e-3));
+        assert_eq!(tiny.max_depth, 13);
+    }
+
+    #[test]
+    fn test_auto_tune() {
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let mut qt = Quadtree::with_options(
+            boundary,
+            Options {
+                collect_metrics: true,
+                ..Default::default()
+            },
+        );
+        for i in 0..100 {
+            for j in 0..100 {
+                qt.put(Point::new(f64::from(i), f64::from(j)));
+            }
+        }
+        let range = Rectangle::new(10.0, 20.0, 30.5, 5.5);
+        let before: Vec<Point> = qt.query(range).into_iter().copied().collect();
+        assert_eq!(qt.metrics().unwrap().max_depth, 3);
+
+        qt.auto_tune();
+        assert_eq!(qt.len(), 10_000);
+        let mut after: Vec<Point> = qt.query(range).into_iter().copied().collect();
+        let mut before = before;
+        before.sort();
+        after.sort();
+        assert_eq!(before, after);
+        assert!(qt.metrics().unwrap().max_depth > 3);
+        assert_eq!(
+            qt.remove(Point::new(50.0, 50.0)),
+            Some(Point::new(50.0, 50.0))
+        );
+        assert_eq!(qt.len(), 9_999);
+    }
+
+    #[test]
+    fn test_cursor() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                track_dirty: true,
+                ..Default::default()
+            },
+        );
+        qt.put(Point::new(10.0, 10.0));
+        qt.put(Point::new(20.0, 20.0));
+        qt.put(Point::new(90.0, 90.0));
+        qt.take_dirty_regions();
+
+        let bounds = |r: &Rectangle| (r.x(), r.y(), r.width(), r.height());
+        let mut cursor = qt.cursor();
+        assert!(!cursor.ascend());
+        assert!(cursor.descend(0));
+        assert_eq!(bounds(&cursor.bounds()), (0.0, 0.0, 50.0, 50.0));
+        assert_eq!(cursor.len(), 2);
+        assert!(cursor.descend(2));
+        assert!(cursor.is_empty());
+        assert!(cursor.ascend());
+        assert!(cursor.descend(0));
+        assert!(cursor.descend(2));
+        assert_eq!(cursor.depth(), 3);
+        assert!(cursor.is_leaf());
+        assert_eq!(cursor.items(), &[Point::new(20.0, 20.0)]);
+        assert!(!cursor.descend(0));
+        cursor.root();
+        assert!(cursor.descend(2));
+        assert_eq!(cursor.items(), &[Point::new(90.0, 90.0)]);
+        assert!(cursor.ascend());
+        assert_eq!(cursor.len(), 3);
+
+        let mut cursor = qt.cursor_mut();
+        cursor.descend(0);
+        cursor.descend(0);
+        cursor.descend(2);
+        assert_eq!(cursor.remove_item(1), None);
+        assert_eq!(cursor.remove_item(0), Some(Point::new(20.0, 20.0)));
+        assert!(cursor.is_empty());
+        cursor.ascend();
+        assert_eq!(cursor.len(), 1);
+        cursor.descend(0);
+        assert_eq!(cursor.retain(|p| p.x() > 50.0), 1);
+        cursor.root();
+        assert_eq!(cursor.len(), 1);
+
+        assert_eq!(qt.len(), 1);
+        assert_eq!(
+            qt.items_bounds().as_ref().map(bounds),
+            Some((90.0, 90.0, 0.0, 0.0))
+        );
+        let regions: Vec<_> = qt.take_dirty_regions().iter().map(bounds).collect();
+        assert_eq!(
+            regions,
+            vec![(0.0, 0.0, 12.5, 12.5), (12.5, 12.5, 12.5, 12.5)]
+        );
+    }
+
+    #[test]
+    fn test_cursor_mut_node_data() {
+        let mut qt: Quadtree<Point, u32> = Quadtree::with_node_data(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Point::new(10.0, 10.0));
+        qt.put(Point::new(90.0, 10.0));
+
+        let mut cursor = qt.cursor_mut();
+        *cursor.data_mut() = 1;
+        cursor.descend(1);
+        *cursor.data_mut() = 7;
+        assert_eq!(qt.cursor().data(), &1);
+        let mut cursor = qt.cursor();
+        cursor.descend(1);
+        assert_eq!(cursor.data(), &7);
+    }
+
+    #[test]
+    fn test_leaf_at() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        let root = qt.leaf_at(Point::new(70.0, 70.0)).unwrap();
+        assert_eq!(root.depth(), 0);
+
+        qt.put(Point::new(10.0, 10.0));
+        qt.put(Point::new(20.0, 20.0));
+        let bounds = |r: Rectangle| (r.x(), r.y(), r.width(), r.height());
+
+        let deep = qt.leaf_at(Point::new(20.0, 20.0)).unwrap();
+        assert_eq!(deep.depth(), 3);
+        assert_eq!(
+            qt.node_bounds(deep).map(bounds),
+            Some((12.5, 12.5, 12.5, 12.5))
+        );
+        let coarse = qt.leaf_at(Point::new(70.0, 70.0)).unwrap();
+        assert_eq!(coarse.depth(), 1);
+        assert_eq!(
+            qt.node_bounds(coarse).map(bounds),
+            Some((50.0, 50.0, 50.0, 50.0))
+        );
+        assert!(qt.leaf_at(Point::new(-1.0, 50.0)).is_none());
+    }
+
+    #[test]
+    fn test_cell_key_of() {
+        let mut qt = KeyedQuadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        let a = qt.insert(Point::new(10.0, 10.0), "a").unwrap();
+        let b = qt.insert(Point::new(20.0, 20.0), "b").unwrap();
+        let c = qt.insert(Point::new(90.0, 10.0), "c").unwrap();
+
+        assert_eq!(qt.path_to(a), Some(vec![0, 0, 0]));
+        assert_eq!(qt.path_to(b), Some(vec![0, 0, 2]));
+        assert_eq!(qt.path_to(c), Some(vec![1]));
+        let key = qt.cell_key_of(b).unwrap();
+        assert_eq!(key.depth(), 3);
+        assert_eq!(qt.cell_key_at(Point::new(24.0, 13.0)), Some(key));
+        assert_ne!(qt.cell_key_at(Point::new(26.0, 13.0)), Some(key));
+        assert!(qt.cell_key_at(Point::new(101.0, 13.0)).is_none());
+
+        qt.remove_by_id(b);
+        assert!(qt.cell_key_of(b).is_none());
+        assert!(qt.path_to(b).is_none());
+    }
+
+    #[test]
+    fn test_region_subscriptions() {
+        let mut qt = KeyedQuadtree::new(Rectangle::new(0.0, 0.0, 100.0, 100.0));
+        let west = qt.subscribe(Rectangle::new(0.0, 0.0, 50.0, 100.0));
+        let corner = qt.subscribe(Rectangle::new(40.0, 40.0, 20.0, 20.0));
+
+        let a = qt.insert(Point::new(10.0, 10.0), "a").unwrap();
+        let b = qt.insert(Point::new(45.0, 45.0), "b").unwrap();
+        let c = qt.insert(Point::new(90.0, 90.0), "c").unwrap();
+        assert_eq!(
+            qt.take_events(),
+            vec![
+                (west, RegionEvent::Entered(a)),
+                (west, RegionEvent::Entered(b)),
+                (corner, RegionEvent::Entered(b)),
+            ]
+        );
+
+        qt.apply_moves(vec![
+            (a, Point::new(20.0, 20.0)),
+            (b, Point::new(55.0, 45.0)),
+            (c, Point::new(80.0, 80.0)),
+        ]);
+        let mut events = qt.take_events();
+        events.sort_by_key(|&(id, _)| id != west);
+        assert_eq!(
+            events,
+            vec![
+                (west, RegionEvent::Moved(a)),
+                (west, RegionEvent::Left(b)),
+                (corner, RegionEvent::Moved(b)),
+            ]
+        );
+
+        assert!(qt.unsubscribe(west));
+        assert!(!qt.unsubscribe(west));
+        qt.remove_by_id(b);
+        assert_eq!(qt.take_events(), vec![(corner, RegionEvent::Left(b))]);
+        assert!(qt.take_events().is_empty());
+    }
+
+    #[test]
+    fn test_query_layers() {
+        #[derive(Debug, PartialEq)]
+        struct Body {
+            point: Point,
+            layers: u32,
+        }
+
+        impl Position for Body {
+            fn position(&self) -> Point {
+                self.point
+            }
+        }
+
+        impl Layered for Body {
+            fn layers(&self) -> u32 {
+                self.layers
+            }
+        }
+
+        const PLAYER: u32 = 1;
+        const ENEMY: u32 = 2;
+        const PICKUP: u32 = 4;
+
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let options = Options {
+            max_items: 2,
+            ..Default::default()
+        };
+        let mut plain = Quadtree::with_options(boundary, options);
+        let mut pruned = AggregateQuadtree::with_options(boundary, options, Layers);
+        for i in 0..30 {
+            let layers = match i % 3 {
+                0 => PLAYER,
+                1 => ENEMY,
+                _ => ENEMY | PICKUP,
+            };
+            let point = Point::new(f64::from(i * 3), f64::from(i * 3));
+            plain.put(Body { point, layers });
+            pruned.put(Body { point, layers });
+        }
+
+        let range = Rectangle::new(0.0, 0.0, 30.0, 30.0);
+        let x = |bodies: Vec<&Body>| bodies.iter().map(|b| b.point.x()).collect::<Vec<_>>();
+        assert_eq!(
+            x(plain.query_layers(range, PLAYER)),
+            vec![0.0, 9.0, 18.0, 27.0]
+        );
+        assert_eq!(x(plain.query_layers(range, PICKUP)), vec![6.0, 15.0, 24.0]);
+        assert_eq!(plain.query_layers(range, ENEMY).len(), 7);
+        assert!(plain.query_layers(range, 8).is_empty());
+
+        for mask in [PLAYER, ENEMY, PICKUP, PLAYER | PICKUP, 8] {
+            for range in [range, boundary, Rectangle::new(40.0, 10.0, 50.0, 80.0)] {
+                let mut expected = x(plain.query_layers(range, mask));
+                let mut found = x(pruned.query_layers(range, mask));
+                expected.sort_by(f64::total_cmp);
+                found.sort_by(f64::total_cmp);
+                assert_eq!(found, expected);
+            }
+        }
+        assert_eq!(pruned.aggregate(), PLAYER | ENEMY | PICKUP);
+    }
+
+    #[test]
+    fn test_query_ordered() {
+        #[derive(Debug, PartialEq)]
+        struct Sprite {
+            point: Point,
+            z: f64,
+        }
+
+        impl Position for Sprite {
+            fn position(&self) -> Point {
+                self.point
+            }
+        }
+
+        impl Priority for Sprite {
+            fn priority(&self) -> f64 {
+                self.z
+            }
+        }
+
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let options = Options {
+            max_items: 2,
+            ..Default::default()
+        };
+        let mut plain = Quadtree::with_options(boundary, options);
+        let mut ordered = AggregateQuadtree::with_options(boundary, options, Max(|s: &Sprite| s.z));
+        for i in 0..40 {
+            let point = Point::new(f64::from(i % 8) * 12.0, f64::from(i / 8) * 20.0);
+            let z = f64::from(i * 17 % 23);
+            plain.put(Sprite { point, z });
+            ordered.put(Sprite { point, z });
+        }
+
+        let range = Rectangle::new(10.0, 10.0, 50.0, 50.0);
+        let z = |sprites: Vec<&Sprite>| sprites.iter().map(|s| s.z).collect::<Vec<_>>();
+        let expected = z(plain.query_ordered(range));
+        assert_eq!(expected.len(), 15);
+        assert!(expected.windows(2).all(|w| w[0] >= w[1]));
+        assert_eq!(z(ordered.query_ordered(range).collect()), expected);
+
+        let top = ordered
+            .query_ordered(Rectangle::new(0.0, 0.0, 100.0, 100.0))
+            .next()
+            .unwrap();
+        assert_eq!(top.z, 22.0);
+        let picked = ordered
+            .query_ordered(Rectangle::new(24.0, 20.0, 0.0, 0.0))
+            .next();
+        assert_eq!(picked.map(|s| s.point), Some(Point::new(24.0, 20.0)));
+        assert!(ordered
+            .query_ordered(Rectangle::new(200.0, 0.0, 5.0, 5.0))
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn test_prune_expired() {
+        #[derive(Debug, PartialEq)]
+        struct Ping {
+            point: Point,
+            until: f64,
+        }
+
+        impl Position for Ping {
+            fn position(&self) -> Point {
+                self.point
+            }
+        }
+
+        impl Expiring for Ping {
+            fn expires_at(&self) -> f64 {
+                self.until
+            }
+        }
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 2,
+                ..Default::default()
+            },
+        );
+        for i in 0..20 {
+            qt.put(Ping {
+                point: Point::new(f64::from(i * 5), f64::from(i * 5)),
+                until: f64::from(i % 4) * 10.0,
+            });
+        }
+        let all = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        assert_eq!(qt.query_live(all, 0.0).len(), 15);
+        assert_eq!(
+            qt.query_live(Rectangle::new(0.0, 0.0, 20.0, 20.0), 15.0)
+                .len(),
+            2
+        );
+
+        let expired = qt.prune_expired(10.0);
+        assert_eq!(expired.len(), 10);
+        assert!(expired.iter().all(|ping| ping.until <= 10.0));
+        assert_eq!(qt.len(), 10);
+        assert_eq!(qt.query(all).len(), 10);
+        assert!(qt.prune_expired(10.0).is_empty());
+
+        assert_eq!(qt.prune_expired(30.0).len(), 10);
+        assert!(qt.is_empty());
+        assert!(qt.items_bounds().is_none());
+    }
+
+    #[test]
+    fn test_max_total_items_evicts_oldest_first() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 2,
+                max_total_items: 3,
+                ..Default::default()
+            },
+        );
+        for i in 0..5 {
+            qt.put(Point::new(f64::from(i * 10), f64::from(i * 10)));
+        }
+        assert_eq!(qt.len(), 3);
+        assert!(qt.get(Point::new(0.0, 0.0)).is_none());
+        assert!(qt.get(Point::new(10.0, 10.0)).is_none());
+        assert!(qt.get(Point::new(40.0, 40.0)).is_some());
+
+        qt.remove(Point::new(20.0, 20.0));
+        qt.extend_streaming((5..8).map(|i| Point::new(f64::from(i * 10), 5.0)), 2);
+        assert_eq!(qt.len(), 3);
+        let mut kept: Vec<Point> = qt.iter().copied().collect();
+        kept.sort();
+        assert_eq!(
+            kept,
+            vec![
+                Point::new(50.0, 5.0),
+                Point::new(60.0, 5.0),
+                Point::new(70.0, 5.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_max_total_items_evicts_farthest_from_focus() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 2,
+                max_total_items: 4,
+                ..Default::default()
+            },
+        );
+        for i in 0..6 {
+            qt.put(Point::new(f64::from(i * 15), 50.0));
+        }
+        qt.set_eviction_policy(Box::new(FarthestFrom::new(Point::new(100.0, 50.0))));
+        qt.put(Point::new(90.0, 50.0));
+        assert_eq!(qt.len(), 4);
+        let mut kept: Vec<f64> = qt.iter().map(|p| p.x()).collect();
+        kept.sort_by(f64::total_cmp);
+        assert_eq!(kept, vec![45.0, 60.0, 75.0, 90.0]);
+
+        assert!(qt.take_eviction_policy().is_some());
+        qt.put(Point::new(10.0, 10.0));
+        assert_eq!(qt.len(), 5);
+    }
+
+    #[test]
+    fn test_oldest_first_tracks_items_at_the_same_position() {
+        struct Tagged(Point, &'static str);
+        impl Position for Tagged {
+            fn position(&self) -> Point {
+                self.0
+            }
+        }
+
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_total_items: 2,
+                ..Default::default()
+            },
+        );
+        qt.put(Tagged(Point::new(10.0, 10.0), "old"));
+        qt.put(Tagged(Point::new(50.0, 50.0), "kept"));
+        assert_eq!(qt.remove(Point::new(10.0, 10.0)).unwrap().1, "old");
+        qt.put(Tagged(Point::new(10.0, 10.0), "new"));
+
+        let evicted = qt.put_evicting(Tagged(Point::new(90.0, 90.0), "newest"));
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].1, "kept");
+        assert_eq!(qt.get(Point::new(10.0, 10.0)).unwrap().1, "new");
+
+        qt.cursor_mut().retain(|item| item.1 != "new");
+        qt.put(Tagged(Point::new(20.0, 20.0), "last"));
+        assert_eq!(qt.len(), 2);
+        assert!(qt.get(Point::new(90.0, 90.0)).is_some());
+    }
+
+    #[test]
+    fn test_item_cost_subdivides_sooner() {
+        struct Shape {
+            at: Point,
+            vertices: usize,
+        }
+
+        impl Position for Shape {
+            fn position(&self) -> Point {
+                self.at
+            }
+
+            fn cost(&self) -> usize {
+                self.vertices
+            }
+        }
+
+        let options = Options {
+            max_items: 8,
+            ..Default::default()
+        };
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let mut light = Quadtree::with_options(boundary, options);
+        let mut heavy = Quadtree::with_options(boundary, options);
+        for i in 0..4 {
+            let at = Point::new(f64::from(i * 20 + 10), 50.0);
+            light.put(Shape { at, vertices: 1 });
+            heavy.put(Shape { at, vertices: 3 });
+        }
+        assert!(light.cursor().is_leaf());
+        assert!(!heavy.cursor().is_leaf());
+        assert_eq!(heavy.query(boundary).len(), 4);
+
+        heavy.remove(Point::new(10.0, 50.0));
+        assert!(!heavy.cursor().is_leaf());
+        heavy.remove(Point::new(30.0, 50.0));
+        assert!(heavy.cursor().is_leaf());
+        assert_eq!(heavy.len(), 2);
+    }
+
+    #[test]
+    fn test_explain_query() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 200.0, 200.0),
+            Options {
+                max_items: 1,
+                collect_metrics: true,
+                ..Default::default()
+            },
+        );
+        qt.put(Point::new(10.0, 10.0));
+        qt.put(Point::new(20.0, 20.0));
+        qt.put(Point::new(150.0, 150.0));
+
+        let range = Rectangle::new(140.0, 140.0, 20.0, 20.0);
+        let trace = qt.explain_query(range);
+        assert_eq!(trace.nodes_visited, 5);
+        assert_eq!(trace.nodes_pruned, 1);
+        assert_eq!(trace.items_scanned, 1);
+        assert_eq!(trace.items_matched, qt.query(range).len());
+        assert_eq!(qt.metrics().unwrap().queries, 1);
+        assert_eq!(
+            trace.to_string(),
+            "query (140, 140, 160, 160): 5 nodes visited, 1 pruned, 1 items scanned, 1 matched"
+        );
+    }
+
+    #[test]
+    fn test_estimate_count_in_region() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 4,
+                max_depth: 6,
+                ..Default::default()
+            },
+        );
+        for i in 0..50 {
+            for j in 0..50 {
+                qt.put(Point::new(
+                    f64::from(i) * 2.0 + 1.0,
+                    f64::from(j) * 2.0 + 1.0,
+                ));
+            }
+        }
+
+        let range = Rectangle::new(10.0, 15.0, 47.0, 33.0);
+        let exact = qt.query(range).len() as f64;
+        assert_eq!(qt.estimate_count_in_region(range, 0.0).count, exact);
+
+        for &max_error in &[10.0, 100.0, 1000.0] {
+            let estimate = qt.estimate_count_in_region(range, max_error);
+            assert!(estimate.error <= max_error);
+            assert!((estimate.count - exact).abs() <= estimate.error);
+        }
+        let everything = qt.estimate_count_in_region(Rectangle::new(0.0, 0.0, 100.0, 100.0), 5.0);
+        assert_eq!(everything.count, 2500.0);
+        assert_eq!(everything.error, 0.0);
+    }
+
+    #[test]
+    fn test_to_kml() {
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(-180.0, -90.0, 360.0, 180.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Item::new(Point::new(-0.1276, 51.5072), &"London"));
+        qt.put(Item::new(Point::new(151.2093, -33.8688), &"Sydney & co"));
+
+        let kml = qt.to_kml(false);
+        assert!(kml.starts_with("<?xml"));
+        assert!(kml.ends_with("</kml>\n"));
+        assert_eq!(kml.matches("<Placemark>").count(), 2);
+        assert!(kml.contains("<coordinates>-0.1276,51.5072</coordinates>"));
+        assert!(!kml.contains("<Polygon>"));
+
+        let kml = qt.to_kml_with(true, |item| item.to_string());
+        assert!(kml.contains("<name>Sydney &amp; co</name>"));
+        assert_eq!(kml.matches("<Polygon>").count(), 5);
+        assert!(kml.contains("-180,-90 180,-90 180,90 -180,90 -180,-90"));
+    }
+
+    #[test]
+    fn test_pop_in_region() {
+        let jobs = ["north", "middle", "middle again", "far"];
+        let mut qt = Quadtree::with_options(
+            Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            Options {
+                max_items: 1,
+                ..Default::default()
+            },
+        );
+        qt.put(Item::new(Point::new(20.0, 5.0), &jobs[0]));
+        qt.put(Item::new(Point::new(22.0, 18.0), &jobs[1]));
+        qt.put(Item::new(Point::new(22.0, 18.0), &jobs[2]));
+        qt.put(Item::new(Point::new(90.0, 90.0), &jobs[3]));
+
+        let region = Rectangle::new(0.0, 0.0, 40.0, 40.0);
+        let mut popped = Vec::new();
+        while let Some(item) = qt.pop_in_region(region) {
+            popped.push(*item);
+        }
+        assert_eq!(popped, vec!["middle", "middle again", "north"]);
+        assert_eq!(qt.len(), 1);
+        assert!(qt.pop_in_region(region).is_none());
+    }
+
+    #[test]
+    fn test_precision_report() {
+        let qt: Quadtree<Point> = Quadtree::new(Rectangle::new(0.0, 0.0, 1.0, 1.0));
+        let report = qt.precision_report();
+        assert_eq!(report.ulp, f64::EPSILON);
+        assert_eq!(report.max_exact_depth, 52);
+        assert_eq!(report.smallest_cell.width(), f64::EPSILON);
+        assert!(!report.lossy);
+
+        let far: Quadtree<Point> = Quadtree::with_options(
+            Rectangle::new(1.0e15, 1.0e15, 1024.0, 1024.0),
+            Options {
+                max_depth: 16,
+                ..Default::default()
+            },
+        );
+        let report = far.precision_report();
+        assert_eq!(report.ulp, 0.125);
+        assert_eq!(report.max_exact_depth, 13);
+        assert!(report.lossy);
+        assert_eq!(
+            report.to_string(),
+            "ulp 1.25e-1, exact to depth 13 (cells 1.25e-1 x 1.25e-1), max_depth is lossy"
+        );
+    }
+
+    #[cfg(feature = "proptest")]
+    mod properties {
+        use proptest::collection::vec;
+        use proptest::prelude::*;
+        use quadtree::*;
+
+        proptest! {
+            #[test]
+            fn test_arbitrary_tree_holds_its_items(tree in any::<Quadtree<Point>>()) {
+                let boundary = tree.items_bounds().unwrap_or_else(|| Rectangle::new(0.0, 0.0, 1.0, 1.0));
+                prop_assert_eq!(tree.query(boundary.inflated(1.0)).len(), tree.len());
+            }
+
+            #[test]
+            fn test_query_matches_naive_index(
+                options in any::<Options>(),
+                points in vec((0.0..100.0, 0.0..100.0), 0..200),
+                range in (0.0..100.0, 0.0..100.0, 0.0..60.0, 0.0..60.0)
+                    .prop_map(|(x, y, w, h)| Rectangle::new(x, y, w, h)),
+                probe in (0.0..100.0, 0.0..100.0),
+            ) {
+                let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+                let mut tree = Quadtree::with_options(boundary, options);
+                let mut model = NaiveIndex::new(boundary);
+                for &(x, y) in &points {
+                    tree.put(Point::new(x, y));
+                    model.put(Point::new(x, y));
+                }
+
+                let mut found = tree.query(range);
+                let mut expected = model.query(range);
+                found.sort();
+                expected.sort();
+                prop_assert_eq!(found, expected);
+
+                let probe = Point::new(probe.0, probe.1);
+                let distance = |p: Option<&Point>| p.map(|p| (p.x() - probe.x()).hypot(p.y() - probe.y()));
+                prop_assert_eq!(distance(tree.nearest(probe)), distance(model.nearest(probe)));
+            }
+        }
+    }
 }
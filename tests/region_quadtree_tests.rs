@@ -0,0 +1,171 @@
+mod region_quadtree_tests {
+    use quadtree::*;
+
+    fn grid(rows: &[&str]) -> (Vec<bool>, u32, u32) {
+        let cells = rows
+            .iter()
+            .flat_map(|row| row.chars().map(|c| c == '#'))
+            .collect();
+        (cells, rows[0].len() as u32, rows.len() as u32)
+    }
+
+    #[test]
+    fn test_from_grid_merges_homogeneous_blocks() {
+        let (cells, width, height) = grid(&[
+            "####....", //
+            "####....", //
+            "####....", //
+            "####...#", //
+            "........", //
+            "........", //
+        ]);
+        let region = RegionQuadtree::from_grid(&cells, width, height);
+        assert_eq!((region.width(), region.height()), (8, 6));
+        assert_eq!(region.to_grid(), cells);
+        assert!(region.get(0, 0));
+        assert!(region.get(7, 3));
+        assert!(!region.get(8, 3));
+        assert!(region.is_occupied(Point::new(3.5, 3.9)));
+        assert!(!region.is_occupied(Point::new(4.0, 3.9)));
+
+        // The occupied and the two free 4x4 blocks, plus the north-east block
+        // split down to the single occupied cell.
+        assert_eq!(region.leaf_count(), 3 + 3 + 4);
+        let occupied: f64 = region
+            .leaves()
+            .iter()
+            .filter(|(_, occupied)| *occupied)
+            .map(|(cell, _)| cell.width() * cell.height())
+            .sum();
+        assert_eq!(occupied, 17.0);
+    }
+
+    #[test]
+    fn test_from_grid_uniform() {
+        let region = RegionQuadtree::from_grid(&[true; 16], 4, 4);
+        assert_eq!(region.leaf_count(), 1);
+        let region = RegionQuadtree::from_grid(&[], 0, 0);
+        assert!(region.to_grid().is_empty());
+    }
+
+    fn corners(contour: &[Point]) -> Vec<(f64, f64)> {
+        contour.iter().map(|p| (p.x(), p.y())).collect()
+    }
+
+    #[test]
+    fn test_contours() {
+        let (cells, width, height) = grid(&[
+            "......", //
+            ".##...", //
+            ".##...", //
+            "...#..", //
+            "......", //
+        ]);
+        let region = RegionQuadtree::from_grid(&cells, width, height);
+        let contours = region.contours();
+        assert_eq!(contours.len(), 2);
+        assert_eq!(
+            corners(&contours[0]),
+            vec![(3.0, 1.0), (3.0, 3.0), (1.0, 3.0), (1.0, 1.0)]
+        );
+        assert_eq!(
+            corners(&contours[1]),
+            vec![(4.0, 3.0), (4.0, 4.0), (3.0, 4.0), (3.0, 3.0)]
+        );
+
+        let (cells, width, height) = grid(&[
+            "###", //
+            "#.#", //
+            "###", //
+        ]);
+        let contours = RegionQuadtree::from_grid(&cells, width, height).contours();
+        assert_eq!(contours.len(), 2);
+        assert_eq!(
+            corners(&contours[0]),
+            vec![(3.0, 0.0), (3.0, 3.0), (0.0, 3.0), (0.0, 0.0)]
+        );
+        assert_eq!(
+            corners(&contours[1]),
+            vec![(1.0, 2.0), (2.0, 2.0), (2.0, 1.0), (1.0, 1.0)]
+        );
+
+        assert!(RegionQuadtree::from_grid(&[false; 4], 2, 2)
+            .contours()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_flood_fill() {
+        let (cells, width, height) = grid(&[
+            "..#....", //
+            "..#....", //
+            "###....", //
+            "....#..", //
+            "....#..", //
+        ]);
+        let mut region = RegionQuadtree::from_grid(&cells, width, height);
+
+        assert_eq!(region.flood_fill(Point::new(0.5, 0.5), true), 4);
+        let (expected, _, _) = grid(&[
+            "###....", //
+            "###....", //
+            "###....", //
+            "....#..", //
+            "....#..", //
+        ]);
+        assert_eq!(region.to_grid(), expected);
+
+        assert_eq!(region.flood_fill(Point::new(6.0, 4.0), true), 24);
+        assert_eq!(region.to_grid(), vec![true; 35]);
+        assert_eq!(
+            region,
+            RegionQuadtree::from_grid(&[true; 35], width, height)
+        );
+
+        assert_eq!(region.flood_fill(Point::new(2.0, 2.0), true), 0);
+        assert_eq!(region.flood_fill(Point::new(7.5, 1.0), false), 0);
+        assert_eq!(region.flood_fill(Point::new(3.0, 3.0), false), 35);
+        assert_eq!(
+            region,
+            RegionQuadtree::from_grid(&[false; 35], width, height)
+        );
+    }
+
+    #[test]
+    fn test_boolean_operations() {
+        let (a, width, height) = grid(&[
+            "####..", //
+            "####..", //
+            "####..", //
+            "......", //
+            "......", //
+        ]);
+        let (b, _, _) = grid(&[
+            "......", //
+            "..##..", //
+            "..####", //
+            "..####", //
+            "......", //
+        ]);
+        let ta = RegionQuadtree::from_grid(&a, width, height);
+        let tb = RegionQuadtree::from_grid(&b, width, height);
+
+        let cellwise = |op: fn(bool, bool) -> bool| {
+            let cells: Vec<bool> = a.iter().zip(&b).map(|(&x, &y)| op(x, y)).collect();
+            RegionQuadtree::from_grid(&cells, width, height)
+        };
+        assert_eq!(ta.union(&tb), cellwise(|x, y| x || y));
+        assert_eq!(ta.intersection(&tb), cellwise(|x, y| x && y));
+        assert_eq!(ta.subtract(&tb), cellwise(|x, y| x && !y));
+        assert_eq!(tb.subtract(&ta), cellwise(|x, y| y && !x));
+        assert_eq!(ta.subtract(&ta).leaf_count(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "same grid")]
+    fn test_boolean_operations_need_same_grid() {
+        let a = RegionQuadtree::from_grid(&[true; 4], 2, 2);
+        let b = RegionQuadtree::from_grid(&[true; 6], 3, 2);
+        a.union(&b);
+    }
+}
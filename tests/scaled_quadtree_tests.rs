@@ -0,0 +1,47 @@
+mod scaled_quadtree_tests {
+    use quadtree::*;
+
+    #[test]
+    fn test_deep_subdivision_far_from_origin() {
+        let world = Rectangle::new(1.0e12, 1.0e12, 1.0, 1.0);
+        let options = Options {
+            max_items: 1,
+            max_depth: 40,
+            ..Default::default()
+        };
+        assert!(
+            Quadtree::<Point>::with_options(world, options)
+                .precision_report()
+                .lossy
+        );
+
+        let resolution = 1.0 / 1024.0;
+        let options = IntOptions {
+            max_items: options.max_items,
+            max_depth: options.max_depth,
+            ..Default::default()
+        };
+        let mut qt = ScaledQuadtree::with_options(world, resolution, options);
+        for i in 0..16 {
+            qt.put(Point::new(1.0e12 + f64::from(i) / 16.0, 1.0e12 + 0.5));
+        }
+        qt.put(Point::new(0.0, 0.0));
+        assert_eq!(qt.len(), 16);
+
+        let found = qt.query(Rectangle::new(1.0e12 + 0.25, 1.0e12, 0.25, 1.0));
+        let mut xs: Vec<f64> = found.iter().map(|p| p.x() - 1.0e12).collect();
+        xs.sort_by(f64::total_cmp);
+        assert_eq!(xs, vec![0.25, 0.3125, 0.375, 0.4375, 0.5]);
+        assert!(qt.query(Rectangle::new(0.0, 0.0, 10.0, 10.0)).is_empty());
+
+        let lattice = qt.to_lattice(Point::new(1.0e12 + 0.5, 1.0e12 + 0.25));
+        assert_eq!(lattice, IntPoint::new(512, 256));
+        assert_eq!(
+            qt.to_world(lattice),
+            Point::new(1.0e12 + 0.5, 1.0e12 + 0.25)
+        );
+
+        qt.clear();
+        assert!(qt.is_empty());
+    }
+}
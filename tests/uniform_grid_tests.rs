@@ -0,0 +1,112 @@
+mod uniform_grid_tests {
+    use quadtree::*;
+
+    #[test]
+    fn test_query_matches_plain_quadtree() {
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let mut grid = UniformGrid::new(boundary, 7.0);
+        let mut plain = Quadtree::new(boundary);
+        for i in 0..30 {
+            for j in 0..30 {
+                let point = Point::new(f64::from(i) * 3.3 + 0.2, f64::from(j) * 3.3 + 0.5);
+                grid.put(point);
+                plain.put(point);
+            }
+        }
+        grid.put(Point::new(-1.0, 50.0));
+        assert_eq!(grid.len(), 900);
+
+        let sorted = |items: Vec<&Point>| {
+            let mut items: Vec<Point> = items.into_iter().copied().collect();
+            items.sort();
+            items
+        };
+        for range in [
+            Rectangle::new(10.0, 20.0, 30.0, 5.0),
+            Rectangle::new(-5.0, -5.0, 12.0, 120.0),
+            Rectangle::new(99.0, 99.0, 10.0, 10.0),
+            boundary,
+        ] {
+            assert_eq!(sorted(grid.query(range)), sorted(plain.query(range)));
+        }
+    }
+
+    #[test]
+    fn test_nearest_searches_outward() {
+        let mut grid = UniformGrid::new(Rectangle::new(0.0, 0.0, 100.0, 50.0), 10.0);
+        assert!(grid.nearest(Point::new(5.0, 5.0)).is_none());
+        grid.put(Point::new(95.0, 45.0));
+        grid.put(Point::new(19.5, 1.0));
+        grid.put(Point::new(1.0, 12.0));
+
+        assert_eq!(
+            grid.nearest(Point::new(5.0, 5.0)),
+            Some(&Point::new(1.0, 12.0))
+        );
+        assert_eq!(
+            grid.nearest(Point::new(11.0, 1.0)),
+            Some(&Point::new(19.5, 1.0))
+        );
+        assert_eq!(
+            grid.nearest(Point::new(70.0, 30.0)),
+            Some(&Point::new(95.0, 45.0))
+        );
+
+        assert_eq!(
+            grid.remove(Point::new(19.5, 1.0)),
+            Some(Point::new(19.5, 1.0))
+        );
+        assert_eq!(
+            grid.nearest(Point::new(11.0, 1.0)),
+            Some(&Point::new(1.0, 12.0))
+        );
+        grid.clear();
+        assert!(grid.is_empty());
+    }
+
+    #[test]
+    fn test_remove_keeps_cell_order() {
+        struct Tagged(Point, u32);
+        impl Position for Tagged {
+            fn position(&self) -> Point {
+                self.0
+            }
+        }
+
+        let mut grid = UniformGrid::new(Rectangle::new(0.0, 0.0, 10.0, 10.0), 10.0);
+        for tag in 0..4 {
+            grid.put(Tagged(Point::new(f64::from(tag), 1.0), tag));
+        }
+        assert_eq!(grid.remove(Point::new(0.0, 1.0)).map(|t| t.1), Some(0));
+        let tags: Vec<u32> = grid
+            .query(Rectangle::new(0.0, 0.0, 10.0, 10.0))
+            .iter()
+            .map(|t| t.1)
+            .collect();
+        assert_eq!(tags, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_nearest_matches_plain_quadtree() {
+        let boundary = Rectangle::new(0.0, 0.0, 100.0, 60.0);
+        let mut grid = UniformGrid::new(boundary, 4.0);
+        let mut plain = Quadtree::new(boundary);
+        for i in 0..40 {
+            let point = Point::new(f64::from(i * 37 % 100), f64::from(i * 23 % 60));
+            grid.put(point);
+            plain.put(point);
+        }
+        for i in 0..50 {
+            let probe = Point::new(f64::from(i * 13 % 100) + 0.5, f64::from(i * 7 % 60) + 0.25);
+            let (found, expected) = (grid.nearest(probe).unwrap(), plain.nearest(probe).unwrap());
+            let distance = |p: &Point| (p.x() - probe.x()).hypot(p.y() - probe.y());
+            assert_eq!(distance(found), distance(expected));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "cell size")]
+    fn test_rejects_empty_cells() {
+        UniformGrid::<Point>::new(Rectangle::new(0.0, 0.0, 1.0, 1.0), 0.0);
+    }
+}